@@ -36,9 +36,51 @@
 //! let resolver = VadeEvanSubstrate::new(ResolverConfig {
 //!     signer,
 //!     target: "127.0.0.1".to_string(),
+//!     endpoints: None,
+//!     content_store: None,
+//!     extrinsic_signer: None,
+//!     timeouts: None,
+//!     retry_policy: None,
+//!     resolution_result: false,
+//!     block_metadata: false,
 //! });
 //! ```
 //!
+//! `target` is a shorthand for a node that serves its HTTP RPC at `https://{target}/rpc`, its websocket RPC at `wss://{target}/ws` and an IPFS gateway at `https://{target}/ipfs`. Other setups, e.g. a local development node, can be configured with `endpoints`, which takes precedence over `target`:
+//!
+//! ```rust
+//! use vade_evan_substrate::{IpfsApi, ResolverConfig, SubstrateEndpoints, VadeEvanSubstrate};
+//! use vade_signer::{LocalSigner, Signer};
+//! let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+//! let resolver = VadeEvanSubstrate::new(ResolverConfig {
+//!     signer,
+//!     target: "127.0.0.1".to_string(),
+//!     endpoints: Some(SubstrateEndpoints {
+//!         http_rpc: "http://127.0.0.1:9933".to_string(),
+//!         ws_rpc: "ws://127.0.0.1:9944".to_string(),
+//!         ipfs: "http://127.0.0.1:8080/ipfs".to_string(),
+//!     }),
+//!     content_store: Some(Box::new(IpfsApi::new("http://127.0.0.1:5001"))),
+//!     extrinsic_signer: None,
+//!     timeouts: None,
+//!     retry_policy: None,
+//!     resolution_result: false,
+//!     block_metadata: false,
+//! });
+//! ```
+//!
+//! DID documents are fetched from `content_store`, which defaults to the IPFS gateway of the endpoints. Custom stores can be used by implementing the `ContentStore` trait. Fetched documents are only returned if they match the CID stored on chain.
+//!
+//! With `resolution_result` set, `did_resolve` returns a W3C DID Resolution Result with the DID document, its `versionId` and whether it has been deactivated, instead of the plain DID document. With `block_metadata` set as well, the blocks the DID has been created and updated in are added. They are found with a binary search over past blocks, which requires a node, that keeps the state of these blocks, e.g. an archive node.
+//!
+//! The `setDidDocument` operation of `did_update` overwrites the current document of a DID, which is the one with the highest index, i.e. the number of its documents - 1. With `updateMode` set to `append` in its options, the new document is added at the next index instead, so previous documents stay resolvable by their `versionId`. `did_resolve` returns the document with the highest index.
+//!
+//! DIDs are deactivated with the `deactivateDid` operation of `did_update`. `DidModule` has no call to deactivate DIDs, so this is a convention of this plugin: a tombstone document like `{"type":"vade-evan-substrate/DidTombstone","did":"0x1234"}` is appended as latest version of the DID. Resolving the DID fails with `SubstrateError::DidDeactivated` afterwards, or reports it as deactivated in DID Resolution Results, while previous versions can still be resolved by their `versionId`. Only documents with exactly these properties and the DID itself count as tombstones, documents with this `type` cannot be set with `setDidDocument`. Resolvers, that do not follow this convention, return the tombstone as DID document.
+//!
+//! With `extrinsic_signer` set, extrinsics are sent as signed extrinsics of this substrate account, e.g. an `sr25519::Pair`, `ed25519::Pair` or `ecdsa::Pair`. Otherwise DID module calls are sent unsigned and are only authorized by the signature of the identity. Nonces of identities are derived from the account nonce of this account, or from the block time for unsigned calls.
+//!
+//! `timeouts` sets how long RPC requests, waits for events and fetches of DID documents may take, each defaults to 60 seconds. Storage and metadata requests, that fail with transport errors or timeouts, are retried according to `retry_policy`, which defaults to 3 retries with a backoff starting at 500 ms.
+//!
 //! When signing remotely, `signing_url` will be called with a POST request. The messages that should be signed is passed to the server alongside a reference to a key like this:
 //!
 //! ```json
@@ -105,9 +147,6 @@
 #[macro_use]
 pub extern crate log;
 
-#[cfg(target_arch = "wasm32")]
-extern crate instant;
-
 mod utils;
 
 // did
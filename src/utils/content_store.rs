@@ -0,0 +1,272 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+use crate::utils::errors::SubstrateError;
+use async_trait::async_trait;
+use multibase::Base;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+
+/// multicodec code of raw binary content
+const CODEC_RAW: u64 = 0x55;
+/// multicodec code of merkle dag protobuf nodes
+const CODEC_DAG_PB: u64 = 0x70;
+/// multihash code of sha2-256
+const HASH_SHA2_256: u64 = 0x12;
+/// default chunk size of IPFS, larger content is split into multiple blocks
+const MAX_SINGLE_BLOCK_SIZE: usize = 262_144;
+/// UnixFS data type for files
+const UNIXFS_FILE: u64 = 2;
+
+/// Storage that content referenced by DIDs can be fetched from.
+#[async_trait(?Send)]
+pub trait ContentStore {
+    /// Fetches content for given CID. Implementations do not need to verify the content, this is
+    /// done by the caller with [`verify_cid`](verify_cid).
+    ///
+    /// # Arguments
+    /// * `cid` - content identifier, e.g. `QmWATWQ7fVPP2EFGu71UkfnqhYXDYH566qy47CnJDgvs8u`
+    ///
+    /// # Returns
+    /// * `Vec<u8>` - content as fetched from store
+    async fn get(&self, cid: &str) -> Result<Vec<u8>, Box<dyn Error>>;
+}
+
+/// Fetches content from an IPFS HTTP gateway, e.g. `https://ipfs.io/ipfs`.
+pub struct IpfsGateway {
+    /// Base URL of gateway, content is fetched from `{url}/{cid}`
+    pub url: String,
+    /// HTTP client for requests to the gateway, reused so connections are pooled
+    pub http_client: reqwest::Client,
+}
+
+impl IpfsGateway {
+    pub fn new(url: &str) -> IpfsGateway {
+        IpfsGateway {
+            url: url.to_string(),
+            http_client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl ContentStore for IpfsGateway {
+    async fn get(&self, cid: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let url = format!("{}/{}", self.url.trim_end_matches('/'), cid);
+        trace!("fetching content from gateway at: {}", &url);
+        let response = self
+            .http_client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| SubstrateError::Transport(err.to_string()))?;
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| SubstrateError::Transport(err.to_string()))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Fetches content from the HTTP API of an IPFS node, e.g. `http://127.0.0.1:5001`.
+pub struct IpfsApi {
+    /// Base URL of API, content is fetched from `{url}/api/v0/cat`
+    pub url: String,
+    /// HTTP client for requests to the API, reused so connections are pooled
+    pub http_client: reqwest::Client,
+}
+
+impl IpfsApi {
+    pub fn new(url: &str) -> IpfsApi {
+        IpfsApi {
+            url: url.to_string(),
+            http_client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl ContentStore for IpfsApi {
+    async fn get(&self, cid: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let url = format!("{}/api/v0/cat", self.url.trim_end_matches('/'));
+        trace!("fetching content {} from api at: {}", cid, &url);
+        let response = self
+            .http_client
+            .post(&url)
+            .query(&[("arg", cid)])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| SubstrateError::Transport(err.to_string()))?;
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| SubstrateError::Transport(err.to_string()))?;
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Checks that `content` is the content addressed by `cid`.
+///
+/// Supports CIDv0 and CIDv1 with sha2-256 hashes. Content of `dag-pb` CIDs has to fit into a single
+/// UnixFS block, as it is the case for documents added with the default settings of IPFS.
+///
+/// # Arguments
+/// * `cid` - content identifier, the content has been fetched with
+/// * `content` - content to check
+pub fn verify_cid(cid: &str, content: &[u8]) -> Result<(), SubstrateError> {
+    let (codec, hash_code, digest) = parse_cid(cid)?;
+    if hash_code != HASH_SHA2_256 {
+        return Err(SubstrateError::InvalidContent(format!(
+            "unsupported hash function 0x{:x} in cid {}",
+            hash_code, cid
+        )));
+    }
+    let hashed = match codec {
+        CODEC_RAW => content.to_vec(),
+        CODEC_DAG_PB => {
+            if content.len() > MAX_SINGLE_BLOCK_SIZE {
+                return Err(SubstrateError::InvalidContent(format!(
+                    "content for cid {} is too large to be verified",
+                    cid
+                )));
+            }
+            encode_unixfs_file_node(content)
+        }
+        _ => {
+            return Err(SubstrateError::InvalidContent(format!(
+                "unsupported codec 0x{:x} in cid {}",
+                codec, cid
+            )))
+        }
+    };
+    if Sha256::digest(&hashed).as_slice() != digest.as_slice() {
+        return Err(SubstrateError::InvalidContent(format!(
+            "content does not match cid {}",
+            cid
+        )));
+    }
+    Ok(())
+}
+
+/// Parses a CID into its codec, hash function and digest.
+fn parse_cid(cid: &str) -> Result<(u64, u64, Vec<u8>), SubstrateError> {
+    let invalid =
+        |reason: &str| SubstrateError::InvalidContent(format!("invalid cid {}; {}", cid, reason));
+    // CIDv0 is a plain base58btc encoded sha2-256 multihash
+    let (codec, multihash) = if cid.len() == 46 && cid.starts_with("Qm") {
+        let bytes = Base::Base58Btc
+            .decode(cid)
+            .map_err(|err| invalid(&err.to_string()))?;
+        (CODEC_DAG_PB, bytes)
+    } else {
+        let (_, bytes) = multibase::decode(cid).map_err(|err| invalid(&err.to_string()))?;
+        let mut input = bytes.as_slice();
+        let version = read_varint(&mut input).ok_or_else(|| invalid("missing version"))?;
+        if version != 1 {
+            return Err(invalid("unsupported version"));
+        }
+        let codec = read_varint(&mut input).ok_or_else(|| invalid("missing codec"))?;
+        (codec, input.to_vec())
+    };
+    let mut input = multihash.as_slice();
+    let hash_code = read_varint(&mut input).ok_or_else(|| invalid("missing hash function"))?;
+    let length = read_varint(&mut input).ok_or_else(|| invalid("missing hash length"))?;
+    if input.len() as u64 != length {
+        return Err(invalid("hash length does not match"));
+    }
+    Ok((codec, hash_code, input.to_vec()))
+}
+
+/// Encodes content as `dag-pb` node with UnixFS file data and without links.
+fn encode_unixfs_file_node(content: &[u8]) -> Vec<u8> {
+    // UnixFS Data { Type = File, Data = content, filesize = content length }
+    let mut unixfs = Vec::new();
+    write_varint(&mut unixfs, 0x08);
+    write_varint(&mut unixfs, UNIXFS_FILE);
+    if !content.is_empty() {
+        write_varint(&mut unixfs, 0x12);
+        write_varint(&mut unixfs, content.len() as u64);
+        unixfs.extend_from_slice(content);
+    }
+    write_varint(&mut unixfs, 0x18);
+    write_varint(&mut unixfs, content.len() as u64);
+
+    // PBNode { Data = unixfs }
+    let mut node = Vec::new();
+    write_varint(&mut node, 0x0a);
+    write_varint(&mut node, unixfs.len() as u64);
+    node.extend_from_slice(&unixfs);
+    node
+}
+
+fn read_varint(input: &mut &[u8]) -> Option<u64> {
+    let mut value: u64 = 0;
+    for (i, byte) in input.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *input = &input[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_verify_cid_v0() -> Result<(), Box<dyn Error>> {
+        verify_cid(
+            "Qmf412jQZiuVUtdgnB36FXFX7xg5V6KEbSJ4dpQuhkLyfD",
+            b"hello world",
+        )?;
+        verify_cid("QmbFMke1KXqnYyBBWxB74N4c5SBnJMVAiMNRcGu6x1AwQH", b"")?;
+        Ok(())
+    }
+
+    #[test]
+    fn can_verify_cid_v1() -> Result<(), Box<dyn Error>> {
+        verify_cid(
+            "bafybeihykld7uyxzogax6vgyvag42y7464eywpf55gxi5qpoisibh3c5wa",
+            b"hello world",
+        )?;
+        verify_cid(
+            "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e",
+            b"hello world",
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_content_not_matching_cid() {
+        assert!(verify_cid(
+            "Qmf412jQZiuVUtdgnB36FXFX7xg5V6KEbSJ4dpQuhkLyfD",
+            b"hello world!"
+        )
+        .is_err());
+    }
+}
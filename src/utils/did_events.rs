@@ -0,0 +1,117 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+use crate::utils::extrinsic::{
+    events::{EventTypes, RawEvent},
+    node_metadata::EventArg,
+};
+use parity_scale_codec::{Decode, Encode, Error as CodecError};
+
+/// Name of the runtime module, that emits the events of this module.
+pub const DID_MODULE: &str = "DidModule";
+
+/// A DID has been created.
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct Created {
+    /// DID without DID method
+    pub did: Vec<u8>,
+    /// identity, that owns the DID
+    pub owner: Vec<u8>,
+    /// nonce of the call, that has created the DID
+    pub nonce: u64,
+}
+
+/// A payload of a DID has been added or updated.
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct UpdatedDid {
+    /// DID without DID method
+    pub did: Vec<u8>,
+    /// index of added or updated payload
+    pub index: u32,
+    /// nonce of the call, that has updated the DID
+    pub nonce: u64,
+}
+
+/// An identity has been whitelisted for an account.
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct IdentityWhitelist {
+    /// whitelisted identity
+    pub identity: Vec<u8>,
+    /// account, that has requested the whitelisting
+    pub account: Vec<u8>,
+    /// true if identity is whitelisted
+    pub approved: bool,
+    /// nonce of the call, that has whitelisted the identity
+    pub nonce: u64,
+}
+
+/// Event emitted by `DidModule`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DidModuleEvent {
+    Created(Created),
+    UpdatedDid(UpdatedDid),
+    IdentityWhitelist(IdentityWhitelist),
+}
+
+impl DidModuleEvent {
+    /// Decodes a raw event, returns `None` for events of other modules or unknown variants.
+    ///
+    /// # Arguments
+    /// * `raw` - event decoded by an `EventsDecoder`
+    pub fn from_raw(raw: &RawEvent) -> Result<Option<DidModuleEvent>, CodecError> {
+        if raw.module != DID_MODULE {
+            return Ok(None);
+        }
+        let mut data = &raw.data[..];
+        Ok(Some(match raw.variant.as_str() {
+            "Created" => DidModuleEvent::Created(Decode::decode(&mut data)?),
+            "UpdatedDid" => DidModuleEvent::UpdatedDid(Decode::decode(&mut data)?),
+            "IdentityWhitelist" => DidModuleEvent::IdentityWhitelist(Decode::decode(&mut data)?),
+            _ => return Ok(None),
+        }))
+    }
+
+    /// Gets the name of the event, e.g. `Created`.
+    pub fn variant(&self) -> &'static str {
+        match self {
+            DidModuleEvent::Created(_) => "Created",
+            DidModuleEvent::UpdatedDid(_) => "UpdatedDid",
+            DidModuleEvent::IdentityWhitelist(_) => "IdentityWhitelist",
+        }
+    }
+
+    /// Gets the nonce of the call, that has emitted this event.
+    pub fn nonce(&self) -> u64 {
+        match self {
+            DidModuleEvent::Created(event) => event.nonce,
+            DidModuleEvent::UpdatedDid(event) => event.nonce,
+            DidModuleEvent::IdentityWhitelist(event) => event.nonce,
+        }
+    }
+}
+
+/// Registers the names, that runtimes may use for argument types of `DidModule` events, so these
+/// events can be decoded without registering their sizes.
+///
+/// # Arguments
+/// * `types` - types to add `DidModule` types to
+pub fn register_did_module_types(types: &mut EventTypes) {
+    let bytes = EventArg::Vec(Box::new(EventArg::Primitive("u8".to_string())));
+    types.register_arg("Did", bytes.clone());
+    types.register_arg("Identity", bytes);
+    types.register_arg("Nonce", EventArg::Primitive("u64".to_string()));
+    types.register_arg("DetailIndex", EventArg::Primitive("u32".to_string()));
+}
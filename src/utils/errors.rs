@@ -0,0 +1,61 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+use parity_scale_codec::Error as CodecError;
+
+/// Errors returned by `VadeEvanSubstrate` and its substrate functions.
+///
+/// Functions still return `Box<dyn Error>`, use `downcast_ref::<SubstrateError>()` to check for a
+/// specific error.
+#[derive(Debug, thiserror::Error)]
+pub enum SubstrateError {
+    #[error("DID not found: {0}")]
+    DidNotFound(String),
+    #[error("DID has been deactivated: {0}")]
+    DidDeactivated(String),
+    #[error("block not found: {0}")]
+    BlockNotFound(String),
+    #[error("identity is not whitelisted: {0}")]
+    IdentityNotWhitelisted(String),
+    #[error(
+        "extrinsic failed with error '{module}::{name}' (module {module_index}, error {error_index}); {documentation}"
+    )]
+    DispatchModule {
+        module_index: u8,
+        error_index: u8,
+        module: String,
+        name: String,
+        documentation: String,
+    },
+    #[error("extrinsic failed with error '{name}'; {documentation}")]
+    DispatchSystem { name: String, documentation: String },
+    #[error("extrinsic failed; {0}")]
+    Dispatch(String),
+    #[error("timeout while {0}")]
+    Timeout(String),
+    #[error("transport error; {0}")]
+    Transport(String),
+    #[error("RPC error; {0}")]
+    Rpc(String),
+    #[error("invalid content; {0}")]
+    InvalidContent(String),
+    #[error("could not decode data; {0}")]
+    Codec(#[from] CodecError),
+    #[error("invalid metadata; {0}")]
+    Metadata(String),
+    #[error("signer error; {0}")]
+    Signer(String),
+}
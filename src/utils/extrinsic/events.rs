@@ -15,12 +15,14 @@
 // along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::utils::extrinsic::node_metadata::{EventArg, Metadata, MetadataError};
-use parity_scale_codec::{Codec, Compact, Decode, Encode, Error as CodecError, Input, Output};
+use parity_scale_codec::{Codec, Compact, Decode, Encode, Error as CodecError, Input};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 pub use sp_core::H256 as Hash;
-use std::{collections::HashMap, convert::TryFrom, marker::Send};
+use std::{collections::HashMap, convert::TryFrom, marker::Send, sync::Arc};
 
-/// Event for the System module.
-#[derive(Clone, Debug, Decode)]
+/// Result of an extrinsic, taken from the `System` event, that has been emitted for it.
+#[derive(Clone, Debug)]
 pub enum SystemEvent {
     // An extrinsic completed successfully.
     ExtrinsicSuccess(DispatchInfo),
@@ -28,14 +30,38 @@ pub enum SystemEvent {
     ExtrinsicFailed(DispatchError, DispatchInfo),
 }
 
-/// Top level Event that can be produced by a substrate runtime
-#[derive(Debug)]
-pub enum RuntimeEvent {
-    System(SystemEvent),
-    Raw(RawEvent),
+impl SystemEvent {
+    /// Takes the result of an extrinsic from a raw event, returns `None` for other events. `System`
+    /// events are decoded with the metadata like events of other modules, so their layout may differ
+    /// between runtimes, e.g. weights of `DispatchInfo`.
+    ///
+    /// # Arguments
+    /// * `raw` - event decoded by an `EventsDecoder`
+    pub fn from_raw(raw: &RawEvent) -> Result<Option<SystemEvent>, CodecError> {
+        if raw.module != "System" {
+            return Ok(None);
+        }
+        Ok(Some(match (raw.variant.as_str(), &raw.arguments[..]) {
+            ("ExtrinsicSuccess", [info]) => {
+                SystemEvent::ExtrinsicSuccess(DispatchInfo::from_argument(info)?)
+            }
+            // the error is the first argument, so the data of the event starts with it, module
+            // errors only keep their first byte, so this works for every length of them
+            ("ExtrinsicFailed", [_, info]) => SystemEvent::ExtrinsicFailed(
+                DispatchError::decode(&mut &raw.data[..])?,
+                DispatchInfo::from_argument(info)?,
+            ),
+            ("ExtrinsicSuccess", _) | ("ExtrinsicFailed", _) => {
+                return Err(CodecError::from("unexpected arguments of extrinsic result"))
+            }
+            _ => return Ok(None),
+        }))
+    }
 }
 
-/// Raw bytes for an Event
+/// Event of a runtime module with its raw bytes and its arguments, that have been decoded with the
+/// metadata. Typed events are taken from it with `SystemEvent::from_raw` or
+/// `DidModuleEvent::from_raw`.
 #[derive(Debug)]
 pub struct RawEvent {
     /// The name of the module from whence the Event originated
@@ -44,9 +70,11 @@ pub struct RawEvent {
     pub variant: String,
     /// The raw Event data
     pub data: Vec<u8>,
+    /// The decoded Event arguments
+    pub arguments: Vec<Value>,
 }
 
-#[derive(Debug, Encode, Decode)]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
 pub enum Phase {
     /// Applying an extrinsic.
     ApplyExtrinsic(u32),
@@ -56,6 +84,14 @@ pub enum Phase {
     Initialization,
 }
 
+/// Record of an event in `System::Events` with the phase it has been emitted in and its topics.
+#[derive(Debug)]
+pub struct EventRecord {
+    pub phase: Phase,
+    pub event: RawEvent,
+    pub topics: Vec<Hash>,
+}
+
 /// Reason why a dispatch call failed.
 #[derive(Encode, Decode, Debug, Clone)]
 pub enum DispatchError {
@@ -69,12 +105,127 @@ pub enum DispatchError {
     Module {
         /// Module index, matching the metadata module index.
         index: u8,
-        /// Module specific error value.
+        /// Module specific error value, runtimes with 4 byte module errors start them with it.
         error: u8,
         /// Optional error message.
         #[codec(skip)]
         message: Option<&'static str>,
     },
+    /// At least one consumer is remaining so the account cannot be destroyed.
+    ConsumerRemaining,
+    /// There are no providers so the account cannot be created.
+    NoProviders,
+    /// An error to do with tokens.
+    Token(TokenError),
+    /// An arithmetic error.
+    Arithmetic(ArithmeticError),
+}
+
+impl DispatchError {
+    /// Decodes an error, whose module errors are `module_error_len` bytes long, see
+    /// [`Metadata::module_error_len`](Metadata::module_error_len). Only the first byte of module
+    /// errors is kept, so decoding `DispatchError` as prefix of a longer input works for every
+    /// layout, the length is only required to decode the data after the error.
+    ///
+    /// # Arguments
+    /// * `input` - encoded error
+    /// * `module_error_len` - number of bytes of module errors
+    pub fn decode_with_module_error_len(
+        input: &mut &[u8],
+        module_error_len: usize,
+    ) -> Result<DispatchError, CodecError> {
+        // variant index of `DispatchError::Module`
+        if input.first() != Some(&3) {
+            return DispatchError::decode(input);
+        }
+        let (_, index, error) = <(u8, u8, u8)>::decode(input)?;
+        let mut remaining = vec![0u8; module_error_len.saturating_sub(1)];
+        input.read(&mut remaining)?;
+        Ok(DispatchError::Module {
+            index,
+            error,
+            message: None,
+        })
+    }
+
+    /// Name of error, errors of modules have to be resolved with the metadata.
+    pub fn name(&self) -> String {
+        match self {
+            DispatchError::Other(_) => "Other".to_string(),
+            DispatchError::CannotLookup => "CannotLookup".to_string(),
+            DispatchError::BadOrigin => "BadOrigin".to_string(),
+            DispatchError::Module { .. } => "Module".to_string(),
+            DispatchError::ConsumerRemaining => "ConsumerRemaining".to_string(),
+            DispatchError::NoProviders => "NoProviders".to_string(),
+            DispatchError::Token(error) => format!("Token::{:?}", error),
+            DispatchError::Arithmetic(error) => format!("Arithmetic::{:?}", error),
+        }
+    }
+
+    /// Documentation of error, errors of modules have to be resolved with the metadata.
+    pub fn documentation(&self) -> &'static str {
+        match self {
+            DispatchError::Other(message) if !message.is_empty() => message,
+            DispatchError::Other(_) => "Some error occurred.",
+            DispatchError::CannotLookup => "Failed to lookup some data.",
+            DispatchError::BadOrigin => "A bad origin.",
+            DispatchError::Module { .. } => "A custom error in a module.",
+            DispatchError::ConsumerRemaining => {
+                "At least one consumer is remaining so the account cannot be destroyed."
+            }
+            DispatchError::NoProviders => {
+                "There are no providers so the account cannot be created."
+            }
+            DispatchError::Token(error) => error.documentation(),
+            DispatchError::Arithmetic(error) => error.documentation(),
+        }
+    }
+}
+
+/// Reason why a dispatch call failed because of tokens.
+#[derive(Encode, Decode, Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TokenError {
+    NoFunds,
+    WouldDie,
+    BelowMinimum,
+    CannotCreate,
+    UnknownAsset,
+    Frozen,
+    Unsupported,
+}
+
+impl TokenError {
+    fn documentation(&self) -> &'static str {
+        match self {
+            TokenError::NoFunds => "Funds are unavailable.",
+            TokenError::WouldDie => "Account that must exist would die.",
+            TokenError::BelowMinimum => "Account cannot exist with the funds that would be given.",
+            TokenError::CannotCreate => "Account cannot be created.",
+            TokenError::UnknownAsset => "The asset in question is unknown.",
+            TokenError::Frozen => "Funds exist but are frozen.",
+            TokenError::Unsupported => "Operation is not supported by the asset.",
+        }
+    }
+}
+
+/// Reason why a dispatch call failed because of an arithmetic error.
+#[derive(Encode, Decode, Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArithmeticError {
+    Underflow,
+    Overflow,
+    DivisionByZero,
+}
+
+impl ArithmeticError {
+    fn documentation(&self) -> &'static str {
+        match self {
+            ArithmeticError::Underflow => "An underflow would occur.",
+            ArithmeticError::Overflow => "An overflow would occur.",
+            ArithmeticError::DivisionByZero => "Division by zero.",
+        }
+    }
 }
 
 /// Numeric range of a transaction weight.
@@ -91,8 +242,31 @@ pub struct DispatchInfo {
     pub pays_fee: Pays,
 }
 
+impl DispatchInfo {
+    /// Takes dispatch info from its decoded event argument, a list of weight, class and whether fees
+    /// are paid. Weights, that consist of multiple parts, e.g. `ref_time` and `proof_size` of newer
+    /// runtimes, are read from their first part.
+    fn from_argument(argument: &Value) -> Result<DispatchInfo, CodecError> {
+        let invalid = || CodecError::from("invalid dispatch info");
+        let (weight, class, pays_fee) = match argument.as_array().map(Vec::as_slice) {
+            Some([weight, class, pays_fee]) => (weight, class, pays_fee),
+            _ => return Err(invalid()),
+        };
+        let weight = match weight {
+            Value::Array(parts) => parts.first(),
+            weight => Some(weight),
+        };
+        Ok(DispatchInfo {
+            weight: weight.and_then(Value::as_u64).ok_or_else(invalid)?,
+            class: serde_json::from_value(class.clone()).map_err(|_| invalid())?,
+            pays_fee: serde_json::from_value(pays_fee.clone()).map_err(|_| invalid())?,
+        })
+    }
+}
+
 /// A generalized group of dispatch types.
-#[derive(Encode, Decode, Debug, Clone)]
+#[derive(Encode, Decode, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum DispatchClass {
     /// A normal dispatch.
     Normal,
@@ -114,7 +288,8 @@ pub enum DispatchClass {
 }
 
 /// Explicit enum to denote if a transaction pays fee or not.
-#[derive(Encode, Decode, Debug, Clone)]
+#[derive(Encode, Decode, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum Pays {
     /// Transactor will pay related fees.
     Yes,
@@ -132,10 +307,53 @@ pub enum EventsError {
     TypeSizeUnavailable(String),
 }
 
+/// Decodes a custom type from its SCALE encoding to JSON.
+type TypeDecoder = Arc<dyn Fn(&mut &[u8]) -> Result<Value, CodecError> + Send + Sync>;
+
+/// Custom types for decoding event arguments, that can be shared by multiple decoders.
+#[derive(Clone, Default)]
+pub struct EventTypes {
+    decoders: HashMap<String, TypeDecoder>,
+    aliases: HashMap<String, EventArg>,
+}
+
+impl EventTypes {
+    /// Registers a type, that is decoded with its `Decode` implementation and converted to JSON
+    /// with its `Serialize` implementation, e.g. a custom struct of a runtime module.
+    pub fn register<U>(&mut self, name: &str)
+    where
+        U: Decode + Serialize,
+    {
+        self.decoders.insert(
+            name.to_string(),
+            Arc::new(|input: &mut &[u8]| {
+                let value = U::decode(input)?;
+                serde_json::to_value(value)
+                    .map_err(|_| CodecError::from("could not convert to JSON"))
+            }),
+        );
+    }
+
+    /// Registers a type name, that is decoded like `definition`, e.g. `Balance` as `u128`.
+    pub fn register_alias(&mut self, name: &str, definition: &str) -> Result<(), EventsError> {
+        let definition = definition
+            .parse()
+            .map_err(|_| EventsError::TypeSizeUnavailable(definition.to_owned()))?;
+        self.register_arg(name, definition);
+        Ok(())
+    }
+
+    /// Registers a type name, that is decoded like the already parsed `definition`.
+    pub fn register_arg(&mut self, name: &str, definition: EventArg) {
+        self.aliases.insert(name.to_string(), definition);
+    }
+}
+
 #[derive(Clone)]
 pub struct EventsDecoder {
     metadata: Metadata,
     type_sizes: HashMap<String, usize>,
+    types: EventTypes,
 }
 
 impl TryFrom<Metadata> for EventsDecoder {
@@ -145,6 +363,7 @@ impl TryFrom<Metadata> for EventsDecoder {
         let mut decoder = Self {
             metadata,
             type_sizes: HashMap::new(),
+            types: EventTypes::default(),
         };
         // register default event arg type sizes for dynamic decoding of events
         decoder.register_type_size::<bool>("bool")?;
@@ -154,6 +373,10 @@ impl TryFrom<Metadata> for EventsDecoder {
         decoder.register_type_size::<u8>("u8")?;
         decoder.register_type_size::<u32>("u32")?;
         decoder.register_type_size::<u64>("u64")?;
+        decoder.register_type_size::<u16>("u16")?;
+        decoder.register_type_size::<u128>("u128")?;
+        decoder.register_type_size::<[u8; 20]>("[u8; 20]")?;
+        decoder.register_type_size::<[u8; 32]>("[u8; 32]")?;
         decoder.register_type_size::<u32>("AccountIndex")?;
         decoder.register_type_size::<u32>("SessionIndex")?;
         decoder.register_type_size::<u32>("PropIndex")?;
@@ -164,6 +387,24 @@ impl TryFrom<Metadata> for EventsDecoder {
         decoder.register_type_size::<Hash>("Hash")?;
         // VoteThreshold enum index
         decoder.register_type_size::<u8>("VoteThreshold")?;
+        // common runtime types, that are decoded like the types they are defined as
+        decoder.types.register_alias("AccountId", "[u8; 32]")?;
+        decoder.types.register_alias("AccountId32", "[u8; 32]")?;
+        decoder.types.register_alias("Balance", "u128")?;
+        decoder.types.register_alias("BalanceOf", "u128")?;
+        decoder.types.register_alias("BlockNumber", "u32")?;
+        decoder.types.register_alias("Index", "u32")?;
+        decoder.types.register_alias("Moment", "u64")?;
+        decoder.types.register_alias("Weight", "u64")?;
+        decoder.types.register_alias("H160", "[u8; 20]")?;
+        decoder.types.register_alias("H256", "[u8; 32]")?;
+        decoder.types.register_alias("H512", "[u8; 64]")?;
+        // V14 metadata describes `DispatchInfo` by its fields, others by its name
+        decoder
+            .types
+            .register_alias("DispatchInfo", "(Weight, DispatchClass, Pays)")?;
+        decoder.types.register::<DispatchClass>("DispatchClass");
+        decoder.types.register::<Pays>("Pays");
 
         Ok(decoder)
     }
@@ -183,91 +424,517 @@ impl EventsDecoder {
         }
     }
 
-    fn decode_raw_bytes<I: Input, W: Output>(
-        &self,
-        args: &[EventArg],
-        input: &mut I,
-        output: &mut W,
-    ) -> Result<(), EventsError> {
-        for arg in args {
-            match arg {
-                EventArg::Vec(arg) => {
-                    let len = <Compact<u32>>::decode(input)?;
-                    len.encode_to(output);
-                    for _ in 0..len.0 {
-                        self.decode_raw_bytes(&[*arg.clone()], input, output)?
-                    }
+    /// Adds custom types to the types of this decoder, replaces types with the same name.
+    pub fn with_types(mut self, types: &EventTypes) -> Self {
+        self.types
+            .decoders
+            .extend(types.decoders.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.types
+            .aliases
+            .extend(types.aliases.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self
+    }
+
+    /// Decodes an event argument to JSON. Numbers up to 64 bit are returned as JSON numbers, larger
+    /// ones as decimal strings, bytes as hex strings, e.g. for `Vec<u8>`, `[u8; 32]` or types
+    /// registered by their size.
+    fn decode_argument(&self, arg: &EventArg, input: &mut &[u8]) -> Result<Value, EventsError> {
+        match arg {
+            EventArg::Vec(arg) => {
+                let len = <Compact<u32>>::decode(input)?.0 as usize;
+                if self.is_byte(arg) {
+                    return read_hex(input, len);
                 }
-                EventArg::Tuple(args) => self.decode_raw_bytes(args, input, output)?,
-                EventArg::Primitive(name) => {
-                    if name.contains("PhantomData") {
-                        // PhantomData is size 0
-                        return Ok(());
-                    }
-                    if let Some(size) = self.type_sizes.get(name) {
-                        let mut buf = vec![0; *size];
-                        input.read(&mut buf)?;
-                        output.write(&buf);
-                    } else {
-                        return Err(EventsError::TypeSizeUnavailable(name.to_owned()));
-                    }
+                (0..len)
+                    .map(|_| self.decode_argument(arg, input))
+                    .collect::<Result<Vec<Value>, EventsError>>()
+                    .map(Value::Array)
+            }
+            EventArg::Array(arg, len) => {
+                if self.is_byte(arg) {
+                    return read_hex(input, *len);
                 }
+                (0..*len)
+                    .map(|_| self.decode_argument(arg, input))
+                    .collect::<Result<Vec<Value>, EventsError>>()
+                    .map(Value::Array)
             }
+            EventArg::Tuple(args) => args
+                .iter()
+                .map(|arg| self.decode_argument(arg, input))
+                .collect::<Result<Vec<Value>, EventsError>>()
+                .map(Value::Array),
+            EventArg::Option(arg) => match input.read_byte()? {
+                0 => Ok(Value::Null),
+                1 => self.decode_argument(arg, input),
+                _ => Err(CodecError::from("invalid Option").into()),
+            },
+            EventArg::Compact(_) => Ok(to_json_number(<Compact<u128>>::decode(input)?.0)),
+            EventArg::Primitive(name) => self.decode_primitive(name, input),
         }
-        Ok(())
     }
 
-    pub fn decode_events(
+    fn decode_primitive(&self, name: &str, input: &mut &[u8]) -> Result<Value, EventsError> {
+        if let Some(decoder) = self.types.decoders.get(name) {
+            return Ok(decoder(input)?);
+        }
+        if let Some(definition) = self.types.aliases.get(name) {
+            return self.decode_argument(definition, input);
+        }
+        if name.contains("PhantomData") {
+            // PhantomData is size 0
+            return Ok(Value::Null);
+        }
+        Ok(match name {
+            "bool" => json!(bool::decode(input)?),
+            "u8" => json!(u8::decode(input)?),
+            "u16" => json!(u16::decode(input)?),
+            "u32" => json!(u32::decode(input)?),
+            "u64" => json!(u64::decode(input)?),
+            "u128" => to_json_number(u128::decode(input)?),
+            "i8" => json!(i8::decode(input)?),
+            "i16" => json!(i16::decode(input)?),
+            "i32" => json!(i32::decode(input)?),
+            "i64" => json!(i64::decode(input)?),
+            "i128" => json!(i128::decode(input)?.to_string()),
+            "String" | "Text" | "str" => json!(String::decode(input)?),
+            // enum with variants of different sizes
+            "DispatchError" => match DispatchError::decode_with_module_error_len(
+                input,
+                self.metadata.module_error_len(),
+            )? {
+                DispatchError::Module { index, error, .. } => {
+                    json!({ "module": { "index": index, "error": error } })
+                }
+                DispatchError::Other(_) => json!("other"),
+                DispatchError::CannotLookup => json!("cannotLookup"),
+                DispatchError::BadOrigin => json!("badOrigin"),
+                DispatchError::ConsumerRemaining => json!("consumerRemaining"),
+                DispatchError::NoProviders => json!("noProviders"),
+                DispatchError::Token(error) => json!({ "token": error }),
+                DispatchError::Arithmetic(error) => json!({ "arithmetic": error }),
+            },
+            _ => match self.type_sizes.get(name) {
+                Some(size) => read_hex(input, *size)?,
+                None => return Err(EventsError::TypeSizeUnavailable(name.to_owned())),
+            },
+        })
+    }
+
+    /// Checks if `arg` is a single byte, so lists of it can be returned as hex strings.
+    fn is_byte(&self, arg: &EventArg) -> bool {
+        match arg {
+            EventArg::Primitive(name) => {
+                !self.types.decoders.contains_key(name)
+                    && match self.types.aliases.get(name) {
+                        Some(definition) => self.is_byte(definition),
+                        None => name == "u8",
+                    }
+            }
+            _ => false,
+        }
+    }
+
+    pub fn decode_events(&self, input: &mut &[u8]) -> Result<Vec<EventRecord>, EventsError> {
+        match self.decode_events_until_error(input) {
+            (events, None) => Ok(events),
+            (_, Some(err)) => Err(err),
+        }
+    }
+
+    /// Decodes events like [`decode_events`](EventsDecoder::decode_events), but returns the events
+    /// before the first event, that cannot be decoded, together with the error instead of failing.
+    /// Events after this event are not returned, as their position depends on its size.
+    pub fn decode_events_until_error(
         &self,
         input: &mut &[u8],
-    ) -> Result<Vec<(Phase, RuntimeEvent)>, EventsError> {
+    ) -> (Vec<EventRecord>, Option<EventsError>) {
         debug!("Decoding compact len: {:?}", input);
-        let compact_len = <Compact<u32>>::decode(input)?;
-        let len = compact_len.0 as usize;
+        let len = match <Compact<u32>>::decode(input) {
+            Ok(compact_len) => compact_len.0 as usize,
+            Err(err) => return (Vec::new(), Some(err.into())),
+        };
 
         let mut r = Vec::new();
         for _ in 0..len {
-            // decode EventRecord
-            let phase = Phase::decode(input)?;
-            let module_variant = input.read_byte()?;
-            let module = self.metadata.module_with_events(module_variant)?;
-            let event = if module.name() == "System" {
-                let system_event = SystemEvent::decode(input)?;
-                RuntimeEvent::System(system_event)
-            } else {
-                let event_variant = input.read_byte()?;
-                let event_metadata = module.event(event_variant)?;
-                debug!(
-                    "decoding event '{}::{}'",
-                    module.name(),
-                    event_metadata.name
-                );
+            match self.decode_event_record(input) {
+                Ok(record) => r.push(record),
+                Err(err) => return (r, Some(err)),
+            }
+        }
+        (r, None)
+    }
+
+    fn decode_event_record(&self, input: &mut &[u8]) -> Result<EventRecord, EventsError> {
+        let phase = Phase::decode(input)?;
+        let module_variant = input.read_byte()?;
+        let module = self.metadata.module_with_events(module_variant)?;
+        let event_variant = input.read_byte()?;
+        let event_metadata = module.event(event_variant)?;
+        debug!(
+            "decoding event '{}::{}'",
+            module.name(),
+            event_metadata.name
+        );
+
+        let event_start = *input;
+        let arguments = event_metadata
+            .arguments()
+            .iter()
+            .map(|arg| self.decode_argument(arg, input))
+            .collect::<Result<Vec<Value>, EventsError>>()?;
+        let event_data = event_start[..event_start.len() - input.len()].to_vec();
+
+        debug!(
+            "received event '{}::{}', raw bytes; {}",
+            module.name(),
+            event_metadata.name,
+            hex::encode(&event_data),
+        );
 
-                let mut event_data = Vec::<u8>::new();
+        let event = RawEvent {
+            module: module.name().to_string(),
+            variant: event_metadata.name.clone(),
+            data: event_data,
+            arguments,
+        };
+
+        // topics come after the event data in EventRecord
+        debug!("Phase {:?}, Event: {:?}", phase, event);
+
+        debug!("Decoding topics {:?}", input);
+        let topics = Vec::<Hash>::decode(input)?;
+        Ok(EventRecord {
+            phase,
+            event,
+            topics,
+        })
+    }
+}
+
+/// Reads `len` bytes and returns them as hex string.
+fn read_hex(input: &mut &[u8], len: usize) -> Result<Value, EventsError> {
+    if input.len() < len {
+        return Err(CodecError::from("Not enough data to fill buffer").into());
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(json!(format!("0x{}", hex::encode(bytes))))
+}
+
+/// Converts numbers to JSON numbers if they fit into 64 bit, to decimal strings otherwise.
+fn to_json_number(value: u128) -> Value {
+    match u64::try_from(value) {
+        Ok(value) => json!(value),
+        Err(_) => json!(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        extrinsic::{
+            frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed},
+            scale_info::{PortableType, Type, TypeDef, TypeDefArray},
+        },
+        mock_node::{mock_metadata, METADATA_V12, METADATA_V13, METADATA_V14},
+    };
+
+    fn get_decoder() -> EventsDecoder {
+        let metadata = Metadata::parse(mock_metadata()).expect("could not parse metadata");
+        EventsDecoder::try_from(metadata).expect("could not create decoder")
+    }
+
+    fn decode(decoder: &EventsDecoder, ty: &str, bytes: &[u8]) -> Result<Value, EventsError> {
+        let arg: EventArg = ty.parse().expect("could not parse type");
+        let mut input = bytes;
+        let value = decoder.decode_argument(&arg, &mut input)?;
+        assert!(
+            input.is_empty(),
+            "not all bytes of {} have been decoded",
+            ty
+        );
+        Ok(value)
+    }
+
+    #[test]
+    fn decodes_event_arguments_to_json() -> Result<(), EventsError> {
+        let decoder = get_decoder();
+        let account = [7u8; 32];
+
+        assert_eq!(
+            decode(&decoder, "Vec<u8>", &vec![1u8, 2].encode())?,
+            json!("0x0102")
+        );
+        assert_eq!(
+            decode(&decoder, "T::AccountId", &account.encode())?,
+            json!(format!("0x{}", hex::encode(account)))
+        );
+        assert_eq!(
+            decode(
+                &decoder,
+                "(BlockNumber, Compact<BalanceOf<T>>)",
+                &(5u32, Compact(u128::MAX)).encode()
+            )?,
+            json!([5, u128::MAX.to_string()])
+        );
+        assert_eq!(
+            decode(
+                &decoder,
+                "Vec<Option<u64>>",
+                &vec![Some(1u64), None].encode()
+            )?,
+            json!([1, null])
+        );
+        assert_eq!(
+            decode(&decoder, "[u16; 2]", &[1u16, 2].encode())?,
+            json!([1, 2])
+        );
+        assert_eq!(
+            decode(&decoder, "Option<bool>", &Some(false).encode())?,
+            json!(false)
+        );
+        assert!(matches!(
+            decode(&decoder, "Unknown", &[0]),
+            Err(EventsError::TypeSizeUnavailable(_))
+        ));
+
+        Ok(())
+    }
 
-                self.decode_raw_bytes(&event_metadata.arguments(), input, &mut event_data)?;
+    #[test]
+    fn decodes_raw_data_and_arguments_of_events() -> Result<(), EventsError> {
+        let decoder = get_decoder();
+        let data = (vec![1u8; 32], vec![2u8; 20], 3u64).encode();
+        let mut events = Compact(1u32).encode();
+        events.extend(Phase::ApplyExtrinsic(0).encode());
+        // `DidModule::Created`
+        events.extend(&[1, 0]);
+        events.extend(&data);
+        events.extend(vec![Hash::repeat_byte(4)].encode());
 
-                debug!(
-                    "received event '{}::{}', raw bytes; {}",
-                    module.name(),
-                    event_metadata.name,
-                    hex::encode(&event_data),
+        let decoded = decoder.decode_events(&mut events.as_slice())?;
+        match &decoded[..] {
+            [EventRecord {
+                phase: Phase::ApplyExtrinsic(0),
+                event: raw,
+                topics,
+            }] => {
+                assert_eq!(topics, &vec![Hash::repeat_byte(4)]);
+                assert_eq!(raw.variant, "Created");
+                assert_eq!(raw.data, data);
+                assert_eq!(
+                    raw.arguments,
+                    vec![
+                        json!(format!("0x{}", hex::encode([1u8; 32]))),
+                        json!(format!("0x{}", hex::encode([2u8; 20]))),
+                        json!(3),
+                    ]
                 );
+            }
+            _ => panic!("unexpected events {:?}", decoded),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_custom_types() -> Result<(), EventsError> {
+        #[derive(Decode, Serialize)]
+        struct Custom {
+            id: u32,
+            name: String,
+        }
+
+        let mut types = EventTypes::default();
+        types.register::<Custom>("Custom");
+        types.register_alias("Amount", "u128")?;
+        let decoder = get_decoder().with_types(&types);
+
+        assert_eq!(
+            decode(
+                &decoder,
+                "Vec<Custom>",
+                &vec![(1u32, "first".to_string())].encode()
+            )?,
+            json!([{ "id": 1, "name": "first" }])
+        );
+        assert_eq!(decode(&decoder, "Amount", &3u128.encode())?, json!(3));
 
-                RuntimeEvent::Raw(RawEvent {
-                    module: module.name().to_string(),
-                    variant: event_metadata.name.clone(),
-                    data: event_data,
-                })
-            };
+        Ok(())
+    }
 
-            // topics come after the event data in EventRecord
-            debug!("Phase {:?}, Event: {:?}", phase, event);
+    #[test]
+    fn decodes_system_events_with_metadata() -> Result<(), Box<dyn std::error::Error>> {
+        let info = DispatchInfo {
+            weight: 7,
+            class: DispatchClass::Operational,
+            pays_fee: Pays::No,
+        };
+        let error = DispatchError::Module {
+            index: 1,
+            error: 2,
+            message: None,
+        };
+        // block with `NewAccount`, `ExtrinsicSuccess` and `ExtrinsicFailed` of `System`
+        let mut events = Compact(3u32).encode();
+        events.extend(Phase::ApplyExtrinsic(0).encode());
+        events.extend(&[0, 3]);
+        events.extend(&[5u8; 32]);
+        events.extend(Vec::<Hash>::new().encode());
+        events.extend(Phase::ApplyExtrinsic(0).encode());
+        events.extend(&[0, 0]);
+        events.extend(info.encode());
+        events.extend(Vec::<Hash>::new().encode());
+        events.extend(Phase::ApplyExtrinsic(1).encode());
+        events.extend(&[0, 1]);
+        events.extend((error, info).encode());
+        events.extend(Vec::<Hash>::new().encode());
 
-            debug!("Decoding topics {:?}", input);
-            let _ignore_topic_errors = Vec::<sp_core::H256>::decode(input);
-            r.push((phase, event));
+        let mut runtimes = vec![mock_metadata()];
+        for fixture in &[METADATA_V12, METADATA_V13, METADATA_V14] {
+            let bytes = hex::decode(fixture.trim().trim_start_matches("0x"))?;
+            runtimes.push(RuntimeMetadataPrefixed::decode(&mut &bytes[..])?);
         }
-        Ok(r)
+        for runtime in runtimes {
+            let decoder = EventsDecoder::try_from(Metadata::parse(runtime)?)?;
+            let decoded = decoder.decode_events(&mut events.as_slice())?;
+            let raw: Vec<&RawEvent> = decoded.iter().map(|record| &record.event).collect();
+            match &raw[..] {
+                [new_account, success, failed] => {
+                    assert_eq!(new_account.module, "System");
+                    assert_eq!(new_account.variant, "NewAccount");
+                    assert_eq!(
+                        new_account.arguments,
+                        vec![json!(format!("0x{}", hex::encode([5u8; 32])))]
+                    );
+                    assert!(SystemEvent::from_raw(new_account)?.is_none());
+
+                    assert_eq!(success.arguments, vec![json!([7, "operational", "no"])]);
+                    assert!(matches!(
+                        SystemEvent::from_raw(success)?,
+                        Some(SystemEvent::ExtrinsicSuccess(DispatchInfo {
+                            weight: 7,
+                            class: DispatchClass::Operational,
+                            pays_fee: Pays::No,
+                        }))
+                    ));
+                    assert!(matches!(
+                        SystemEvent::from_raw(failed)?,
+                        Some(SystemEvent::ExtrinsicFailed(
+                            DispatchError::Module {
+                                index: 1,
+                                error: 2,
+                                ..
+                            },
+                            DispatchInfo { weight: 7, .. },
+                        ))
+                    ));
+                }
+                _ => panic!("unexpected events {:?}", decoded),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_module_errors_with_four_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        // V14 fixture with `ModuleError.error` changed from `u8` to `[u8; 4]`
+        let bytes = hex::decode(METADATA_V14.trim().trim_start_matches("0x"))?;
+        let mut runtime = RuntimeMetadataPrefixed::decode(&mut &bytes[..])?;
+        match &mut runtime.1 {
+            RuntimeMetadata::V14(meta) => {
+                let array_id = meta.types.types.len() as u32;
+                let module_error = meta
+                    .types
+                    .types
+                    .iter_mut()
+                    .find(|portable_type| portable_type.ty.path == ["sp_runtime", "ModuleError"])
+                    .ok_or("missing ModuleError")?;
+                let error_field = match &mut module_error.ty.type_def {
+                    TypeDef::Composite(composite) => composite
+                        .fields
+                        .iter_mut()
+                        .find(|field| field.name.as_deref() == Some("error"))
+                        .ok_or("missing error field")?,
+                    _ => panic!("unexpected ModuleError type"),
+                };
+                let byte_id = error_field.ty;
+                error_field.ty.0 = array_id;
+                meta.types.types.push(PortableType {
+                    id: array_id,
+                    ty: Type {
+                        path: Vec::new(),
+                        type_params: Vec::new(),
+                        type_def: TypeDef::Array(TypeDefArray {
+                            len: 4,
+                            type_param: byte_id,
+                        }),
+                        docs: Vec::new(),
+                    },
+                });
+            }
+            _ => panic!("unexpected metadata version"),
+        }
+        let metadata = Metadata::parse(runtime)?;
+        assert_eq!(metadata.module_error_len(), 4);
+
+        let info = DispatchInfo {
+            weight: 7,
+            class: DispatchClass::Normal,
+            pays_fee: Pays::Yes,
+        };
+        // block with `ExtrinsicFailed` and `NewAccount` of `System`
+        let mut events = Compact(2u32).encode();
+        events.extend(Phase::ApplyExtrinsic(0).encode());
+        events.extend(&[0, 1]);
+        events.extend(&[3, 1, 2, 0, 0, 0]);
+        events.extend(info.encode());
+        events.extend(Vec::<Hash>::new().encode());
+        events.extend(Phase::ApplyExtrinsic(0).encode());
+        events.extend(&[0, 3]);
+        events.extend(&[5u8; 32]);
+        events.extend(Vec::<Hash>::new().encode());
+
+        let decoder = EventsDecoder::try_from(metadata)?;
+        let decoded = decoder.decode_events(&mut events.as_slice())?;
+        let raw: Vec<&RawEvent> = decoded.iter().map(|record| &record.event).collect();
+        match &raw[..] {
+            [failed, new_account] => {
+                assert_eq!(
+                    failed.arguments[0],
+                    json!({ "module": { "index": 1, "error": 2 } })
+                );
+                assert!(matches!(
+                    SystemEvent::from_raw(failed)?,
+                    Some(SystemEvent::ExtrinsicFailed(
+                        DispatchError::Module {
+                            index: 1,
+                            error: 2,
+                            ..
+                        },
+                        DispatchInfo { weight: 7, .. },
+                    ))
+                ));
+                assert_eq!(new_account.variant, "NewAccount");
+            }
+            _ => panic!("unexpected events {:?}", decoded),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_weights_with_multiple_parts() -> Result<(), CodecError> {
+        // `Weight` of newer runtimes with `ref_time` and `proof_size`
+        let info = DispatchInfo::from_argument(&json!([[7, 3], "normal", "yes"]))?;
+        assert_eq!(info.weight, 7);
+        assert!(matches!(info.class, DispatchClass::Normal));
+        assert!(matches!(info.pays_fee, Pays::Yes));
+        assert!(DispatchInfo::from_argument(&json!([7, "normal"])).is_err());
+
+        Ok(())
     }
 }
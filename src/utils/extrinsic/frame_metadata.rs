@@ -21,6 +21,7 @@
 //! it should be removed entirely to an external module for shimming on to the
 //! codec-encoded metadata.
 
+use super::scale_info::{PortableRegistry, TypeId};
 use parity_scale_codec::{Decode, Error, Input};
 use parity_scale_codec::{Encode, Output};
 use serde::Serialize;
@@ -124,7 +125,7 @@ where
 
 pub type DecodeDifferentArray<B, O = B> = DecodeDifferent<&'static [B], Vec<O>>;
 
-type DecodeDifferentStr = DecodeDifferent<&'static str, StringBuf>;
+pub type DecodeDifferentStr = DecodeDifferent<&'static str, StringBuf>;
 
 /// All the metadata about a function.
 #[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize)]
@@ -365,6 +366,12 @@ pub enum RuntimeMetadata {
     V10(RuntimeMetadataDeprecated),
     /// Version 11 for runtime metadata.
     V11(RuntimeMetadataV11),
+    /// Version 12 for runtime metadata.
+    V12(RuntimeMetadataV12),
+    /// Version 13 for runtime metadata.
+    V13(RuntimeMetadataV13),
+    /// Version 14 for runtime metadata.
+    V14(RuntimeMetadataV14),
 }
 
 /// Enum that should fail.
@@ -403,5 +410,224 @@ pub struct ModuleMetadata {
     pub errors: DFnA<ErrorMetadata>,
 }
 
-type ODFnA<T> = Option<DFnA<T>>;
-type DFnA<T> = DecodeDifferent<FnEncode<&'static [T]>, Vec<T>>;
+pub type ODFnA<T> = Option<DFnA<T>>;
+pub type DFnA<T> = DecodeDifferent<FnEncode<&'static [T]>, Vec<T>>;
+
+/// The metadata of a runtime.
+#[derive(Eq, Encode, PartialEq, Decode, Serialize)]
+pub struct RuntimeMetadataV12 {
+    /// Metadata of all the modules.
+    pub modules: DecodeDifferentArray<ModuleMetadataV12>,
+    /// Metadata of the extrinsic.
+    pub extrinsic: ExtrinsicMetadata,
+}
+
+/// All metadata about an runtime module, adds explicit module index to V11 metadata.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct ModuleMetadataV12 {
+    pub name: DecodeDifferentStr,
+    pub storage: Option<DecodeDifferent<FnEncode<StorageMetadata>, StorageMetadata>>,
+    pub calls: ODFnA<FunctionMetadata>,
+    pub event: ODFnA<EventMetadata>,
+    pub constants: DFnA<ModuleConstantMetadata>,
+    pub errors: DFnA<ErrorMetadata>,
+    /// Define the index of the module, this index will be used for the encoding of module event,
+    /// call and origin variants.
+    pub index: u8,
+}
+
+/// The metadata of a runtime.
+#[derive(Eq, Encode, PartialEq, Decode, Serialize)]
+pub struct RuntimeMetadataV13 {
+    /// Metadata of all the modules.
+    pub modules: DecodeDifferentArray<ModuleMetadataV13>,
+    /// Metadata of the extrinsic.
+    pub extrinsic: ExtrinsicMetadata,
+}
+
+/// All metadata about an runtime module, adds storage maps with multiple keys to V12 metadata.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct ModuleMetadataV13 {
+    pub name: DecodeDifferentStr,
+    pub storage: Option<DecodeDifferent<FnEncode<StorageMetadataV13>, StorageMetadataV13>>,
+    pub calls: ODFnA<FunctionMetadata>,
+    pub event: ODFnA<EventMetadata>,
+    pub constants: DFnA<ModuleConstantMetadata>,
+    pub errors: DFnA<ErrorMetadata>,
+    /// Define the index of the module, this index will be used for the encoding of module event,
+    /// call and origin variants.
+    pub index: u8,
+}
+
+/// All metadata of the storage.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct StorageMetadataV13 {
+    /// The common prefix used by all storage entries.
+    pub prefix: DecodeDifferent<&'static str, StringBuf>,
+    pub entries: DecodeDifferent<&'static [StorageEntryMetadataV13], Vec<StorageEntryMetadataV13>>,
+}
+
+/// All the metadata about one storage entry.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct StorageEntryMetadataV13 {
+    pub name: DecodeDifferentStr,
+    pub modifier: StorageEntryModifier,
+    pub ty: StorageEntryTypeV13,
+    pub default: ByteGetter,
+    pub documentation: DecodeDifferentArray<&'static str, StringBuf>,
+}
+
+/// A storage entry type.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize)]
+pub enum StorageEntryTypeV13 {
+    Plain(DecodeDifferentStr),
+    Map {
+        hasher: StorageHasher,
+        key: DecodeDifferentStr,
+        value: DecodeDifferentStr,
+        // is_linked flag previously, unused now to keep backwards compat
+        unused: bool,
+    },
+    DoubleMap {
+        hasher: StorageHasher,
+        key1: DecodeDifferentStr,
+        key2: DecodeDifferentStr,
+        value: DecodeDifferentStr,
+        key2_hasher: StorageHasher,
+    },
+    NMap {
+        keys: DecodeDifferentArray<&'static str, StringBuf>,
+        hashers: DecodeDifferentArray<StorageHasher>,
+        value: DecodeDifferentStr,
+    },
+}
+
+/// The metadata of a runtime, types are described in the `types` registry and referenced by their
+/// ID.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub struct RuntimeMetadataV14 {
+    /// Type registry containing all types used in the metadata.
+    pub types: PortableRegistry,
+    /// Metadata of all the pallets.
+    pub pallets: Vec<PalletMetadata>,
+    /// Metadata of the extrinsic.
+    pub extrinsic: ExtrinsicMetadataV14,
+    /// The type of the `Runtime`.
+    pub ty: TypeId,
+}
+
+/// All metadata about an runtime pallet.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub struct PalletMetadata {
+    /// Pallet name.
+    pub name: StringBuf,
+    /// Pallet storage metadata.
+    pub storage: Option<PalletStorageMetadata>,
+    /// Pallet calls metadata.
+    pub calls: Option<PalletCallMetadata>,
+    /// Pallet event metadata.
+    pub event: Option<PalletEventMetadata>,
+    /// Pallet constants metadata.
+    pub constants: Vec<PalletConstantMetadata>,
+    /// Pallet error metadata.
+    pub error: Option<PalletErrorMetadata>,
+    /// Define the index of the pallet, this index will be used for the encoding of pallet event,
+    /// call and origin variants.
+    pub index: u8,
+}
+
+/// All metadata of the pallet's storage.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub struct PalletStorageMetadata {
+    /// The common prefix used by all storage entries.
+    pub prefix: StringBuf,
+    /// Metadata for all storage entries.
+    pub entries: Vec<StorageEntryMetadataV14>,
+}
+
+/// All the metadata about one storage entry.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub struct StorageEntryMetadataV14 {
+    /// Variable name of the storage entry.
+    pub name: StringBuf,
+    /// An `Option` modifier of that storage entry.
+    pub modifier: StorageEntryModifier,
+    /// Type of the value stored in the entry.
+    pub ty: StorageEntryTypeV14,
+    /// Default value (SCALE encoded).
+    pub default: Vec<u8>,
+    /// Storage entry documentation.
+    pub docs: Vec<StringBuf>,
+}
+
+/// A storage entry type.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub enum StorageEntryTypeV14 {
+    /// Plain storage entry (just the value).
+    Plain(TypeId),
+    /// A storage map.
+    Map {
+        /// One or more hashers, should be one hasher per key element.
+        hashers: Vec<StorageHasher>,
+        /// The type of the key, can be a tuple with elements for each of the hashers.
+        key: TypeId,
+        /// The type of the value.
+        value: TypeId,
+    },
+}
+
+/// Metadata for all calls in a pallet.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub struct PalletCallMetadata {
+    /// The corresponding enum type for the pallet call.
+    pub ty: TypeId,
+}
+
+/// Metadata about the pallet event type.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub struct PalletEventMetadata {
+    /// The Event type.
+    pub ty: TypeId,
+}
+
+/// Metadata about one pallet constant.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub struct PalletConstantMetadata {
+    /// Name of the pallet constant.
+    pub name: StringBuf,
+    /// Type of the pallet constant.
+    pub ty: TypeId,
+    /// Value stored in the constant (SCALE encoded).
+    pub value: Vec<u8>,
+    /// Documentation of the constant.
+    pub docs: Vec<StringBuf>,
+}
+
+/// Metadata about a pallet error.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub struct PalletErrorMetadata {
+    /// The error type information.
+    pub ty: TypeId,
+}
+
+/// Metadata of the extrinsic used by the runtime.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub struct ExtrinsicMetadataV14 {
+    /// The type of the extrinsic.
+    pub ty: TypeId,
+    /// Extrinsic version.
+    pub version: u8,
+    /// The signed extensions in the order they appear in the extrinsic.
+    pub signed_extensions: Vec<SignedExtensionMetadata>,
+}
+
+/// Metadata of an extrinsic's signed extension.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Serialize, Debug)]
+pub struct SignedExtensionMetadata {
+    /// The unique signed extension identifier, which may be different from the type name.
+    pub identifier: StringBuf,
+    /// The type of the signed extension, with the data to be included in the extrinsic.
+    pub ty: TypeId,
+    /// The type of the additional signed data, with the data to be included in the signed payload.
+    pub additional_signed: TypeId,
+}
@@ -0,0 +1,89 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+use crate::utils::{
+    extrinsic::xt_primitives::{AccountId, MultiSignature},
+    substrate::blake2_256,
+};
+use multibase::Base;
+use sp_core::{ecdsa, ed25519, sr25519, Pair};
+
+/// SS58 address format of generic substrate chains
+const SS58_GENERIC_PREFIX: u8 = 42;
+
+/// Substrate account, that signs extrinsics.
+pub trait ExtrinsicSigner {
+    /// Gets ID of account, that signs extrinsics.
+    fn account_id(&self) -> AccountId;
+
+    /// Signs payload of an extrinsic.
+    ///
+    /// # Arguments
+    /// * `payload` - encoded payload, see `SignedPayload`
+    fn sign(&self, payload: &[u8]) -> MultiSignature;
+}
+
+impl ExtrinsicSigner for sr25519::Pair {
+    fn account_id(&self) -> AccountId {
+        let mut account_id = [0; 32];
+        account_id.copy_from_slice(self.public().as_ref());
+        account_id
+    }
+
+    fn sign(&self, payload: &[u8]) -> MultiSignature {
+        let mut signature = [0; 64];
+        signature.copy_from_slice(Pair::sign(self, payload).as_ref());
+        MultiSignature::Sr25519(signature)
+    }
+}
+
+impl ExtrinsicSigner for ed25519::Pair {
+    fn account_id(&self) -> AccountId {
+        let mut account_id = [0; 32];
+        account_id.copy_from_slice(self.public().as_ref());
+        account_id
+    }
+
+    fn sign(&self, payload: &[u8]) -> MultiSignature {
+        let mut signature = [0; 64];
+        signature.copy_from_slice(Pair::sign(self, payload).as_ref());
+        MultiSignature::Ed25519(signature)
+    }
+}
+
+impl ExtrinsicSigner for ecdsa::Pair {
+    /// Accounts of ECDSA keys are the hash of their compressed public key.
+    fn account_id(&self) -> AccountId {
+        blake2_256(self.public().as_ref())
+    }
+
+    fn sign(&self, payload: &[u8]) -> MultiSignature {
+        let mut signature = [0; 65];
+        signature.copy_from_slice(Pair::sign(self, payload).as_ref());
+        MultiSignature::Ecdsa(signature)
+    }
+}
+
+/// Encodes an account ID as SS58 address with the generic substrate prefix.
+pub fn to_ss58(account_id: &AccountId) -> String {
+    let mut bytes = vec![SS58_GENERIC_PREFIX];
+    bytes.extend_from_slice(account_id);
+    let mut checksum_input = b"SS58PRE".to_vec();
+    checksum_input.extend_from_slice(&bytes);
+    let checksum = blake2_rfc::blake2b::blake2b(64, &[], &checksum_input);
+    bytes.extend_from_slice(&checksum.as_bytes()[0..2]);
+    Base::Base58Btc.encode(bytes)
+}
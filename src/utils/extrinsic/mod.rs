@@ -18,8 +18,10 @@
 pub extern crate parity_scale_codec;
 pub mod events;
 pub mod frame_metadata;
+pub mod keypair;
 pub mod node_metadata;
 pub mod rpc;
+pub mod scale_info;
 pub mod xt_primitives;
 
 /// Generates an Unchecked extrinsic for a given module and call passed as a &str.
@@ -44,9 +46,7 @@ macro_rules! compose_extrinsic {
             info!("Composing generic extrinsic for module {:?} and call {:?}", $module, $call);
             let call = $crate::compose_call!($metadata.clone(), $module, $call $(, ($args)) *);
 
-            UncheckedExtrinsicV4 {
-                function: call.clone(),
-            }
+            UncheckedExtrinsicV4::new_unsigned(call.clone())
 		}
     };
 }
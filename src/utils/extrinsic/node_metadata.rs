@@ -14,14 +14,30 @@
 // You should have received a copy of the GNU General Public License
 // along with substrate-subxt.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::frame_metadata::{
-    DecodeDifferent,
-    RuntimeMetadata,
-    RuntimeMetadataPrefixed,
-    StorageEntryModifier,
-    StorageEntryType,
-    StorageHasher,
-    META_RESERVED,
+use super::{
+    frame_metadata::{
+        DFnA,
+        DecodeDifferent,
+        DecodeDifferentStr,
+        ErrorMetadata,
+        EventMetadata,
+        ExtrinsicMetadata,
+        FnEncode,
+        FunctionMetadata,
+        ODFnA,
+        PalletMetadata,
+        RuntimeMetadata,
+        RuntimeMetadataPrefixed,
+        RuntimeMetadataV14,
+        StorageEntryModifier,
+        StorageEntryType,
+        StorageEntryTypeV13,
+        StorageEntryTypeV14,
+        StorageHasher,
+        StorageMetadataV13,
+        META_RESERVED,
+    },
+    scale_info::{PortableRegistry, PortableType, Type, TypeDef, Variant},
 };
 use crate::utils::substrate;
 use parity_scale_codec::{Decode, Encode};
@@ -38,12 +54,18 @@ pub enum MetadataError {
     ModuleWithEventsNotFound(u8),
     #[error("Event not found")]
     EventNotFound(u8),
+    #[error("Module with errors not found")]
+    ModuleWithErrorsNotFound(u8),
+    #[error("Error not found")]
+    ErrorNotFound(u8),
     #[error("Storage not found")]
     StorageNotFound(&'static str),
     #[error("Storage type error")]
     StorageTypeError,
     #[error("Map value type error")]
     MapValueTypeError,
+    #[error("Storage key can not be decoded")]
+    StorageKeyDecodeError,
 }
 
 #[derive(Clone, Debug)]
@@ -51,7 +73,9 @@ pub struct Metadata {
     modules: HashMap<String, ModuleMetadata>,
     modules_with_calls: HashMap<String, ModuleWithCalls>,
     modules_with_events: HashMap<String, ModuleWithEvents>,
-    modules_with_errors: HashMap<String, ModuleWithEvents>,
+    modules_with_errors: HashMap<String, ModuleWithErrors>,
+    module_error_len: usize,
+    signed_extensions: Vec<SignedExtension>,
 }
 
 impl Metadata {
@@ -82,99 +106,168 @@ impl Metadata {
             .ok_or(MetadataError::ModuleWithEventsNotFound(module_index))
     }
 
-    pub fn module_with_errors(&self, module_index: u8) -> Result<&ModuleWithEvents, MetadataError> {
+    pub fn module_with_errors(&self, module_index: u8) -> Result<&ModuleWithErrors, MetadataError> {
         self.modules_with_errors
             .values()
             .find(|&module| module.index == module_index)
-            .ok_or(MetadataError::ModuleWithEventsNotFound(module_index))
+            .ok_or(MetadataError::ModuleWithErrorsNotFound(module_index))
+    }
+
+    /// Number of bytes of the error in `DispatchError::Module`. Runtimes have extended it from the
+    /// error index to 4 bytes, the error index is followed by data of nested errors then.
+    pub fn module_error_len(&self) -> usize {
+        self.module_error_len
+    }
+
+    /// Signed extensions of extrinsics in the order their data is encoded.
+    pub fn signed_extensions(&self) -> &[SignedExtension] {
+        &self.signed_extensions
     }
 
     pub fn parse(metadata: RuntimeMetadataPrefixed) -> Result<Self, MetadataError> {
         if metadata.0 != META_RESERVED {
             return Err(ConversionError::InvalidPrefix.into());
         }
-        let meta = match metadata.1 {
-            RuntimeMetadata::V11(meta) => meta,
-            _ => return Err(ConversionError::InvalidVersion.into()),
-        };
-        let mut modules = HashMap::new();
-        let mut modules_with_calls = HashMap::new();
-        let mut modules_with_events = HashMap::new();
-        let mut modules_with_errors = HashMap::new();
-        for module in convert(meta.modules)?.into_iter() {
-            let module_name = convert(module.name.clone())?;
-
-            let mut storage_map = HashMap::new();
-            if let Some(storage) = module.storage {
-                let storage = convert(storage)?;
-                let module_prefix = convert(storage.prefix)?;
-                for entry in convert(storage.entries)?.into_iter() {
-                    let storage_prefix = convert(entry.name.clone())?;
-                    let entry = convert_entry(
-                        module_prefix.clone().to_string(),
-                        storage_prefix.clone().to_string(),
-                        entry,
-                    )?;
-                    storage_map.insert(storage_prefix.to_string(), entry);
+        let mut builder = MetadataBuilder::default();
+        match metadata.1 {
+            RuntimeMetadata::V11(meta) => {
+                builder.signed_extensions = convert_signed_extensions(meta.extrinsic)?;
+                for module in convert(meta.modules)?.into_iter() {
+                    let storage = convert_storage(module.storage)?;
+                    builder.insert(convert_module(
+                        module.name,
+                        storage,
+                        module.calls,
+                        module.event,
+                        module.errors,
+                        None,
+                    )?);
                 }
             }
-            modules.insert(
-                module_name.clone().to_string(),
-                ModuleMetadata {
-                    name: module_name.clone().to_string(),
-                    storage: storage_map,
-                },
-            );
-
-            if let Some(calls) = module.calls {
-                let mut call_map = HashMap::new();
-                for (index, call) in convert(calls)?.into_iter().enumerate() {
-                    let name = convert(call.name)?;
-                    call_map.insert(name.to_string(), index as u8);
+            RuntimeMetadata::V12(meta) => {
+                builder.signed_extensions = convert_signed_extensions(meta.extrinsic)?;
+                for module in convert(meta.modules)?.into_iter() {
+                    let storage = convert_storage(module.storage)?;
+                    builder.insert(convert_module(
+                        module.name,
+                        storage,
+                        module.calls,
+                        module.event,
+                        module.errors,
+                        Some(module.index),
+                    )?);
                 }
-                modules_with_calls.insert(
-                    module_name.clone().to_string(),
-                    ModuleWithCalls {
-                        index: modules_with_calls.len() as u8,
-                        name: module_name.clone().to_string(),
-                        calls: call_map,
-                    },
-                );
             }
-            if let Some(events) = module.event {
-                let mut event_map = HashMap::new();
-                for (index, event) in convert(events)?.into_iter().enumerate() {
-                    event_map.insert(index as u8, convert_event(event)?);
+            RuntimeMetadata::V13(meta) => {
+                builder.signed_extensions = convert_signed_extensions(meta.extrinsic)?;
+                for module in convert(meta.modules)?.into_iter() {
+                    let storage = convert_storage_v13(module.storage)?;
+                    builder.insert(convert_module(
+                        module.name,
+                        storage,
+                        module.calls,
+                        module.event,
+                        module.errors,
+                        Some(module.index),
+                    )?);
                 }
-                modules_with_events.insert(
-                    module_name.clone().to_string(),
-                    ModuleWithEvents {
-                        index: modules_with_events.len() as u8,
-                        name: module_name.clone().to_string(),
-                        events: event_map,
-                    },
-                );
             }
-
-            let mut error_map = HashMap::new();
-            for (index, event) in convert(module.errors)?.into_iter().enumerate() {
-                error_map.insert(index as u8, convert_error(event)?);
+            RuntimeMetadata::V14(meta) => {
+                let RuntimeMetadataV14 {
+                    types,
+                    pallets,
+                    extrinsic,
+                    ..
+                } = meta;
+                for pallet in pallets.into_iter() {
+                    builder.insert(convert_pallet(&types, pallet)?);
+                }
+                for extension in extrinsic.signed_extensions.into_iter() {
+                    builder.signed_extensions.push(SignedExtension {
+                        identifier: extension.identifier,
+                        extra: Some(type_name(&types, extension.ty.0)?),
+                        additional_signed: Some(type_name(&types, extension.additional_signed.0)?),
+                    });
+                }
+                builder.module_error_len = Some(module_error_len(&types)?);
             }
-            modules_with_errors.insert(
-                module_name.clone().to_string(),
+            _ => return Err(ConversionError::InvalidVersion.into()),
+        };
+        Ok(builder.build())
+    }
+}
+
+/// Version independent parts of a module, collected while parsing metadata.
+struct ModuleParts {
+    name: String,
+    /// explicit module index, V11 metadata uses position of module instead
+    index: Option<u8>,
+    storage: HashMap<String, StorageMetadata>,
+    calls: Option<HashMap<String, u8>>,
+    events: Option<HashMap<u8, ModuleEventMetadata>>,
+    errors: HashMap<u8, ModuleErrorMetadata>,
+}
+
+#[derive(Default)]
+struct MetadataBuilder {
+    modules: HashMap<String, ModuleMetadata>,
+    modules_with_calls: HashMap<String, ModuleWithCalls>,
+    modules_with_events: HashMap<String, ModuleWithEvents>,
+    modules_with_errors: HashMap<String, ModuleWithErrors>,
+    /// length of module errors, if it is known from the metadata
+    module_error_len: Option<usize>,
+    signed_extensions: Vec<SignedExtension>,
+}
+
+impl MetadataBuilder {
+    fn insert(&mut self, module: ModuleParts) {
+        self.modules.insert(
+            module.name.clone(),
+            ModuleMetadata {
+                name: module.name.clone(),
+                storage: module.storage,
+            },
+        );
+        if let Some(calls) = module.calls {
+            self.modules_with_calls.insert(
+                module.name.clone(),
+                ModuleWithCalls {
+                    index: module.index.unwrap_or(self.modules_with_calls.len() as u8),
+                    name: module.name.clone(),
+                    calls,
+                },
+            );
+        }
+        if let Some(events) = module.events {
+            self.modules_with_events.insert(
+                module.name.clone(),
                 ModuleWithEvents {
-                    index: modules_with_errors.len() as u8,
-                    name: module_name.clone().to_string(),
-                    events: error_map,
+                    index: module.index.unwrap_or(self.modules_with_events.len() as u8),
+                    name: module.name.clone(),
+                    events,
                 },
             );
         }
-        Ok(Metadata {
-            modules,
-            modules_with_calls,
-            modules_with_events,
-            modules_with_errors,
-        })
+        self.modules_with_errors.insert(
+            module.name.clone(),
+            ModuleWithErrors {
+                index: module.index.unwrap_or(self.modules_with_errors.len() as u8),
+                name: module.name.clone(),
+                errors: module.errors,
+            },
+        );
+    }
+
+    fn build(self) -> Metadata {
+        Metadata {
+            modules: self.modules,
+            modules_with_calls: self.modules_with_calls,
+            modules_with_events: self.modules_with_events,
+            modules_with_errors: self.modules_with_errors,
+            // runtimes before V14 metadata only encode the error index
+            module_error_len: self.module_error_len.unwrap_or(1),
+            signed_extensions: self.signed_extensions,
+        }
     }
 }
 
@@ -220,19 +313,74 @@ impl ModuleWithEvents {
     }
 }
 
+/// Signed extension of extrinsics, types of its data are only known from V14 metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedExtension {
+    pub identifier: String,
+    /// Type of the data included in extrinsics, e.g. `Compact<u32>` for `CheckNonce`
+    pub extra: Option<String>,
+    /// Type of the data only included in the signed payload, e.g. `u32` for `CheckSpecVersion`
+    pub additional_signed: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModuleWithErrors {
+    index: u8,
+    name: String,
+    errors: HashMap<u8, ModuleErrorMetadata>,
+}
+
+impl ModuleWithErrors {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn error(&self, index: u8) -> Result<&ModuleErrorMetadata, MetadataError> {
+        self.errors
+            .get(&index)
+            .ok_or(MetadataError::ErrorNotFound(index))
+    }
+}
+
+/// Layout of a storage entry, independent from the metadata version.
+#[derive(Clone, Debug)]
+pub enum StorageType {
+    Plain,
+    Map {
+        hasher: StorageHasher,
+    },
+    DoubleMap {
+        hasher: StorageHasher,
+        key2_hasher: StorageHasher,
+    },
+    NMap {
+        hashers: Vec<StorageHasher>,
+    },
+}
+
 #[derive(Clone, Debug)]
 pub struct StorageMetadata {
     module_prefix: String,
     storage_prefix: String,
     modifier: StorageEntryModifier,
-    ty: StorageEntryType,
+    ty: StorageType,
     default: Vec<u8>,
 }
 
 impl StorageMetadata {
+    pub fn get_value(&self) -> Result<StorageValue, MetadataError> {
+        match &self.ty {
+            StorageType::Plain => Ok(StorageValue {
+                module_prefix: self.module_prefix.as_bytes().to_vec(),
+                storage_prefix: self.storage_prefix.as_bytes().to_vec(),
+            }),
+            _ => Err(MetadataError::StorageTypeError),
+        }
+    }
+
     pub fn get_map<K: Encode, V: Decode + Clone>(&self) -> Result<StorageMap<K, V>, MetadataError> {
         match &self.ty {
-            StorageEntryType::Map { hasher, .. } => {
+            StorageType::Map { hasher } => {
                 let module_prefix = self.module_prefix.as_bytes().to_vec();
                 let storage_prefix = self.storage_prefix.as_bytes().to_vec();
                 let hasher = hasher.to_owned();
@@ -254,6 +402,35 @@ impl StorageMetadata {
             _ => Err(MetadataError::StorageTypeError),
         }
     }
+
+    pub fn get_double_map<K: Encode, Q: Encode, V: Decode + Clone>(
+        &self,
+    ) -> Result<StorageDoubleMap<K, Q, V>, MetadataError> {
+        match &self.ty {
+            StorageType::DoubleMap {
+                hasher,
+                key2_hasher,
+            } => {
+                let default = Decode::decode(&mut &self.default[..])
+                    .map_err(|_| MetadataError::MapValueTypeError)?;
+
+                debug!(
+                    "double map for '{}' '{}' has hashers {:?} and {:?}",
+                    self.module_prefix, self.storage_prefix, hasher, key2_hasher
+                );
+                Ok(StorageDoubleMap {
+                    _marker: PhantomData,
+                    _marker2: PhantomData,
+                    module_prefix: self.module_prefix.as_bytes().to_vec(),
+                    storage_prefix: self.storage_prefix.as_bytes().to_vec(),
+                    hasher: hasher.to_owned(),
+                    key2_hasher: key2_hasher.to_owned(),
+                    default,
+                })
+            }
+            _ => Err(MetadataError::StorageTypeError),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -262,6 +439,14 @@ pub struct StorageValue {
     storage_prefix: Vec<u8>,
 }
 
+impl StorageValue {
+    pub fn key(&self) -> StorageKey {
+        let mut bytes = substrate::twox_128(&self.module_prefix).to_vec();
+        bytes.extend(&substrate::twox_128(&self.storage_prefix)[..]);
+        StorageKey(bytes)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct StorageMap<K, V> {
     _marker: PhantomData<K>,
@@ -273,13 +458,31 @@ pub struct StorageMap<K, V> {
 
 impl<K: Encode, V: Decode + Clone> StorageMap<K, V> {
     pub fn key(&self, key: K) -> StorageKey {
+        let mut bytes = self.prefix().0;
+        bytes.extend(key_hash(&key, &self.hasher));
+        StorageKey(bytes)
+    }
+
+    /// Common prefix of the keys of all entries of the map.
+    pub fn prefix(&self) -> StorageKey {
         let mut bytes = substrate::twox_128(&self.module_prefix).to_vec();
         bytes.extend(&substrate::twox_128(&self.storage_prefix)[..]);
-        bytes.extend(key_hash(&key, &self.hasher));
         StorageKey(bytes)
     }
 }
 
+impl<K: Encode + Decode, V: Decode + Clone> StorageMap<K, V> {
+    /// Gets the map key back from a storage key, only possible for maps with `Identity` or
+    /// concatenating hashers.
+    pub fn decode_key(&self, key: &StorageKey) -> Result<K, MetadataError> {
+        let prefix = self.prefix().0;
+        if !key.0.starts_with(&prefix) {
+            return Err(MetadataError::StorageKeyDecodeError);
+        }
+        decode_key_hash(&key.0[prefix.len()..], &self.hasher)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct StorageDoubleMap<K, Q, V> {
     _marker: PhantomData<K>,
@@ -291,16 +494,72 @@ pub struct StorageDoubleMap<K, Q, V> {
     default: V,
 }
 
+impl<K: Encode, Q: Encode, V: Decode + Clone> StorageDoubleMap<K, Q, V> {
+    pub fn key(&self, key1: K, key2: Q) -> StorageKey {
+        let mut bytes = self.prefix(key1).0;
+        bytes.extend(key_hash(&key2, &self.key2_hasher));
+        StorageKey(bytes)
+    }
+
+    /// Common prefix of the keys of all entries, that share `key1`.
+    pub fn prefix(&self, key1: K) -> StorageKey {
+        let mut bytes = substrate::twox_128(&self.module_prefix).to_vec();
+        bytes.extend(&substrate::twox_128(&self.storage_prefix)[..]);
+        bytes.extend(key_hash(&key1, &self.hasher));
+        StorageKey(bytes)
+    }
+}
+
+impl<K: Encode, Q: Encode + Decode, V: Decode + Clone> StorageDoubleMap<K, Q, V> {
+    /// Gets the second key back from a storage key with the prefix of `key1`, only possible for
+    /// maps with an `Identity` or concatenating `key2_hasher`.
+    pub fn decode_key2(&self, key1: K, key: &StorageKey) -> Result<Q, MetadataError> {
+        let prefix = self.prefix(key1).0;
+        if !key.0.starts_with(&prefix) {
+            return Err(MetadataError::StorageKeyDecodeError);
+        }
+        decode_key_hash(&key.0[prefix.len()..], &self.key2_hasher)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ModuleEventMetadata {
     pub name: String,
     arguments: Vec<EventArg>,
+    documentation: Vec<String>,
 }
 
 impl ModuleEventMetadata {
     pub fn arguments(&self) -> Vec<EventArg> {
         self.arguments.to_vec()
     }
+
+    /// Documentation lines of event, joined with spaces.
+    pub fn documentation(&self) -> String {
+        join_documentation(&self.documentation)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ModuleErrorMetadata {
+    pub name: String,
+    documentation: Vec<String>,
+}
+
+impl ModuleErrorMetadata {
+    /// Documentation lines of error, joined with spaces.
+    pub fn documentation(&self) -> String {
+        join_documentation(&self.documentation)
+    }
+}
+
+fn join_documentation(documentation: &[String]) -> String {
+    documentation
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
 }
 
 /// Naive representation of event argument types, supports current set of substrate EventArg types.
@@ -313,27 +572,57 @@ pub enum EventArg {
     Primitive(String),
     Vec(Box<EventArg>),
     Tuple(Vec<EventArg>),
+    Option(Box<EventArg>),
+    Compact(Box<EventArg>),
+    Array(Box<EventArg>, usize),
 }
 
 impl FromStr for EventArg {
     type Err = ConversionError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.starts_with("Vec<") {
-            if s.ends_with('>') {
-                Ok(EventArg::Vec(Box::new(s[4..s.len() - 1].parse()?)))
-            } else {
-                Err(ConversionError::InvalidEventArg(
+        let s = s.trim();
+        if let Some(inner) = generic_param(s, "Vec")? {
+            Ok(EventArg::Vec(Box::new(inner.parse()?)))
+        } else if let Some(inner) = generic_param(s, "Option")? {
+            Ok(EventArg::Option(Box::new(inner.parse()?)))
+        } else if let Some(inner) = generic_param(s, "Compact")? {
+            Ok(EventArg::Compact(Box::new(inner.parse()?)))
+        } else if s.starts_with('[') {
+            if !s.ends_with(']') {
+                return Err(ConversionError::InvalidEventArg(
                     s.to_string(),
-                    "Expected closing `>` for `Vec`",
-                ))
+                    "Expecting closing `]` for array",
+                ));
             }
+            let inner = &s[1..s.len() - 1];
+            let separator = inner.rfind(';').ok_or_else(|| {
+                ConversionError::InvalidEventArg(s.to_string(), "Expecting `;` in array")
+            })?;
+            let len = inner[separator + 1..].trim().parse().map_err(|_| {
+                ConversionError::InvalidEventArg(s.to_string(), "Invalid array length")
+            })?;
+            Ok(EventArg::Array(Box::new(inner[..separator].parse()?), len))
         } else if s.starts_with('(') {
             if s.ends_with(')') {
                 let mut args = Vec::new();
-                for arg in s[1..s.len() - 1].split(',') {
-                    let arg = arg.trim().parse()?;
-                    args.push(arg)
+                let inner = &s[1..s.len() - 1];
+                // split at top level commas only, nested tuples and generics contain commas as well
+                let mut depth = 0;
+                let mut start = 0;
+                for (i, c) in inner.char_indices() {
+                    match c {
+                        '(' | '<' | '[' => depth += 1,
+                        ')' | '>' | ']' => depth -= 1,
+                        ',' if depth == 0 => {
+                            args.push(inner[start..i].trim().parse()?);
+                            start = i + 1;
+                        }
+                        _ => (),
+                    }
+                }
+                if !inner[start..].trim().is_empty() {
+                    args.push(inner[start..].trim().parse()?);
                 }
                 Ok(EventArg::Tuple(args))
             } else {
@@ -343,11 +632,42 @@ impl FromStr for EventArg {
                 ))
             }
         } else {
-            Ok(EventArg::Primitive(s.to_string()))
+            Ok(EventArg::Primitive(primitive_name(s).to_string()))
         }
     }
 }
 
+/// Gets the parameter of a generic type `name<T>`, e.g. `u8` for `Vec<u8>`, and also accepts
+/// paths like `sp_std::vec::Vec<u8>`.
+fn generic_param<'a>(s: &'a str, name: &str) -> Result<Option<&'a str>, ConversionError> {
+    let start = match s.find('<') {
+        Some(start) if primitive_name(&s[..start]) == name => start,
+        _ => return Ok(None),
+    };
+    if s.ends_with('>') {
+        Ok(Some(&s[start + 1..s.len() - 1]))
+    } else {
+        Err(ConversionError::InvalidEventArg(
+            s.to_string(),
+            "Expected closing `>` for generic type",
+        ))
+    }
+}
+
+/// Strips paths and generic parameters from type names, e.g. `AccountId` for `T::AccountId` or
+/// `<T as frame_system::Config>::AccountId` and `BalanceOf` for `BalanceOf<T>`.
+fn primitive_name(s: &str) -> &str {
+    let name = match s.rfind("::") {
+        Some(index) if !s[index..].contains('>') => &s[index + 2..],
+        _ => s,
+    };
+    match name.find('<') {
+        Some(index) if index > 0 => &name[..index],
+        _ => name,
+    }
+    .trim()
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ConversionError {
     #[error("Invalid prefix")]
@@ -358,6 +678,10 @@ pub enum ConversionError {
     ExpectedDecoded,
     #[error("Invalid event arg {0}")]
     InvalidEventArg(String, &'static str),
+    #[error("Type {0} not found in type registry")]
+    TypeNotFound(u32),
+    #[error("Invalid type {0}; {1}")]
+    InvalidType(u32, &'static str),
 }
 
 fn convert<B: 'static, O: 'static>(dd: DecodeDifferent<B, O>) -> Result<O, ConversionError> {
@@ -367,6 +691,22 @@ fn convert<B: 'static, O: 'static>(dd: DecodeDifferent<B, O>) -> Result<O, Conve
     }
 }
 
+fn convert_signed_extensions(
+    extrinsic: ExtrinsicMetadata,
+) -> Result<Vec<SignedExtension>, ConversionError> {
+    extrinsic
+        .signed_extensions
+        .into_iter()
+        .map(|identifier| {
+            Ok(SignedExtension {
+                identifier: convert(identifier)?,
+                extra: None,
+                additional_signed: None,
+            })
+        })
+        .collect()
+}
+
 fn convert_event(
     event: super::frame_metadata::EventMetadata,
 ) -> Result<ModuleEventMetadata, ConversionError> {
@@ -376,32 +716,356 @@ fn convert_event(
         let arg = arg.parse::<EventArg>()?;
         arguments.push(arg);
     }
-    Ok(ModuleEventMetadata { name, arguments })
+    let documentation = convert(event.documentation)?;
+    Ok(ModuleEventMetadata {
+        name,
+        arguments,
+        documentation,
+    })
 }
 
 fn convert_error(
-    event: super::frame_metadata::ErrorMetadata,
-) -> Result<ModuleEventMetadata, ConversionError> {
-    let name = convert(event.name)?;
-    let arguments = Vec::new();
-    Ok(ModuleEventMetadata { name, arguments })
+    error: super::frame_metadata::ErrorMetadata,
+) -> Result<ModuleErrorMetadata, ConversionError> {
+    let name = convert(error.name)?;
+    let documentation = convert(error.documentation)?;
+    Ok(ModuleErrorMetadata {
+        name,
+        documentation,
+    })
 }
 
 fn convert_entry(
     module_prefix: String,
     storage_prefix: String,
-    entry: super::frame_metadata::StorageEntryMetadata,
-) -> Result<StorageMetadata, ConversionError> {
-    let default = convert(entry.default)?;
-    Ok(StorageMetadata {
+    modifier: StorageEntryModifier,
+    ty: StorageType,
+    default: Vec<u8>,
+) -> StorageMetadata {
+    StorageMetadata {
         module_prefix,
         storage_prefix,
-        modifier: entry.modifier,
-        ty: entry.ty,
+        modifier,
+        ty,
         default,
+    }
+}
+
+fn convert_module(
+    name: DecodeDifferentStr,
+    storage: HashMap<String, StorageMetadata>,
+    calls: ODFnA<FunctionMetadata>,
+    event: ODFnA<EventMetadata>,
+    errors: DFnA<ErrorMetadata>,
+    index: Option<u8>,
+) -> Result<ModuleParts, ConversionError> {
+    let calls = match calls {
+        Some(calls) => {
+            let mut call_map = HashMap::new();
+            for (index, call) in convert(calls)?.into_iter().enumerate() {
+                let name = convert(call.name)?;
+                call_map.insert(name.to_string(), index as u8);
+            }
+            Some(call_map)
+        }
+        None => None,
+    };
+    let events = match event {
+        Some(events) => {
+            let mut event_map = HashMap::new();
+            for (index, event) in convert(events)?.into_iter().enumerate() {
+                event_map.insert(index as u8, convert_event(event)?);
+            }
+            Some(event_map)
+        }
+        None => None,
+    };
+    let mut error_map = HashMap::new();
+    for (index, error) in convert(errors)?.into_iter().enumerate() {
+        error_map.insert(index as u8, convert_error(error)?);
+    }
+    Ok(ModuleParts {
+        name: convert(name)?,
+        index,
+        storage,
+        calls,
+        events,
+        errors: error_map,
+    })
+}
+
+fn convert_storage(
+    storage: Option<
+        DecodeDifferent<
+            FnEncode<super::frame_metadata::StorageMetadata>,
+            super::frame_metadata::StorageMetadata,
+        >,
+    >,
+) -> Result<HashMap<String, StorageMetadata>, ConversionError> {
+    let mut storage_map = HashMap::new();
+    if let Some(storage) = storage {
+        let storage = convert(storage)?;
+        let module_prefix = convert(storage.prefix)?;
+        for entry in convert(storage.entries)?.into_iter() {
+            let storage_prefix = convert(entry.name)?;
+            let ty = match entry.ty {
+                StorageEntryType::Plain(_) => StorageType::Plain,
+                StorageEntryType::Map { hasher, .. } => StorageType::Map { hasher },
+                StorageEntryType::DoubleMap {
+                    hasher,
+                    key2_hasher,
+                    ..
+                } => StorageType::DoubleMap {
+                    hasher,
+                    key2_hasher,
+                },
+            };
+            let entry = convert_entry(
+                module_prefix.clone(),
+                storage_prefix.clone(),
+                entry.modifier,
+                ty,
+                convert(entry.default)?,
+            );
+            storage_map.insert(storage_prefix, entry);
+        }
+    }
+    Ok(storage_map)
+}
+
+fn convert_storage_v13(
+    storage: Option<DecodeDifferent<FnEncode<StorageMetadataV13>, StorageMetadataV13>>,
+) -> Result<HashMap<String, StorageMetadata>, ConversionError> {
+    let mut storage_map = HashMap::new();
+    if let Some(storage) = storage {
+        let storage = convert(storage)?;
+        let module_prefix = convert(storage.prefix)?;
+        for entry in convert(storage.entries)?.into_iter() {
+            let storage_prefix = convert(entry.name)?;
+            let ty = match entry.ty {
+                StorageEntryTypeV13::Plain(_) => StorageType::Plain,
+                StorageEntryTypeV13::Map { hasher, .. } => StorageType::Map { hasher },
+                StorageEntryTypeV13::DoubleMap {
+                    hasher,
+                    key2_hasher,
+                    ..
+                } => StorageType::DoubleMap {
+                    hasher,
+                    key2_hasher,
+                },
+                StorageEntryTypeV13::NMap { hashers, .. } => StorageType::NMap {
+                    hashers: convert(hashers)?,
+                },
+            };
+            let entry = convert_entry(
+                module_prefix.clone(),
+                storage_prefix.clone(),
+                entry.modifier,
+                ty,
+                convert(entry.default)?,
+            );
+            storage_map.insert(storage_prefix, entry);
+        }
+    }
+    Ok(storage_map)
+}
+
+fn convert_pallet(
+    registry: &PortableRegistry,
+    pallet: PalletMetadata,
+) -> Result<ModuleParts, ConversionError> {
+    let mut storage_map = HashMap::new();
+    if let Some(storage) = pallet.storage {
+        for entry in storage.entries.into_iter() {
+            let ty = match entry.ty {
+                StorageEntryTypeV14::Plain(_) => StorageType::Plain,
+                StorageEntryTypeV14::Map { mut hashers, .. } => match hashers.len() {
+                    1 => StorageType::Map {
+                        hasher: hashers.remove(0),
+                    },
+                    2 => StorageType::DoubleMap {
+                        hasher: hashers.remove(0),
+                        key2_hasher: hashers.remove(0),
+                    },
+                    _ => StorageType::NMap { hashers },
+                },
+            };
+            let entry = convert_entry(
+                storage.prefix.clone(),
+                entry.name.clone(),
+                entry.modifier,
+                ty,
+                entry.default,
+            );
+            storage_map.insert(entry.storage_prefix.clone(), entry);
+        }
+    }
+
+    let calls = match pallet.calls {
+        Some(calls) => Some(
+            variants(registry, calls.ty.0)?
+                .iter()
+                .map(|variant| (variant.name.clone(), variant.index))
+                .collect(),
+        ),
+        None => None,
+    };
+    let events = match pallet.event {
+        Some(event) => {
+            let mut event_map = HashMap::new();
+            for variant in variants(registry, event.ty.0)? {
+                let mut arguments = Vec::new();
+                for field in variant.fields.iter() {
+                    arguments.push(type_name(registry, field.ty.0)?.parse::<EventArg>()?);
+                }
+                event_map.insert(
+                    variant.index,
+                    ModuleEventMetadata {
+                        name: variant.name.clone(),
+                        arguments,
+                        documentation: variant.docs.clone(),
+                    },
+                );
+            }
+            Some(event_map)
+        }
+        None => None,
+    };
+    let mut error_map = HashMap::new();
+    if let Some(error) = pallet.error {
+        for variant in variants(registry, error.ty.0)? {
+            error_map.insert(
+                variant.index,
+                ModuleErrorMetadata {
+                    name: variant.name.clone(),
+                    documentation: variant.docs.clone(),
+                },
+            );
+        }
+    }
+
+    Ok(ModuleParts {
+        name: pallet.name,
+        index: Some(pallet.index),
+        storage: storage_map,
+        calls,
+        events,
+        errors: error_map,
     })
 }
 
+/// Gets variants of an enum type from type registry.
+fn variants(registry: &PortableRegistry, id: u32) -> Result<&Vec<Variant>, ConversionError> {
+    match &registry
+        .resolve(id)
+        .ok_or(ConversionError::TypeNotFound(id))?
+        .type_def
+    {
+        TypeDef::Variant(variant) => Ok(&variant.variants),
+        _ => Err(ConversionError::InvalidType(id, "expected enum type")),
+    }
+}
+
+/// Gets the number of bytes of the error in `sp_runtime::ModuleError`, which is either the error
+/// index or an array of bytes starting with it.
+fn module_error_len(registry: &PortableRegistry) -> Result<usize, ConversionError> {
+    let module_error = registry
+        .types
+        .iter()
+        .find(|portable_type| portable_type.ty.path == ["sp_runtime", "ModuleError"]);
+    let (id, fields) = match module_error {
+        Some(PortableType {
+            id,
+            ty:
+                Type {
+                    type_def: TypeDef::Composite(composite),
+                    ..
+                },
+        }) => (*id, &composite.fields),
+        // runtimes without the type only encode the error index
+        _ => return Ok(1),
+    };
+    let error = fields
+        .iter()
+        .find(|field| field.name.as_deref() == Some("error"))
+        .ok_or(ConversionError::InvalidType(
+            id,
+            "module error without error",
+        ))?;
+    let error_type = registry
+        .resolve(error.ty.0)
+        .ok_or(ConversionError::TypeNotFound(error.ty.0))?;
+    match &error_type.type_def {
+        TypeDef::Primitive(_) if type_name(registry, error.ty.0)? == "u8" => Ok(1),
+        TypeDef::Array(array) if type_name(registry, array.type_param.0)? == "u8" => {
+            Ok(array.len as usize)
+        }
+        _ => Err(ConversionError::InvalidType(
+            error.ty.0,
+            "unsupported module error",
+        )),
+    }
+}
+
+/// Builds a type name for a type from the registry, that can be used for dynamic decoding, e.g.
+/// `Vec<u8>`, `(u32, u64)` or `[u8; 32]`. Structs are described by the types of their fields,
+/// enums by their name.
+fn type_name(registry: &PortableRegistry, id: u32) -> Result<String, ConversionError> {
+    let ty = registry
+        .resolve(id)
+        .ok_or(ConversionError::TypeNotFound(id))?;
+    let name = match &ty.type_def {
+        TypeDef::Primitive(primitive) => primitive.name().to_string(),
+        TypeDef::Sequence(sequence) => {
+            format!("Vec<{}>", type_name(registry, sequence.type_param.0)?)
+        }
+        TypeDef::Array(array) => format!(
+            "[{}; {}]",
+            type_name(registry, array.type_param.0)?,
+            array.len
+        ),
+        TypeDef::Tuple(tuple) => format!(
+            "({})",
+            tuple
+                .fields
+                .iter()
+                .map(|field| type_name(registry, field.0))
+                .collect::<Result<Vec<String>, ConversionError>>()?
+                .join(", ")
+        ),
+        TypeDef::Compact(compact) => {
+            format!("Compact<{}>", type_name(registry, compact.type_param.0)?)
+        }
+        TypeDef::Composite(composite) => match composite.fields.len() {
+            1 => type_name(registry, composite.fields[0].ty.0)?,
+            _ => format!(
+                "({})",
+                composite
+                    .fields
+                    .iter()
+                    .map(|field| type_name(registry, field.ty.0))
+                    .collect::<Result<Vec<String>, ConversionError>>()?
+                    .join(", ")
+            ),
+        },
+        TypeDef::Variant(_) => {
+            let name = ty
+                .path
+                .last()
+                .ok_or(ConversionError::InvalidType(id, "enum without name"))?;
+            match (name.as_str(), ty.type_params.first()) {
+                ("Option", Some(param)) => match param.ty {
+                    Some(param_ty) => format!("Option<{}>", type_name(registry, param_ty.0)?),
+                    None => name.to_string(),
+                },
+                _ => name.to_string(),
+            }
+        }
+        TypeDef::BitSequence(_) => "BitVec".to_string(),
+    };
+    Ok(name)
+}
+
 /// generates the key's hash depending on the StorageHasher selected
 fn key_hash<K: Encode>(key: &K, hasher: &StorageHasher) -> Vec<u8> {
     let encoded_key = key.encode();
@@ -420,6 +1084,289 @@ fn key_hash<K: Encode>(key: &K, hasher: &StorageHasher) -> Vec<u8> {
         StorageHasher::Blake2_256 => substrate::blake2_256(&encoded_key).to_vec(),
         StorageHasher::Twox128 => substrate::twox_128(&encoded_key).to_vec(),
         StorageHasher::Twox256 => substrate::twox_256(&encoded_key).to_vec(),
-        StorageHasher::Twox64Concat => substrate::twox_64(&encoded_key).to_vec(),
+        StorageHasher::Twox64Concat => substrate::twox_64(&encoded_key)
+            .iter()
+            .chain(encoded_key.iter())
+            .cloned()
+            .collect::<Vec<_>>(),
+    }
+}
+
+/// decodes a key from its hash, if the StorageHasher keeps the encoded key
+fn decode_key_hash<K: Decode>(hash: &[u8], hasher: &StorageHasher) -> Result<K, MetadataError> {
+    let encoded_key = match hasher {
+        StorageHasher::Identity => hash,
+        StorageHasher::Blake2_128Concat if hash.len() >= 16 => &hash[16..],
+        StorageHasher::Twox64Concat if hash.len() >= 8 => &hash[8..],
+        _ => return Err(MetadataError::StorageKeyDecodeError),
+    };
+    Decode::decode(&mut &encoded_key[..]).map_err(|_| MetadataError::StorageKeyDecodeError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        mock_node::{METADATA_V12, METADATA_V13, METADATA_V14},
+        substrate::{blake2_128, twox_128, twox_64},
+    };
+    use sp_core::H256;
+
+    fn parse_fixture(fixture: &str) -> Metadata {
+        let bytes = hex::decode(fixture.trim().trim_start_matches("0x")).expect("invalid fixture");
+        let metadata = RuntimeMetadataPrefixed::decode(&mut &bytes[..])
+            .expect("could not decode metadata fixture");
+        Metadata::parse(metadata).expect("could not parse metadata fixture")
+    }
+
+    fn storage<'a>(metadata: &'a Metadata, module: &str, name: &'static str) -> &'a StorageType {
+        &metadata
+            .module(module)
+            .and_then(|module| module.storage(name))
+            .expect("missing storage entry")
+            .ty
+    }
+
+    fn assert_module_indices(metadata: &Metadata) -> Result<(), MetadataError> {
+        assert_eq!(metadata.module_with_calls("System")?.index, 0);
+        assert_eq!(metadata.module_with_calls("DidModule")?.index, 1);
+        assert_eq!(metadata.module_with_calls("Utility")?.index, 2);
+        assert_eq!(metadata.module_with_calls("Timestamp")?.index, 5);
+        assert_eq!(metadata.module_with_calls("Assets")?.index, 9);
+        assert_eq!(metadata.module_with_events(9)?.name(), "Assets");
+        assert!(matches!(
+            metadata.module_with_events(5),
+            Err(MetadataError::ModuleWithEventsNotFound(5))
+        ));
+
+        let did_module = metadata.module_with_calls("DidModule")?;
+        assert_eq!(did_module.calls["create_did"], 0);
+        assert_eq!(did_module.calls["update_did_detail"], 3);
+        assert_eq!(metadata.module_with_calls("Utility")?.calls["batch"], 0);
+        assert_eq!(metadata.module_with_calls("Utility")?.calls["batch_all"], 2);
+
+        let created = metadata.module_with_events(1)?.event(0)?;
+        assert_eq!(created.name, "Created");
+        assert_eq!(
+            created.arguments(),
+            vec![
+                EventArg::Vec(Box::new(EventArg::Primitive("u8".to_string()))),
+                EventArg::Vec(Box::new(EventArg::Primitive("u8".to_string()))),
+                EventArg::Primitive("u64".to_string()),
+            ]
+        );
+        let interrupted = metadata.module_with_events(2)?.event(0)?;
+        assert_eq!(interrupted.name, "BatchInterrupted");
+        assert_eq!(
+            interrupted.arguments(),
+            vec![
+                EventArg::Primitive("u32".to_string()),
+                EventArg::Primitive("DispatchError".to_string()),
+            ]
+        );
+
+        let not_owner = metadata.module_with_errors(1)?.error(2)?;
+        assert_eq!(not_owner.name, "NotDidOwner");
+        assert_eq!(
+            not_owner.documentation(),
+            "Sender is not the owner of the DID"
+        );
+
+        Ok(())
+    }
+
+    /// Builds keys of maps with `Twox64Concat` and `Blake2_128Concat` hashers and decodes them.
+    fn assert_storage_keys(metadata: &Metadata) -> Result<(), MetadataError> {
+        let mut prefix = twox_128(b"System").to_vec();
+        prefix.extend(&twox_128(b"BlockHash")[..]);
+        let block_hash = metadata
+            .module("System")?
+            .storage("BlockHash")?
+            .get_map::<u32, H256>()?;
+        let key = block_hash.key(7);
+        let mut expected = prefix.clone();
+        expected.extend(&twox_64(&7u32.encode())[..]);
+        expected.extend(7u32.encode());
+        assert_eq!(key.0, expected);
+        assert_eq!(block_hash.prefix().0, prefix);
+        assert_eq!(block_hash.decode_key(&key)?, 7);
+
+        let did = H256::repeat_byte(1);
+        let count = metadata
+            .module("DidModule")?
+            .storage("DidsDetailsCount")?
+            .get_map::<H256, u32>()?;
+        let key = count.key(did);
+        let mut expected = twox_128(b"DidModule").to_vec();
+        expected.extend(&twox_128(b"DidsDetailsCount")[..]);
+        expected.extend(&blake2_128(did.as_bytes())[..]);
+        expected.extend(did.as_bytes());
+        assert_eq!(key.0, expected);
+        assert_eq!(count.decode_key(&key)?, did);
+        assert!(matches!(
+            count.decode_key(&block_hash.key(7)),
+            Err(MetadataError::StorageKeyDecodeError)
+        ));
+
+        let account = metadata
+            .module("Assets")?
+            .storage("Account")?
+            .get_double_map::<u32, [u8; 32], Vec<u8>>()?;
+        let key = account.key(3, [2; 32]);
+        assert!(key.0.starts_with(&account.prefix(3).0));
+        assert_eq!(account.decode_key2(3, &key)?, [2; 32]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_metadata_v12() -> Result<(), MetadataError> {
+        let metadata = parse_fixture(METADATA_V12);
+
+        assert_module_indices(&metadata)?;
+        let extensions = metadata.signed_extensions();
+        assert_eq!(extensions.len(), 7);
+        assert_eq!(extensions[4].identifier, "CheckNonce");
+        assert_eq!(extensions[4].extra, None);
+        assert_eq!(
+            metadata.module_with_calls("Utility")?.calls["as_derivative"],
+            1
+        );
+        assert!(matches!(
+            storage(&metadata, "System", "Account"),
+            StorageType::Map {
+                hasher: StorageHasher::Blake2_128Concat
+            }
+        ));
+        assert!(matches!(
+            storage(&metadata, "System", "BlockHash"),
+            StorageType::Map {
+                hasher: StorageHasher::Twox64Concat
+            }
+        ));
+        assert!(matches!(
+            storage(&metadata, "Assets", "Account"),
+            StorageType::DoubleMap {
+                hasher: StorageHasher::Blake2_128Concat,
+                key2_hasher: StorageHasher::Blake2_128Concat,
+            }
+        ));
+        assert!(matches!(
+            storage(&metadata, "System", "Events"),
+            StorageType::Plain
+        ));
+        assert_storage_keys(&metadata)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_metadata_v13() -> Result<(), MetadataError> {
+        let metadata = parse_fixture(METADATA_V13);
+
+        assert_module_indices(&metadata)?;
+        assert!(matches!(
+            storage(&metadata, "Assets", "Account"),
+            StorageType::DoubleMap { .. }
+        ));
+        match storage(&metadata, "Assets", "Approvals") {
+            StorageType::NMap { hashers } => {
+                assert_eq!(hashers, &vec![StorageHasher::Blake2_128Concat; 3])
+            }
+            ty => panic!("expected NMap, got {:?}", ty),
+        }
+        assert!(matches!(
+            metadata
+                .module("Assets")?
+                .storage("Approvals")?
+                .get_map::<u32, Vec<u8>>(),
+            Err(MetadataError::StorageTypeError)
+        ));
+        assert_storage_keys(&metadata)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_metadata_v14() -> Result<(), MetadataError> {
+        let metadata = parse_fixture(METADATA_V14);
+
+        assert_module_indices(&metadata)?;
+        assert_eq!(metadata.module_error_len(), 1);
+        let extensions = metadata.signed_extensions();
+        assert_eq!(
+            extensions
+                .iter()
+                .map(|extension| extension.identifier.as_str())
+                .collect::<Vec<&str>>(),
+            vec![
+                "CheckSpecVersion",
+                "CheckTxVersion",
+                "CheckGenesis",
+                "CheckMortality",
+                "CheckNonce",
+                "CheckWeight",
+                "ChargeTransactionPayment",
+            ]
+        );
+        assert_eq!(extensions[0].additional_signed.as_deref(), Some("u32"));
+        assert_eq!(extensions[5].extra.as_deref(), Some("()"));
+        assert_eq!(extensions[5].additional_signed.as_deref(), Some("()"));
+        // call, event and error variants have explicit indices with gaps
+        assert!(!metadata
+            .module_with_calls("Utility")?
+            .calls
+            .contains_key("as_derivative"));
+        assert_eq!(
+            metadata.module_with_calls("System")?.calls["remark_with_event"],
+            7
+        );
+        assert_eq!(metadata.module_with_calls("Assets")?.calls["transfer"], 8);
+        assert_eq!(metadata.module_with_events(0)?.event(5)?.name, "Remarked");
+        assert!(matches!(
+            metadata.module_with_events(0)?.event(2),
+            Err(MetadataError::EventNotFound(2))
+        ));
+        let transferred = metadata.module_with_events(9)?.event(2)?;
+        assert_eq!(transferred.name, "Transferred");
+        assert_eq!(
+            transferred.arguments(),
+            vec![
+                EventArg::Primitive("u32".to_string()),
+                EventArg::Array(Box::new(EventArg::Primitive("u8".to_string())), 32),
+                EventArg::Array(Box::new(EventArg::Primitive("u8".to_string())), 32),
+                EventArg::Primitive("u128".to_string()),
+            ]
+        );
+        let frozen = metadata.module_with_errors(9)?.error(6)?;
+        assert_eq!(frozen.name, "Frozen");
+        assert_eq!(frozen.documentation(), "The origin account is frozen.");
+        assert_eq!(
+            metadata.module_with_errors(0)?.error(5)?.name,
+            "CallFiltered"
+        );
+
+        assert!(matches!(
+            storage(&metadata, "System", "BlockHash"),
+            StorageType::Map {
+                hasher: StorageHasher::Twox64Concat
+            }
+        ));
+        assert!(matches!(
+            storage(&metadata, "Assets", "Account"),
+            StorageType::DoubleMap {
+                hasher: StorageHasher::Blake2_128Concat,
+                key2_hasher: StorageHasher::Blake2_128Concat,
+            }
+        ));
+        match storage(&metadata, "Assets", "Approvals") {
+            StorageType::NMap { hashers } => {
+                assert_eq!(hashers, &vec![StorageHasher::Blake2_128Concat; 3])
+            }
+            ty => panic!("expected NMap, got {:?}", ty),
+        }
+        assert_storage_keys(&metadata)?;
+
+        Ok(())
     }
 }
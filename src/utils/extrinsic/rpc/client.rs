@@ -15,18 +15,23 @@
 
 */
 
-use futures::channel::mpsc::Sender as ThreadOut;
+use futures::channel::{mpsc::UnboundedSender as ThreadOut, oneshot};
 use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
 
 #[cfg(not(target_arch = "wasm32"))]
-use ws::{CloseCode, Handler, Handshake, Message, Result, Sender};
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use ws::{CloseCode, Handler, Handshake, Message, Result};
 
-#[cfg(target_arch = "wasm32")]
-use std::error::Error;
 #[cfg(target_arch = "wasm32")]
 use web_sys::WebSocket;
 
-#[derive(Debug, PartialEq)]
+/// Status of a submitted extrinsic, e.g. to configure until which status an extrinsic is watched.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum XtStatus {
     Finalized,
     InBlock,
@@ -35,256 +40,350 @@ pub enum XtStatus {
     Future,
     Error,
     Unknown,
+    /// Extrinsic has been replaced by another extrinsic with the same sender and nonce
+    Usurped,
+    /// Extrinsic has been dropped from the transaction pool, e.g. because the pool is full
+    Dropped,
+    /// Extrinsic is no longer valid, e.g. because its nonce has been used by another extrinsic
+    Invalid,
+    /// Block that included the extrinsic has been retracted from the best chain, the extrinsic is
+    /// usually included in another block then
+    Retracted,
+    /// Block that included the extrinsic has not been finalized in time
+    FinalityTimeout,
 }
 
+/// Outgoing side of the websocket connection.
 #[cfg(not(target_arch = "wasm32"))]
-pub type OnMessageFn = fn(msg: Message, out: Sender, result: ThreadOut<String>) -> Result<()>;
-
+pub type Out = ws::Sender;
 #[cfg(target_arch = "wasm32")]
-pub type OnMessageFn =
-    fn(msg: &str, out: &WebSocket, result: ThreadOut<String>) -> Result<(), Box<dyn Error>>;
+pub type Out = WebSocket;
 
-#[cfg(not(target_arch = "wasm32"))]
-pub struct RpcClient {
-    pub out: Sender,
-    pub request: String,
-    pub result: ThreadOut<String>,
-    pub on_message_fn: OnMessageFn,
+/// Receiver of a request that is still waiting for its response.
+pub enum Pending {
+    /// Plain request, the `result` (or the `error`) of the response is passed to the sender.
+    Request(oneshot::Sender<std::result::Result<Value, String>>),
+    /// Subscription request, all messages of the subscription are passed to the sender.
+    Subscription(Subscription),
 }
-#[cfg(not(target_arch = "wasm32"))]
-impl Handler for RpcClient {
-    fn on_open(&mut self, _: Handshake) -> Result<()> {
-        self.out.send(self.request.clone())?;
-        Ok(())
-    }
 
-    fn on_message(&mut self, msg: Message) -> Result<()> {
-        (self.on_message_fn)(msg, self.out.clone(), self.result.clone())
+/// Active subscription on the websocket connection.
+pub struct Subscription {
+    pub sender: ThreadOut<String>,
+    pub unsubscribe_method: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConnectionStatus {
+    Closed,
+    Connecting,
+    Open,
+}
+
+/// State of a websocket connection shared between the `RpcClient` and the connection handler.
+/// Requests and subscriptions are multiplexed over the connection by their JSON-RPC id.
+pub struct RpcState {
+    pub status: ConnectionStatus,
+    pub out: Option<Out>,
+    generation: u64,
+    next_id: u64,
+    queued: Vec<String>,
+    pending: HashMap<u64, Pending>,
+    subscriptions: HashMap<String, Subscription>,
+}
+
+impl Default for RpcState {
+    fn default() -> Self {
+        RpcState {
+            status: ConnectionStatus::Closed,
+            out: None,
+            generation: 0,
+            next_id: 1,
+            queued: Vec::new(),
+            pending: HashMap::new(),
+            subscriptions: HashMap::new(),
+        }
     }
 }
-#[cfg(not(target_arch = "wasm32"))]
-pub fn on_subscription_msg(msg: Message, _out: Sender, result: ThreadOut<String>) -> Result<()> {
-    let retstr = msg.as_text()?;
-    if let Ok(value) = serde_json::from_str::<serde_json::Value>(retstr) {
-        match value["id"].as_str() {
-            Some(_idstr) => {}
+
+impl RpcState {
+    /// Registers a request and returns its serialized message. Messages have to be passed to
+    /// `send` afterwards.
+    pub fn register(&mut self, method: &str, params: Value, pending: Pending) -> String {
+        let id = self.next_id();
+        self.pending.insert(id, pending);
+        json_rpc_message(method, params, id)
+    }
+
+    /// Marks state as connecting and returns an identifier for the new connection. Events of
+    /// previous connections are ignored from now on.
+    pub fn on_connect(&mut self) -> u64 {
+        self.generation += 1;
+        self.status = ConnectionStatus::Connecting;
+        self.generation
+    }
+
+    /// Sends a message if the connection is open, otherwise keeps it until the connection has been
+    /// opened.
+    pub fn send(&mut self, message: String) -> std::result::Result<(), String> {
+        match (&self.status, &self.out) {
+            (ConnectionStatus::Open, Some(out)) => send_message(out, &message),
             _ => {
-                // subscriptions
-                debug!("no id field found in response. must be subscription");
-                match value["method"].as_str() {
-                    Some("state_storage") => {
-                        serde_json::to_string(&value["params"]["result"])
-                            .map(|head| {
-                                result.clone().try_send(head).unwrap_or_else(|_| {
-                                    _out.close(CloseCode::Normal).ok();
-                                });
-                            })
-                            .unwrap_or_else(|_| error!("Could not parse header"));
-                    }
-                    Some("chain_finalizedHead") => {
-                        serde_json::to_string(&value["params"]["result"])
-                            .map(|head| {
-                                result.clone().try_send(head).unwrap_or_else(|_| {
-                                    _out.close(CloseCode::Normal).ok();
-                                });
-                            })
-                            .unwrap_or_else(|_| error!("Could not parse header"));
+                self.queued.push(message);
+                Ok(())
+            }
+        }
+    }
+
+    /// Marks connection as opened and sends all messages that have been queued while connecting.
+    pub fn on_open(&mut self, generation: u64, out: Out) {
+        if generation != self.generation {
+            return;
+        }
+        debug!("rpc connection opened");
+        for message in self.queued.drain(..) {
+            if let Err(err) = send_message(&out, &message) {
+                error!("could not send queued message; {}", &err);
+            }
+        }
+        self.out = Some(out);
+        self.status = ConnectionStatus::Open;
+    }
+
+    /// Marks connection as closed. Pending requests and subscriptions are dropped, so their
+    /// receivers are notified about the connection loss.
+    pub fn on_close(&mut self, generation: u64) {
+        if generation != self.generation {
+            return;
+        }
+        debug!("rpc connection closed");
+        self.status = ConnectionStatus::Closed;
+        self.out = None;
+        self.queued.clear();
+        self.pending.clear();
+        self.subscriptions.clear();
+    }
+
+    /// Dispatches an incoming message to the request or subscription it belongs to.
+    ///
+    /// # Returns
+    /// * `Vec<String>` - messages that have to be sent in reaction to the incoming message
+    pub fn on_message(&mut self, msg: &str) -> Vec<String> {
+        let value: Value = match serde_json::from_str(msg) {
+            Ok(value) => value,
+            Err(err) => {
+                error!("could not parse rpc message; {}", &err);
+                return Vec::new();
+            }
+        };
+
+        if let Some(id) = parse_id(&value["id"]) {
+            match self.pending.remove(&id) {
+                Some(Pending::Request(sender)) => {
+                    let result = match value["error"].as_object() {
+                        Some(_) => Err(value["error"].to_string()),
+                        None => Ok(value["result"].clone()),
+                    };
+                    sender.send(result).ok();
+                }
+                Some(Pending::Subscription(mut subscription)) => {
+                    match parse_subscription_id(&value["result"]) {
+                        Some(subscription_id) => {
+                            debug!("subscription {} started", &subscription_id);
+                            self.subscriptions.insert(subscription_id, subscription);
+                        }
+                        None => {
+                            // pass error to subscriber, subscription ends here
+                            subscription.sender.unbounded_send(msg.to_string()).ok();
+                        }
                     }
-                    _ => error!("unsupported method"),
                 }
+                None => debug!("ignoring response for unknown request id {}", id),
+            }
+            return Vec::new();
+        }
+
+        let subscription_id = match parse_subscription_id(&value["params"]["subscription"]) {
+            Some(subscription_id) => subscription_id,
+            None => {
+                error!("unsupported message: {}", msg);
+                return Vec::new();
             }
         };
-    }
-    Ok(())
-}
-#[cfg(not(target_arch = "wasm32"))]
-pub fn on_extrinsic_msg_until_finalized(
-    msg: Message,
-    out: Sender,
-    result: ThreadOut<String>,
-) -> Result<()> {
-    let retstr = msg.as_text()?;
-    match parse_status(retstr) {
-        (XtStatus::Finalized, val) => end_process(out, result, val),
-        (XtStatus::Error, e) => end_process(out, result, e),
-        (XtStatus::Future, _) => {
-            warn!("extrinsic has 'future' status. aborting");
-            end_process(out, result, None);
+        let closed = match self.subscriptions.get(&subscription_id) {
+            // sending only fails if the receiver has been dropped
+            Some(subscription) => subscription.sender.unbounded_send(msg.to_string()).is_err(),
+            None => {
+                debug!(
+                    "ignoring message for unknown subscription {}",
+                    &subscription_id
+                );
+                false
+            }
+        };
+        if closed {
+            if let Some(subscription) = self.subscriptions.remove(&subscription_id) {
+                debug!("subscription {} has been dropped", &subscription_id);
+                let id = self.next_id();
+                return vec![json_rpc_message(
+                    &subscription.unsubscribe_method,
+                    json!([subscription_id]),
+                    id,
+                )];
+            }
         }
-        _ => (),
-    };
-    Ok(())
-}
-#[cfg(not(target_arch = "wasm32"))]
-pub fn on_extrinsic_msg_until_in_block(
-    msg: Message,
-    out: Sender,
-    result: ThreadOut<String>,
-) -> Result<()> {
-    let retstr = msg.as_text()?;
-    match parse_status(retstr) {
-        (XtStatus::Finalized, val) => end_process(out, result, val),
-        (XtStatus::InBlock, val) => end_process(out, result, val),
-        (XtStatus::Future, _) => end_process(out, result, None),
-        (XtStatus::Error, _) => end_process(out, result, None),
-        _ => (),
-    };
-    Ok(())
+        Vec::new()
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
 }
+
 #[cfg(not(target_arch = "wasm32"))]
-pub fn on_extrinsic_msg_until_broadcast(
-    msg: Message,
-    out: Sender,
-    result: ThreadOut<String>,
-) -> Result<()> {
-    let retstr = msg.as_text()?;
-    match parse_status(retstr) {
-        (XtStatus::Finalized, val) => end_process(out, result, val),
-        (XtStatus::Broadcast, _) => end_process(out, result, None),
-        (XtStatus::Future, _) => end_process(out, result, None),
-        (XtStatus::Error, _) => end_process(out, result, None),
-        _ => (),
-    };
-    Ok(())
+pub struct RpcHandler {
+    pub out: ws::Sender,
+    pub state: Arc<Mutex<RpcState>>,
+    pub generation: u64,
 }
+
 #[cfg(not(target_arch = "wasm32"))]
-pub fn on_extrinsic_msg_until_ready(
-    msg: Message,
-    out: Sender,
-    result: ThreadOut<String>,
-) -> Result<()> {
-    let retstr = msg.as_text()?;
-    match parse_status(retstr) {
-        (XtStatus::Finalized, val) => end_process(out, result, val),
-        (XtStatus::Ready, _) => end_process(out, result, None),
-        (XtStatus::Future, _) => end_process(out, result, None),
-        (XtStatus::Error, e) => end_process(out, result, e),
-        _ => (),
-    };
-    Ok(())
+impl Handler for RpcHandler {
+    fn on_open(&mut self, _: Handshake) -> Result<()> {
+        match self.state.lock() {
+            Ok(mut state) => state.on_open(self.generation, self.out.clone()),
+            Err(_) => {
+                error!("rpc state is poisoned, closing connection");
+                self.out.close(CloseCode::Error)?;
+            }
+        };
+        Ok(())
+    }
+
+    fn on_message(&mut self, msg: Message) -> Result<()> {
+        let msg = match msg {
+            Message::Text(msg) => msg,
+            Message::Binary(data) => {
+                // not part of JSON-RPC, must not close the connection shared by all requests
+                warn!("ignoring binary rpc message of {} bytes", data.len());
+                return Ok(());
+            }
+        };
+        let responses = match self.state.lock() {
+            Ok(mut state) => state.on_message(&msg),
+            Err(_) => {
+                error!("rpc state is poisoned, closing connection");
+                self.out.close(CloseCode::Error)?;
+                return Ok(());
+            }
+        };
+        for response in responses {
+            self.out.send(response)?;
+        }
+        Ok(())
+    }
+
+    fn on_close(&mut self, code: CloseCode, reason: &str) {
+        debug!("rpc connection closed with {:?}; {}", code, reason);
+        if let Ok(mut state) = self.state.lock() {
+            state.on_close(self.generation);
+        }
+    }
+
+    fn on_error(&mut self, err: ws::Error) {
+        error!("rpc connection error; {}", &err);
+        if let Ok(mut state) = self.state.lock() {
+            state.on_close(self.generation);
+        }
+    }
 }
+
 #[cfg(not(target_arch = "wasm32"))]
-fn end_process(out: Sender, result: ThreadOut<String>, value: Option<String>) {
-    // return result to calling thread
-    let val = value.unwrap_or_else(|| "".to_string());
-    result.clone().try_send(val).ok();
-    out.close(CloseCode::Normal).ok();
+fn send_message(out: &Out, message: &str) -> std::result::Result<(), String> {
+    out.send(message).map_err(|err| err.to_string())
 }
 
-// WASM implementation
-
 #[cfg(target_arch = "wasm32")]
-pub fn on_subscription_msg(
-    msg: &str,
-    out: &WebSocket,
-    result: ThreadOut<String>,
-) -> Result<(), Box<dyn Error>> {
-    let value: serde_json::Value = serde_json::from_str(msg)?;
-    match value["id"].as_str() {
-        Some(_idstr) => {}
-        _ => {
-            // subscriptions
-            debug!("no id field found in response. must be subscription");
-            debug!("method: {:?}", value["method"].as_str());
-            match value["method"].as_str() {
-                Some("state_storage") => {
-                    serde_json::to_string(&value["params"]["result"])
-                        .map(|head| {
-                            result.clone().try_send(head).unwrap_or_else(|_| {
-                                out.close_with_code(1000).ok();
-                            });
-                        })
-                        .unwrap_or_else(|_| error!("Could not parse header"));
-                }
-                Some("chain_finalizedHead") => {
-                    serde_json::to_string(&value["params"]["result"])
-                        .map(|head| {
-                            let _ = result.clone().try_send(head);
-                        })
-                        .unwrap_or_else(|_| error!("Could not parse header"));
-                }
-                _ => error!("unsupported method"),
-            }
-        }
-    };
-    Ok(())
+fn send_message(out: &Out, message: &str) -> std::result::Result<(), String> {
+    out.send_with_str(message)
+        .map_err(|err| err.as_string().unwrap_or_else(|| "".to_string()))
 }
 
-#[cfg(target_arch = "wasm32")]
-pub fn on_extrinsic_msg_until_finalized(
-    msg: &str,
-    out: &WebSocket,
-    result: ThreadOut<String>,
-) -> Result<(), Box<dyn Error>> {
-    match parse_status(msg) {
-        (XtStatus::Finalized, val) => end_process(out, result, val),
-        (XtStatus::Error, e) => end_process(out, result, e),
-        (XtStatus::Future, _) => {
-            warn!("extrinsic has 'future' status. aborting");
-            end_process(out, result, None);
-        }
-        _ => (),
-    };
-    Ok(())
+fn json_rpc_message(method: &str, params: Value, id: u64) -> String {
+    json!({
+        "method": method,
+        "params": params,
+        "jsonrpc": "2.0",
+        "id": id,
+    })
+    .to_string()
 }
 
-#[cfg(target_arch = "wasm32")]
-pub fn on_extrinsic_msg_until_in_block(
-    msg: &str,
-    out: &WebSocket,
-    result: ThreadOut<String>,
-) -> Result<(), Box<dyn Error>> {
-    match parse_status(msg) {
-        (XtStatus::Finalized, val) => end_process(out, result, val),
-        (XtStatus::InBlock, val) => end_process(out, result, val),
-        (XtStatus::Future, _) => end_process(out, result, None),
-        (XtStatus::Error, _) => end_process(out, result, None),
-        _ => (),
-    };
-    Ok(())
+fn parse_id(id: &Value) -> Option<u64> {
+    match id {
+        Value::Number(id) => id.as_u64(),
+        Value::String(id) => id.parse().ok(),
+        _ => None,
+    }
 }
 
-#[cfg(target_arch = "wasm32")]
-pub fn on_extrinsic_msg_until_broadcast(
-    msg: &str,
-    out: &WebSocket,
-    result: ThreadOut<String>,
-) -> Result<(), Box<dyn Error>> {
-    match parse_status(msg) {
-        (XtStatus::Finalized, val) => end_process(out, result, val),
-        (XtStatus::Broadcast, _) => end_process(out, result, None),
-        (XtStatus::Future, _) => end_process(out, result, None),
-        (XtStatus::Error, _) => end_process(out, result, None),
-        _ => (),
-    };
-    Ok(())
+fn parse_subscription_id(id: &Value) -> Option<String> {
+    match id {
+        Value::String(id) => Some(id.to_string()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
 }
 
-#[cfg(target_arch = "wasm32")]
-pub fn on_extrinsic_msg_until_ready(
-    msg: &str,
-    out: &WebSocket,
-    result: ThreadOut<String>,
-) -> Result<(), Box<dyn Error>> {
+/// Checks a message of an extrinsic watch subscription, whether waiting for `exit_on` is done.
+/// Waiting is done as well, if a status has been received, after which `exit_on` cannot be reached
+/// anymore, see `status_error`.
+///
+/// # Returns
+/// * `Option<(XtStatus, Option<String>)>` - status and status data if waiting can be stopped
+pub fn on_extrinsic_msg(msg: &str, exit_on: XtStatus) -> Option<(XtStatus, Option<String>)> {
     match parse_status(msg) {
-        (XtStatus::Finalized, val) => end_process(out, result, val),
-        (XtStatus::Ready, _) => end_process(out, result, None),
-        (XtStatus::Future, _) => end_process(out, result, None),
-        (XtStatus::Error, e) => end_process(out, result, e),
-        _ => (),
-    };
-    Ok(())
+        (XtStatus::Finalized, val) => Some((XtStatus::Finalized, val)),
+        (XtStatus::Future, _) => {
+            warn!("extrinsic has 'future' status. aborting");
+            Some((XtStatus::Future, None))
+        }
+        (status, val) if status_error(status, val.as_deref()).is_some() => Some((status, val)),
+        (status, val) if status == exit_on => Some((status, val)),
+        _ => None,
+    }
 }
 
-#[cfg(target_arch = "wasm32")]
-fn end_process(out: &WebSocket, result: ThreadOut<String>, value: Option<String>) {
-    // return result to calling thread
-    let val = value.unwrap_or_else(|| "".to_string());
-    result.clone().try_send(val).ok();
-    out.close_with_code(1000).ok();
+/// Gets the error of a status, after which an extrinsic will not be dispatched or will not be
+/// finalized anymore.
+///
+/// # Arguments
+/// * `status` - status of extrinsic
+/// * `data` - status data as returned by `parse_status`
+///
+/// # Returns
+/// * `Option<String>` - error message, `None` if `status` is no failure
+pub fn status_error(status: XtStatus, data: Option<&str>) -> Option<String> {
+    match status {
+        XtStatus::Error => Some(data.unwrap_or("unknown error").to_string()),
+        XtStatus::Future => Some("extrinsic has 'future' status".to_string()),
+        XtStatus::Dropped => Some("extrinsic has been dropped from transaction pool".to_string()),
+        XtStatus::Invalid => Some("extrinsic has become invalid".to_string()),
+        XtStatus::Usurped => Some(format!(
+            "extrinsic has been usurped by {}",
+            data.unwrap_or("another extrinsic")
+        )),
+        XtStatus::FinalityTimeout => Some(format!(
+            "block {} of extrinsic has not been finalized in time",
+            data.unwrap_or_default()
+        )),
+        _ => None,
+    }
 }
 
-fn parse_status(msg: &str) -> (XtStatus, Option<String>) {
+pub fn parse_status(msg: &str) -> (XtStatus, Option<String>) {
     let value: serde_json::Value = match serde_json::from_str(msg) {
         Ok(result) => result,
         Err(_) => {
@@ -307,7 +406,7 @@ fn parse_status(msg: &str) -> (XtStatus, Option<String>) {
             error!(
                 "extrinsic error code {}; {}",
                 match obj.get("code") {
-                    Some(result) => match result.as_u64() {
+                    Some(result) => match result.as_i64() {
                         Some(result) => result,
                         None => {
                             return (XtStatus::Error, None);
@@ -348,6 +447,24 @@ fn parse_status(msg: &str) -> (XtStatus, Option<String>) {
                 } else if let Some(array) = obj.get("broadcast") {
                     debug!("broadcast: {:?}", array);
                     (XtStatus::Broadcast, Some(array.to_string()))
+                } else if let Some(hash) = obj.get("usurped") {
+                    debug!("usurped: {:?}", hash);
+                    (
+                        XtStatus::Usurped,
+                        hash.as_str().map(|hash| hash.to_string()),
+                    )
+                } else if let Some(hash) = obj.get("retracted") {
+                    debug!("retracted: {:?}", hash);
+                    (
+                        XtStatus::Retracted,
+                        hash.as_str().map(|hash| hash.to_string()),
+                    )
+                } else if let Some(hash) = obj.get("finalityTimeout") {
+                    debug!("finalityTimeout: {:?}", hash);
+                    (
+                        XtStatus::FinalityTimeout,
+                        hash.as_str().map(|hash| hash.to_string()),
+                    )
                 } else {
                     (XtStatus::Unknown, None)
                 }
@@ -355,9 +472,313 @@ fn parse_status(msg: &str) -> (XtStatus, Option<String>) {
             None => match value["params"]["result"].as_str() {
                 Some("ready") => (XtStatus::Ready, None),
                 Some("future") => (XtStatus::Future, None),
+                Some("dropped") => (XtStatus::Dropped, None),
+                Some("invalid") => (XtStatus::Invalid, None),
                 Some(&_) => (XtStatus::Unknown, None),
                 None => (XtStatus::Unknown, None),
             },
         },
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::utils::extrinsic::rpc::RpcClient;
+    use futures::channel::mpsc::unbounded;
+    use std::{error::Error, thread};
+
+    fn register_request(
+        state: &mut RpcState,
+    ) -> (u64, oneshot::Receiver<std::result::Result<Value, String>>) {
+        let (sender, receiver) = oneshot::channel();
+        let message = state.register("state_getStorage", json!([]), Pending::Request(sender));
+        let id = serde_json::from_str::<Value>(&message).unwrap()["id"]
+            .as_u64()
+            .unwrap();
+        (id, receiver)
+    }
+
+    fn register_subscription(
+        state: &mut RpcState,
+    ) -> (u64, futures::channel::mpsc::UnboundedReceiver<String>) {
+        let (sender, receiver) = unbounded();
+        let message = state.register(
+            "state_subscribeStorage",
+            json!([]),
+            Pending::Subscription(Subscription {
+                sender,
+                unsubscribe_method: "state_unsubscribeStorage".to_string(),
+            }),
+        );
+        let id = serde_json::from_str::<Value>(&message).unwrap()["id"]
+            .as_u64()
+            .unwrap();
+        (id, receiver)
+    }
+
+    fn notification(subscription: &str, result: Value) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "state_storage",
+            "params": { "subscription": subscription, "result": result },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn routes_responses_by_id() {
+        let mut state = RpcState::default();
+        let (first_id, mut first) = register_request(&mut state);
+        let (second_id, mut second) = register_request(&mut state);
+        assert_ne!(first_id, second_id);
+
+        // responses may arrive in any order
+        state.on_message(
+            &json!({ "jsonrpc": "2.0", "result": "second", "id": second_id }).to_string(),
+        );
+        state.on_message(
+            &json!({
+                "jsonrpc": "2.0",
+                "error": { "code": -32601, "message": "failed" },
+                "id": first_id.to_string(),
+            })
+            .to_string(),
+        );
+
+        assert_eq!(second.try_recv(), Ok(Some(Ok(json!("second")))));
+        match first.try_recv() {
+            Ok(Some(Err(err))) => assert!(err.contains("failed")),
+            result => panic!("expected error response, got {:?}", result),
+        }
+        // responses for unknown ids are ignored
+        assert!(state
+            .on_message(&json!({ "jsonrpc": "2.0", "result": null, "id": 42 }).to_string())
+            .is_empty());
+    }
+
+    #[test]
+    fn routes_notifications_to_subscriptions() {
+        let mut state = RpcState::default();
+        let (first_id, mut first) = register_subscription(&mut state);
+        let (second_id, mut second) = register_subscription(&mut state);
+        state.on_message(
+            &json!({ "jsonrpc": "2.0", "result": "sub-1", "id": first_id }).to_string(),
+        );
+        state.on_message(&json!({ "jsonrpc": "2.0", "result": 2, "id": second_id }).to_string());
+
+        let first_message = notification("sub-1", json!("first"));
+        let second_message = notification("2", json!("second"));
+        state.on_message(&second_message);
+        state.on_message(&first_message);
+        state.on_message(&notification("unknown", json!("unknown")));
+
+        assert_eq!(first.try_next().ok(), Some(Some(first_message)));
+        assert_eq!(second.try_next().ok(), Some(Some(second_message)));
+        assert!(first.try_next().is_err());
+        assert!(second.try_next().is_err());
+    }
+
+    #[test]
+    fn keeps_notifications_of_subscribers_falling_behind() {
+        let mut state = RpcState::default();
+        let (id, mut receiver) = register_subscription(&mut state);
+        state.on_message(&json!({ "jsonrpc": "2.0", "result": "sub-1", "id": id }).to_string());
+
+        for index in 0..1000 {
+            state.on_message(&notification("sub-1", json!(index)));
+        }
+        for index in 0..1000 {
+            assert_eq!(
+                receiver.try_next().ok(),
+                Some(Some(notification("sub-1", json!(index))))
+            );
+        }
+        assert!(receiver.try_next().is_err());
+    }
+
+    #[test]
+    fn passes_failed_subscription_requests_to_subscriber() {
+        let mut state = RpcState::default();
+        let (id, mut receiver) = register_subscription(&mut state);
+        let response = json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32601, "message": "Method not found" },
+            "id": id,
+        })
+        .to_string();
+        state.on_message(&response);
+
+        assert_eq!(receiver.try_next().ok(), Some(Some(response)));
+        // subscription has ended with the error, so its sender is gone
+        assert_eq!(receiver.try_next().ok(), Some(None));
+    }
+
+    #[test]
+    fn unsubscribes_on_notification_after_receiver_has_been_dropped() {
+        let mut state = RpcState::default();
+        let (id, receiver) = register_subscription(&mut state);
+        state.on_message(&json!({ "jsonrpc": "2.0", "result": "sub-1", "id": id }).to_string());
+        drop(receiver);
+
+        let messages = state.on_message(&notification("sub-1", json!("change")));
+        assert_eq!(messages.len(), 1);
+        let unsubscribe: Value = serde_json::from_str(&messages[0]).unwrap();
+        assert_eq!(unsubscribe["method"], json!("state_unsubscribeStorage"));
+        assert_eq!(unsubscribe["params"], json!(["sub-1"]));
+        // subscription is gone, so it is only cancelled once
+        assert!(state
+            .on_message(&notification("sub-1", json!("change")))
+            .is_empty());
+    }
+
+    #[test]
+    fn queues_messages_until_connection_is_open() {
+        let mut state = RpcState::default();
+        let generation = state.on_connect();
+        assert_eq!(state.status, ConnectionStatus::Connecting);
+        state.send("first".to_string()).unwrap();
+        state.send("second".to_string()).unwrap();
+        assert_eq!(state.queued, vec!["first", "second"]);
+
+        // connection has failed before being opened, queued messages are of no use anymore
+        state.on_close(generation);
+        assert_eq!(state.status, ConnectionStatus::Closed);
+        assert!(state.queued.is_empty());
+    }
+
+    #[test]
+    fn drops_pending_requests_when_connection_closes() {
+        let mut state = RpcState::default();
+        let generation = state.on_connect();
+        let (_, mut request) = register_request(&mut state);
+        let (id, mut subscription) = register_subscription(&mut state);
+
+        // events of previous connections are ignored
+        state.on_close(generation - 1);
+        assert_eq!(request.try_recv(), Ok(None));
+        assert!(subscription.try_next().is_err());
+
+        state.on_close(generation);
+        assert!(request.try_recv().is_err());
+        assert_eq!(subscription.try_next().ok(), Some(None));
+        // late responses of the closed connection are ignored
+        assert!(state
+            .on_message(&json!({ "jsonrpc": "2.0", "result": "sub-1", "id": id }).to_string())
+            .is_empty());
+        assert!(state.subscriptions.is_empty());
+    }
+
+    #[test]
+    fn parses_final_extrinsic_status() {
+        let message = |result: Value| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "author_extrinsicUpdate",
+                "params": { "subscription": "sub-1", "result": result },
+            })
+            .to_string()
+        };
+        let hash = "0x9d3d0f4b5b2f4c2a7f7b0e8b8a3a7c6b7e1f0a2b3c4d5e6f708192a3b4c5d6e7";
+        let cases = vec![
+            (json!("dropped"), XtStatus::Dropped, None),
+            (json!("invalid"), XtStatus::Invalid, None),
+            (
+                json!({ "usurped": hash }),
+                XtStatus::Usurped,
+                Some(hash.to_string()),
+            ),
+            (
+                json!({ "finalityTimeout": hash }),
+                XtStatus::FinalityTimeout,
+                Some(hash.to_string()),
+            ),
+        ];
+        for (result, status, data) in cases {
+            let parsed = parse_status(&message(result));
+            assert_eq!(parsed, (status, data));
+            let error = status_error(parsed.0, parsed.1.as_deref());
+            assert!(error.is_some(), "{:?} has to be a failure", status);
+        }
+
+        // extrinsics of retracted blocks are usually included in another block
+        let retracted = parse_status(&message(json!({ "retracted": hash })));
+        assert_eq!(retracted, (XtStatus::Retracted, Some(hash.to_string())));
+        for status in &[
+            XtStatus::Ready,
+            XtStatus::Broadcast,
+            XtStatus::InBlock,
+            XtStatus::Retracted,
+            XtStatus::Finalized,
+        ] {
+            assert_eq!(status_error(*status, Some(hash)), None);
+        }
+    }
+
+    #[test]
+    fn stops_watching_extrinsics_on_final_status() {
+        let message = |result: Value| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "author_extrinsicUpdate",
+                "params": { "subscription": "sub-1", "result": result },
+            })
+            .to_string()
+        };
+        assert_eq!(
+            on_extrinsic_msg(&message(json!("ready")), XtStatus::InBlock),
+            None
+        );
+        assert_eq!(
+            on_extrinsic_msg(&message(json!("ready")), XtStatus::Ready),
+            Some((XtStatus::Ready, None))
+        );
+        for (result, status) in vec![
+            (json!("dropped"), XtStatus::Dropped),
+            (json!("invalid"), XtStatus::Invalid),
+            (json!({ "usurped": "0x01" }), XtStatus::Usurped),
+            (
+                json!({ "finalityTimeout": "0x01" }),
+                XtStatus::FinalityTimeout,
+            ),
+        ] {
+            let stopped = on_extrinsic_msg(&message(result), XtStatus::Finalized);
+            assert_eq!(stopped.map(|(status, _)| status), Some(status));
+        }
+        assert_eq!(
+            on_extrinsic_msg(
+                &message(json!({ "retracted": "0x01" })),
+                XtStatus::Finalized
+            ),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_binary_messages() -> std::result::Result<(), Box<dyn Error>> {
+        let socket = ws::Builder::new()
+            .build(|out: ws::Sender| {
+                move |msg: Message| {
+                    let request: Value = serde_json::from_str(msg.as_text()?).unwrap_or_default();
+                    out.send(Message::Binary(vec![1, 2, 3]))?;
+                    out.send(
+                        json!({ "jsonrpc": "2.0", "result": "pong", "id": request["id"] })
+                            .to_string(),
+                    )
+                }
+            })?
+            .bind("127.0.0.1:0")?;
+        let address = socket.local_addr()?;
+        thread::spawn(move || {
+            socket.run().ok();
+        });
+
+        let client = RpcClient::new(&format!("ws://{}", address));
+        // first request is queued until the connection has been opened, second one reuses it
+        for _ in 0..2 {
+            assert_eq!(client.request("ping", json!([])).await?, json!("pong"));
+        }
+
+        Ok(())
+    }
+}
@@ -15,9 +15,18 @@
 
 */
 
+use crate::utils::errors::SubstrateError;
 pub use client::XtStatus;
 use client::*;
-use futures::channel::mpsc::Sender as ThreadOut;
+use futures::channel::{
+    mpsc::{unbounded, UnboundedReceiver},
+    oneshot,
+};
+use serde_json::Value;
+use std::{
+    error::Error,
+    sync::{Arc, Mutex, MutexGuard},
+};
 
 #[cfg(not(target_arch = "wasm32"))]
 use std::thread;
@@ -29,88 +38,239 @@ use wasm_bindgen::prelude::*;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::JsCast;
 #[cfg(target_arch = "wasm32")]
-use web_sys::{ErrorEvent, MessageEvent, WebSocket};
+use web_sys::{CloseEvent, ErrorEvent, MessageEvent, WebSocket};
 
 pub mod client;
 
-// TODO: update return value to Result<(), Box<dyn Error>> for proper error handling
-#[cfg(not(target_arch = "wasm32"))]
-pub fn start_rpc_client_thread(
+/// JSON-RPC client using one persistent websocket connection for all requests and subscriptions.
+///
+/// The connection is opened on the first request and re-opened on the next request after it has
+/// been closed. Clones share the same connection.
+#[derive(Clone)]
+pub struct RpcClient {
+    inner: Arc<RpcClientInner>,
+}
+
+struct RpcClientInner {
     url: String,
-    jsonreq: String,
-    result_in: ThreadOut<String>,
-    on_message_fn: OnMessageFn,
-) {
-    match thread::Builder::new()
-        .name("client".to_owned())
+    state: Arc<Mutex<RpcState>>,
+}
+
+impl RpcClient {
+    /// Creates a new `RpcClient` for websocket endpoint `url`, does not connect yet.
+    pub fn new(url: &str) -> RpcClient {
+        RpcClient {
+            inner: Arc::new(RpcClientInner {
+                url: url.to_string(),
+                state: Arc::new(Mutex::new(RpcState::default())),
+            }),
+        }
+    }
+
+    /// Sends a JSON-RPC request and waits for its response.
+    ///
+    /// # Arguments
+    /// * `method` - RPC method to call
+    /// * `params` - parameters for method, usually a JSON array
+    ///
+    /// # Returns
+    /// * `Value` - `result` field of response
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, Box<dyn Error>> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(method, params, Pending::Request(sender))?;
+        let response = receiver.await.map_err(|_| {
+            SubstrateError::Transport(format!(
+                "connection closed before response for '{}'",
+                method
+            ))
+        })?;
+        response.map_err(|err| {
+            Box::from(SubstrateError::Rpc(format!(
+                "error in response for '{}'; {}",
+                method, err
+            )))
+        })
+    }
+
+    /// Starts a JSON-RPC subscription.
+    ///
+    /// The subscription is cancelled with `unsubscribe_method` when the first message after the
+    /// returned receiver has been dropped arrives, until then the node keeps sending messages.
+    /// Receiver returns the full messages as they have been received, which includes error responses
+    /// to the subscription request. Messages are buffered until they are read, so no message is lost,
+    /// if the subscriber falls behind.
+    ///
+    /// # Arguments
+    /// * `method` - RPC method to start subscription with
+    /// * `params` - parameters for method, usually a JSON array
+    /// * `unsubscribe_method` - RPC method to cancel subscription with
+    pub fn subscribe(
+        &self,
+        method: &str,
+        params: Value,
+        unsubscribe_method: &str,
+    ) -> Result<UnboundedReceiver<String>, Box<dyn Error>> {
+        let (sender, receiver) = unbounded::<String>();
+        self.send(
+            method,
+            params,
+            Pending::Subscription(Subscription {
+                sender,
+                unsubscribe_method: unsubscribe_method.to_string(),
+            }),
+        )?;
+        Ok(receiver)
+    }
+
+    fn send(&self, method: &str, params: Value, pending: Pending) -> Result<(), Box<dyn Error>> {
+        let mut state = self.inner.lock()?;
+        let message = state.register(method, params, pending);
+        let generation = match state.status {
+            ConnectionStatus::Closed => Some(state.on_connect()),
+            _ => None,
+        };
+        state.send(message)?;
+        drop(state);
+
+        if let Some(generation) = generation {
+            if let Err(err) =
+                start_rpc_client(&self.inner.url, self.inner.state.clone(), generation)
+            {
+                self.inner.lock()?.on_close(generation);
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl RpcClientInner {
+    fn lock(&self) -> Result<MutexGuard<'_, RpcState>, Box<dyn Error>> {
+        self.state.lock().map_err(|_| {
+            Box::from(SubstrateError::Transport(
+                "rpc client state is poisoned".to_string(),
+            ))
+        })
+    }
+}
+
+impl Drop for RpcClientInner {
+    fn drop(&mut self) {
+        if let Ok(state) = self.state.lock() {
+            if let Some(out) = &state.out {
+                close_connection(out);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn start_rpc_client(
+    url: &str,
+    state: Arc<Mutex<RpcState>>,
+    generation: u64,
+) -> Result<(), Box<dyn Error>> {
+    let url = url.to_string();
+    thread::Builder::new()
+        .name("rpc-client".to_owned())
         .spawn(move || {
-            match connect(url, |out| RpcClient {
+            let handler_state = state.clone();
+            if let Err(err) = connect(url, |out| RpcHandler {
                 out,
-                request: jsonreq.clone(),
-                result: result_in.clone(),
-                on_message_fn,
+                state: handler_state.clone(),
+                generation,
             }) {
-                Ok(_) => (),
-                Err(err) => {
-                    error!("could not spawn rpc client; {}", &err);
-                }
+                error!("could not connect rpc client; {}", &err);
             }
-        }) {
-        Ok(_) => (),
-        Err(err) => {
-            error!("could not spawn rpc client; {}", &err);
-        }
-    };
+            // make sure waiting requests do not wait for a connection that is gone
+            if let Ok(mut state) = state.lock() {
+                state.on_close(generation);
+            }
+        })
+        .map_err(|err| {
+            SubstrateError::Transport(format!("could not spawn rpc client; {}", &err))
+        })?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn close_connection(out: &Out) {
+    out.close(ws::CloseCode::Normal).ok();
 }
 
-// TODO: update return value to Result<(), Box<dyn Error>> for proper error handling
 #[cfg(target_arch = "wasm32")]
-pub fn start_rpc_client_thread(
-    url: String,
-    jsonreq: String,
-    result_in: ThreadOut<String>,
-    on_message_fn: OnMessageFn,
-) {
-    let ws = match WebSocket::new(&url) {
-        Ok(result) => result,
-        Err(err) => {
-            error!(
-                "create new websocket; {}",
-                &err.as_string().unwrap_or("".to_string())
-            );
-            return;
-        }
-    };
-    let ws_c = ws.clone();
+fn start_rpc_client(
+    url: &str,
+    state: Arc<Mutex<RpcState>>,
+    generation: u64,
+) -> Result<(), Box<dyn Error>> {
+    let ws = WebSocket::new(url).map_err(|err| {
+        SubstrateError::Transport(format!(
+            "create new websocket; {}",
+            &err.as_string().unwrap_or_else(|| "".to_string())
+        ))
+    })?;
     debug!("open websocket");
-    let on_message = {
-        Closure::wrap(Box::new(move |evt: MessageEvent| {
-            let msg = match evt.data().as_string() {
-                Some(value) => value,
-                None => {
-                    error!("Can't convert received data to a string");
-                    return;
-                }
-            };
-            let _res_e = (on_message_fn)(&msg, &ws_c, result_in.clone());
-        }) as Box<dyn FnMut(MessageEvent)>)
-    };
 
+    let ws_c = ws.clone();
+    let message_state = state.clone();
+    let on_message = Closure::wrap(Box::new(move |evt: MessageEvent| {
+        let msg = match evt.data().as_string() {
+            Some(value) => value,
+            None => {
+                error!("Can't convert received data to a string");
+                return;
+            }
+        };
+        let responses = match message_state.lock() {
+            Ok(mut state) => state.on_message(&msg),
+            Err(_) => {
+                error!("rpc state is poisoned");
+                return;
+            }
+        };
+        for response in responses {
+            if let Err(err) = ws_c.send_with_str(&response) {
+                debug!("error sending message: {:?}", err);
+            }
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
     ws.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
     on_message.forget();
+
+    let error_state = state.clone();
     let onerror_callback = Closure::wrap(Box::new(move |e: ErrorEvent| {
         debug!("error event: {:?}", e);
+        if let Ok(mut state) = error_state.lock() {
+            state.on_close(generation);
+        }
     }) as Box<dyn FnMut(ErrorEvent)>);
     ws.set_onerror(Some(onerror_callback.as_ref().unchecked_ref()));
     onerror_callback.forget();
+
+    let close_state = state.clone();
+    let onclose_callback = Closure::wrap(Box::new(move |_: CloseEvent| {
+        if let Ok(mut state) = close_state.lock() {
+            state.on_close(generation);
+        }
+    }) as Box<dyn FnMut(CloseEvent)>);
+    ws.set_onclose(Some(onclose_callback.as_ref().unchecked_ref()));
+    onclose_callback.forget();
+
     let cloned_ws = ws.clone();
     let onopen_callback = Closure::wrap(Box::new(move |_| {
-        debug!("sending message: {:?}", jsonreq);
-        match cloned_ws.send_with_str(&jsonreq) {
-            Ok(_) => debug!("message successfully sent"),
-            Err(err) => debug!("error sending message: {:?}", err),
+        if let Ok(mut state) = state.lock() {
+            state.on_open(generation, cloned_ws.clone());
         }
     }) as Box<dyn FnMut(JsValue)>);
     ws.set_onopen(Some(onopen_callback.as_ref().unchecked_ref()));
     onopen_callback.forget();
+
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn close_connection(out: &Out) {
+    out.close_with_code(1000).ok();
 }
@@ -0,0 +1,196 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Decodable variant of the portable `scale-info` type registry, as it is used in V14 metadata.
+
+use parity_scale_codec::{Decode, Encode};
+use serde::Serialize;
+
+/// Reference to a type in the `PortableRegistry`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode, Serialize)]
+pub struct TypeId(#[codec(compact)] pub u32);
+
+/// Registry of all types used in the metadata, types are referenced by their `TypeId`.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct PortableRegistry {
+    pub types: Vec<PortableType>,
+}
+
+impl PortableRegistry {
+    /// Gets type with given ID.
+    pub fn resolve(&self, id: u32) -> Option<&Type> {
+        match self.types.get(id as usize) {
+            Some(portable_type) if portable_type.id == id => Some(&portable_type.ty),
+            _ => self
+                .types
+                .iter()
+                .find(|portable_type| portable_type.id == id)
+                .map(|portable_type| &portable_type.ty),
+        }
+    }
+}
+
+/// Type with its ID in the registry.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct PortableType {
+    #[codec(compact)]
+    pub id: u32,
+    pub ty: Type,
+}
+
+/// Description of a type.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct Type {
+    /// Path of the type, e.g. `["sp_core", "crypto", "AccountId32"]`, empty for primitives.
+    pub path: Vec<String>,
+    /// Generic type parameters.
+    pub type_params: Vec<TypeParameter>,
+    /// Actual type definition.
+    pub type_def: TypeDef,
+    /// Documentation of the type.
+    pub docs: Vec<String>,
+}
+
+/// Generic type parameter.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct TypeParameter {
+    pub name: String,
+    pub ty: Option<TypeId>,
+}
+
+/// Definition of a type, describes its encoding.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub enum TypeDef {
+    /// Struct with named or unnamed fields.
+    Composite(TypeDefComposite),
+    /// Enum, each variant can have own fields.
+    Variant(TypeDefVariant),
+    /// Sequence of elements with compact encoded length prefix.
+    Sequence(TypeDefSequence),
+    /// Array with fixed length.
+    Array(TypeDefArray),
+    /// Tuple of types.
+    Tuple(TypeDefTuple),
+    /// Primitive type.
+    Primitive(TypeDefPrimitive),
+    /// Compact encoded type.
+    Compact(TypeDefCompact),
+    /// Sequence of bits.
+    BitSequence(TypeDefBitSequence),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct TypeDefComposite {
+    pub fields: Vec<Field>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct Field {
+    /// Name of field, `None` for unnamed fields.
+    pub name: Option<String>,
+    /// Type of field.
+    pub ty: TypeId,
+    /// Name of type as used in the source code, e.g. `T::AccountId`.
+    pub type_name: Option<String>,
+    /// Documentation of the field.
+    pub docs: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct TypeDefVariant {
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct Variant {
+    /// Name of variant.
+    pub name: String,
+    /// Fields of variant.
+    pub fields: Vec<Field>,
+    /// Index of variant, used when encoding a value of this variant.
+    pub index: u8,
+    /// Documentation of the variant.
+    pub docs: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct TypeDefSequence {
+    pub type_param: TypeId,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct TypeDefArray {
+    pub len: u32,
+    pub type_param: TypeId,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct TypeDefTuple {
+    pub fields: Vec<TypeId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub enum TypeDefPrimitive {
+    Bool,
+    Char,
+    Str,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    U256,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    I256,
+}
+
+impl TypeDefPrimitive {
+    /// Name of primitive type as used in Rust.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TypeDefPrimitive::Bool => "bool",
+            TypeDefPrimitive::Char => "char",
+            TypeDefPrimitive::Str => "str",
+            TypeDefPrimitive::U8 => "u8",
+            TypeDefPrimitive::U16 => "u16",
+            TypeDefPrimitive::U32 => "u32",
+            TypeDefPrimitive::U64 => "u64",
+            TypeDefPrimitive::U128 => "u128",
+            TypeDefPrimitive::U256 => "u256",
+            TypeDefPrimitive::I8 => "i8",
+            TypeDefPrimitive::I16 => "i16",
+            TypeDefPrimitive::I32 => "i32",
+            TypeDefPrimitive::I64 => "i64",
+            TypeDefPrimitive::I128 => "i128",
+            TypeDefPrimitive::I256 => "i256",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct TypeDefCompact {
+    pub type_param: TypeId,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize)]
+pub struct TypeDefBitSequence {
+    pub bit_store_type: TypeId,
+    pub bit_order_type: TypeId,
+}
@@ -15,15 +15,255 @@
 
 */
 
-use parity_scale_codec::{Decode, Encode, Error, Input};
+use crate::utils::{
+    errors::SubstrateError, extrinsic::node_metadata::SignedExtension, substrate::blake2_256,
+};
+use parity_scale_codec::{Compact, Decode, Encode, Error, Input, Output};
+use sp_core::H256;
 
 #[cfg(feature = "std")]
 use std::fmt;
 
-/// Mirrors the currently used Extrinsic format (V3) from substrate. Has less traits and methods though.
+pub type AccountId = [u8; 32];
+
+/// Mirrors `MultiAddress` from substrate with `AccountId` as account and `u32` as account index.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum MultiAddress {
+    Id(AccountId),
+    Index(Compact<u32>),
+    Raw(Vec<u8>),
+    Address32([u8; 32]),
+    Address20([u8; 20]),
+}
+
+/// Mirrors `MultiSignature` from substrate.
+#[derive(Clone, PartialEq, Eq)]
+pub enum MultiSignature {
+    Ed25519([u8; 64]),
+    Sr25519([u8; 64]),
+    Ecdsa([u8; 65]),
+}
+
+impl Encode for MultiSignature {
+    fn encode_to<T: Output>(&self, dest: &mut T) {
+        let (variant, bytes): (u8, &[u8]) = match self {
+            MultiSignature::Ed25519(bytes) => (0, bytes),
+            MultiSignature::Sr25519(bytes) => (1, bytes),
+            MultiSignature::Ecdsa(bytes) => (2, bytes),
+        };
+        dest.push_byte(variant);
+        dest.write(bytes);
+    }
+}
+
+impl Decode for MultiSignature {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        Ok(match input.read_byte()? {
+            0 => {
+                let mut bytes = [0; 64];
+                input.read(&mut bytes)?;
+                MultiSignature::Ed25519(bytes)
+            }
+            1 => {
+                let mut bytes = [0; 64];
+                input.read(&mut bytes)?;
+                MultiSignature::Sr25519(bytes)
+            }
+            2 => {
+                let mut bytes = [0; 65];
+                input.read(&mut bytes)?;
+                MultiSignature::Ecdsa(bytes)
+            }
+            _ => return Err("Invalid signature variant".into()),
+        })
+    }
+}
+
+/// Mirrors `Era` from substrate, the period extrinsics are valid in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Era {
+    Immortal,
+    /// period and phase, period is a power of two between 4 and 65536
+    Mortal(u64, u64),
+}
+
+impl Era {
+    /// Creates a mortal era like substrate, `period` is rounded up to a power of two between 4 and
+    /// 65536, the phase is taken from `current`, the number of the block the era starts at.
+    ///
+    /// # Arguments
+    /// * `period` - number of blocks the extrinsic is valid in
+    /// * `current` - number of the block the era starts at
+    pub fn mortal(period: u64, current: u64) -> Era {
+        let period = period
+            .checked_next_power_of_two()
+            .unwrap_or(1 << 16)
+            .clamp(4, 1 << 16);
+        let phase = current % period;
+        let quantize_factor = (period >> 12).max(1);
+        Era::Mortal(period, phase / quantize_factor * quantize_factor)
+    }
+}
+
+impl Encode for Era {
+    fn encode_to<T: Output>(&self, dest: &mut T) {
+        match self {
+            Era::Immortal => dest.push_byte(0),
+            Era::Mortal(period, phase) => {
+                let quantize_factor = (*period >> 12).max(1);
+                // periods below 4 are encoded as 4, `Era::mortal` rounds them before
+                let encoded = period.trailing_zeros().saturating_sub(1).clamp(1, 15) as u16
+                    | ((phase / quantize_factor) << 4) as u16;
+                encoded.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl Decode for Era {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let first = input.read_byte()?;
+        if first == 0 {
+            return Ok(Era::Immortal);
+        }
+        let encoded = first as u64 + ((input.read_byte()? as u64) << 8);
+        let period = 2 << (encoded % (1 << 4));
+        let quantize_factor = (period >> 12).max(1);
+        let phase = (encoded >> 4) * quantize_factor;
+        if period >= 4 && phase < period {
+            Ok(Era::Mortal(period, phase))
+        } else {
+            Err("Invalid period and phase".into())
+        }
+    }
+}
+
+/// Signed extra of extrinsics, consists of era, nonce of account and tip.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct GenericExtra(pub Era, pub Compact<u32>, pub Compact<u128>);
+
+impl GenericExtra {
+    pub fn new(era: Era, nonce: u32) -> GenericExtra {
+        GenericExtra(era, Compact(nonce), Compact(0))
+    }
+}
+
+/// Data signed in addition to the signed extra, but not included in extrinsics, encoded in the
+/// order of the signed extensions of the runtime.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdditionalSigned(Vec<u8>);
+
+impl AdditionalSigned {
+    /// Builds the additional signed data for `signed_extensions` of the runtime. Fails, if the
+    /// runtime expects data this crate does not know or a signed extra other than the one of
+    /// `GenericExtra`.
+    ///
+    /// # Arguments
+    /// * `signed_extensions` - signed extensions from the metadata of the runtime
+    /// * `spec_version` - spec version of the runtime
+    /// * `transaction_version` - transaction version of the runtime
+    /// * `genesis_hash` - hash of the genesis block
+    /// * `era_hash` - hash of the block the era starts at, the genesis hash for immortal eras
+    pub fn new(
+        signed_extensions: &[SignedExtension],
+        spec_version: u32,
+        transaction_version: u32,
+        genesis_hash: H256,
+        era_hash: H256,
+    ) -> Result<AdditionalSigned, SubstrateError> {
+        let mut additional_signed = Vec::new();
+        let mut extra = Vec::new();
+        for extension in signed_extensions {
+            match extension.identifier.as_str() {
+                "CheckSpecVersion" => spec_version.encode_to(&mut additional_signed),
+                "CheckTxVersion" => transaction_version.encode_to(&mut additional_signed),
+                "CheckGenesis" => genesis_hash.encode_to(&mut additional_signed),
+                "CheckMortality" | "CheckEra" => {
+                    extra.push("era");
+                    era_hash.encode_to(&mut additional_signed);
+                }
+                "CheckNonce" => extra.push("nonce"),
+                "ChargeTransactionPayment" => extra.push("tip"),
+                "CheckNonZeroSender" | "CheckWeight" => (),
+                // runtime specific extensions can be signed, as long as they have no data
+                _ if is_unit(&extension.extra) && is_unit(&extension.additional_signed) => (),
+                identifier => {
+                    return Err(SubstrateError::Metadata(format!(
+                        "unsupported signed extension {}",
+                        identifier
+                    )))
+                }
+            }
+        }
+        if extra != ["era", "nonce", "tip"] {
+            return Err(SubstrateError::Metadata(format!(
+                "signed extra of runtime consists of {:?} instead of era, nonce and tip",
+                extra
+            )));
+        }
+        Ok(AdditionalSigned(additional_signed))
+    }
+}
+
+impl Encode for AdditionalSigned {
+    fn size_hint(&self) -> usize {
+        self.0.len()
+    }
+
+    fn encode_to<T: Output>(&self, dest: &mut T) {
+        dest.write(&self.0)
+    }
+}
+
+/// Checks if the type of data of a signed extension is known to be empty.
+fn is_unit(type_name: &Option<String>) -> bool {
+    type_name.as_deref() == Some("()")
+}
+
+/// Payload, that is signed for signed extrinsics.
+#[derive(Encode)]
+pub struct SignedPayload<Call>((Call, GenericExtra, AdditionalSigned));
+
+impl<Call> SignedPayload<Call>
+where
+    Call: Encode,
+{
+    pub fn from_raw(call: Call, extra: GenericExtra, additional_signed: AdditionalSigned) -> Self {
+        SignedPayload((call, extra, additional_signed))
+    }
+
+    /// Calls `f` with the encoded payload, payloads longer than 256 bytes are hashed before.
+    pub fn using_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        self.0.using_encoded(|payload| {
+            if payload.len() > 256 {
+                f(&blake2_256(payload)[..])
+            } else {
+                f(payload)
+            }
+        })
+    }
+}
+
+/// Call, that has already been encoded, e.g. to pass calls of different modules to `Utility`
+/// batches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncodedCall(pub Vec<u8>);
+
+impl Encode for EncodedCall {
+    fn size_hint(&self) -> usize {
+        self.0.len()
+    }
+
+    fn encode_to<T: Output>(&self, dest: &mut T) {
+        dest.write(&self.0);
+    }
+}
+
+/// Mirrors the currently used Extrinsic format (V4) from substrate. Has less traits and methods though.
 /// The SingedExtra used does not need to implement SingedExtension here.
 #[derive(Clone, PartialEq)]
 pub struct UncheckedExtrinsicV4<Call> {
+    pub signature: Option<(MultiAddress, MultiSignature, GenericExtra)>,
     pub function: Call,
 }
 
@@ -31,6 +271,25 @@ impl<Call> UncheckedExtrinsicV4<Call>
 where
     Call: Encode,
 {
+    pub fn new_unsigned(function: Call) -> Self {
+        UncheckedExtrinsicV4 {
+            signature: None,
+            function,
+        }
+    }
+
+    pub fn new_signed(
+        function: Call,
+        signed: MultiAddress,
+        signature: MultiSignature,
+        extra: GenericExtra,
+    ) -> Self {
+        UncheckedExtrinsicV4 {
+            signature: Some((signed, signature, extra)),
+            function,
+        }
+    }
+
     pub fn hex_encode(&self) -> String {
         let mut hex_str = hex::encode(self.encode());
         hex_str.insert_str(0, "0x");
@@ -61,7 +320,13 @@ where
 {
     fn encode(&self) -> Vec<u8> {
         encode_with_vec_prefix::<Self, _>(|v| {
-            v.push(V4 & 0b0111_1111);
+            match self.signature.as_ref() {
+                Some(signature) => {
+                    v.push(V4 | 0b1000_0000);
+                    signature.encode_to(v);
+                }
+                None => v.push(V4 & 0b0111_1111),
+            }
             self.function.encode_to(v);
         })
     }
@@ -80,13 +345,18 @@ where
 
         let version = input.read_byte()?;
 
-        let _is_signed = version & 0b1000_0000 != 0;
+        let is_signed = version & 0b1000_0000 != 0;
         let version = version & 0b0111_1111;
         if version != V4 {
             return Err("Invalid transaction version".into());
         }
 
         Ok(UncheckedExtrinsicV4 {
+            signature: if is_signed {
+                Some(Decode::decode(input)?)
+            } else {
+                None
+            },
             function: Decode::decode(input)?,
         })
     }
@@ -114,3 +384,80 @@ fn encode_with_vec_prefix<T: Encode, F: Fn(&mut Vec<u8>)>(encoder: F) -> Vec<u8>
 
     v
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extension(
+        identifier: &str,
+        extra: Option<&str>,
+        additional_signed: Option<&str>,
+    ) -> SignedExtension {
+        SignedExtension {
+            identifier: identifier.to_string(),
+            extra: extra.map(str::to_string),
+            additional_signed: additional_signed.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn rounds_periods_of_mortal_eras() {
+        assert_eq!(Era::mortal(0, 5), Era::Mortal(4, 1));
+        assert_eq!(Era::mortal(1, 5), Era::Mortal(4, 1));
+        assert_eq!(Era::mortal(100, 300), Era::Mortal(128, 44));
+        assert_eq!(Era::mortal(u64::MAX, 70000), Era::Mortal(65536, 4464));
+
+        for era in &[
+            Era::mortal(1, 5),
+            Era::mortal(100, 300),
+            Era::mortal(u64::MAX, 70000),
+        ] {
+            assert_eq!(&Era::decode(&mut &era.encode()[..]).unwrap(), era);
+        }
+        // periods, that have not been rounded, are encoded as the smallest period
+        assert_eq!(Era::Mortal(1, 0).encode(), Era::Mortal(4, 0).encode());
+    }
+
+    #[test]
+    fn builds_additional_signed_from_signed_extensions() {
+        let genesis_hash = H256::repeat_byte(1);
+        let era_hash = H256::repeat_byte(2);
+        let mut extensions = vec![
+            extension("CheckNonZeroSender", Some("()"), Some("()")),
+            extension("CheckSpecVersion", Some("()"), Some("u32")),
+            extension("CheckTxVersion", Some("()"), Some("u32")),
+            extension("CheckGenesis", Some("()"), Some("[u8; 32]")),
+            extension("CheckMortality", Some("Era"), Some("[u8; 32]")),
+            extension("CheckNonce", Some("Compact<u32>"), Some("()")),
+            extension("CheckWeight", Some("()"), Some("()")),
+            extension(
+                "ChargeTransactionPayment",
+                Some("Compact<u128>"),
+                Some("()"),
+            ),
+            extension("CheckCustom", Some("()"), Some("()")),
+        ];
+        let additional_signed =
+            AdditionalSigned::new(&extensions, 7, 3, genesis_hash, era_hash).unwrap();
+        assert_eq!(
+            additional_signed.encode(),
+            (7u32, 3u32, genesis_hash, era_hash).encode()
+        );
+
+        // extensions with data, that is not provided
+        extensions.push(extension("CheckCustom", Some("u32"), Some("()")));
+        assert!(matches!(
+            AdditionalSigned::new(&extensions, 7, 3, genesis_hash, era_hash),
+            Err(SubstrateError::Metadata(_))
+        ));
+        // extensions without types of pre V14 metadata have to be known
+        extensions.pop();
+        extensions.push(extension("CheckCustom", None, None));
+        assert!(AdditionalSigned::new(&extensions, 7, 3, genesis_hash, era_hash).is_err());
+        // signed extra in a different order than the one of `GenericExtra`
+        extensions.pop();
+        extensions.swap(4, 5);
+        assert!(AdditionalSigned::new(&extensions, 7, 3, genesis_hash, era_hash).is_err());
+    }
+}
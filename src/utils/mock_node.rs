@@ -0,0 +1,1391 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! In-process mock of a substrate node running the DID module, used to run tests without network
+//! access.
+//!
+//! The mock serves JSON-RPC via HTTP and websocket and an IPFS gateway for DID documents. Submitted
+//! extrinsics are applied to an in-memory storage, each one in its own block. Their status updates
+//! and the resulting `System::Events` are sent to the subscriptions of the websocket connection.
+
+use crate::utils::{
+    extrinsic::{
+        events::{DispatchClass, DispatchError, DispatchInfo, Pays, Phase},
+        frame_metadata::{
+            DecodeDifferent,
+            ErrorMetadata,
+            EventMetadata,
+            ExtrinsicMetadata,
+            FunctionArgumentMetadata,
+            FunctionMetadata,
+            ModuleMetadata,
+            RuntimeMetadata,
+            RuntimeMetadataPrefixed,
+            RuntimeMetadataV11,
+            StorageEntryMetadata,
+            StorageEntryModifier,
+            StorageEntryType,
+            StorageHasher,
+            StorageMetadata,
+            META_RESERVED,
+        },
+        node_metadata::Metadata,
+        xt_primitives::{AccountId, GenericExtra, MultiAddress, MultiSignature},
+    },
+    substrate::{blake2_256, recover_ethereum_account, twox_128, SubstrateEndpoints},
+};
+use multibase::Base;
+use parity_scale_codec::{Compact, Decode, Encode};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+use sp_core::{ed25519, sr25519, Pair, H256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryInto,
+    error::Error,
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Module index of `System` in mock metadata.
+const SYSTEM_MODULE: u8 = 0;
+/// Module index of `DidModule` in mock metadata.
+const DID_MODULE: u8 = 1;
+/// Module index of `Utility` in mock metadata.
+const UTILITY_MODULE: u8 = 2;
+/// Minimum time between blocks in milliseconds.
+const BLOCK_TIME: u64 = 6000;
+
+/// Calls of `DidModule` with their arguments, call index is the position in this list.
+const DID_MODULE_CALLS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "create_did",
+        &[
+            ("signature", "Vec<u8>"),
+            ("signed_message", "Vec<u8>"),
+            ("identity", "Vec<u8>"),
+            ("nonce", "u64"),
+        ],
+    ),
+    (
+        "create_did_with_detail",
+        &[
+            ("payload", "Vec<u8>"),
+            ("signature", "Vec<u8>"),
+            ("signed_message", "Vec<u8>"),
+            ("identity", "Vec<u8>"),
+            ("nonce", "u64"),
+        ],
+    ),
+    (
+        "add_did_detail",
+        &[
+            ("did", "[u8; 32]"),
+            ("payload", "Vec<u8>"),
+            ("signature", "Vec<u8>"),
+            ("signed_message", "Vec<u8>"),
+            ("identity", "Vec<u8>"),
+            ("nonce", "u64"),
+        ],
+    ),
+    (
+        "update_did_detail",
+        &[
+            ("did", "[u8; 32]"),
+            ("payload", "Vec<u8>"),
+            ("index", "u32"),
+            ("signature", "Vec<u8>"),
+            ("signed_message", "Vec<u8>"),
+            ("identity", "Vec<u8>"),
+            ("nonce", "u64"),
+        ],
+    ),
+    (
+        "whitelist_identity",
+        &[
+            ("method", "u8"),
+            ("signature", "Vec<u8>"),
+            ("signed_message", "Vec<u8>"),
+            ("identity", "Vec<u8>"),
+            ("nonce", "u64"),
+        ],
+    ),
+];
+
+/// Events of `System` with their argument types, event index is the position in this list, like in
+/// the recorded runtimes.
+const SYSTEM_EVENTS: &[(&str, &[&str])] = &[
+    ("ExtrinsicSuccess", &["DispatchInfo"]),
+    ("ExtrinsicFailed", &["DispatchError", "DispatchInfo"]),
+    ("CodeUpdated", &[]),
+    ("NewAccount", &["AccountId"]),
+];
+const EVENT_EXTRINSIC_SUCCESS: u8 = 0;
+const EVENT_EXTRINSIC_FAILED: u8 = 1;
+const EVENT_NEW_ACCOUNT: u8 = 3;
+
+/// Events of `DidModule` with their argument types, event index is the position in this list.
+const DID_MODULE_EVENTS: &[(&str, &[&str])] = &[
+    ("Created", &["Vec<u8>", "Vec<u8>", "u64"]),
+    ("UpdatedDid", &["Vec<u8>", "u32", "u64"]),
+    ("IdentityWhitelist", &["Vec<u8>", "Vec<u8>", "bool", "u64"]),
+];
+const EVENT_CREATED: u8 = 0;
+const EVENT_UPDATED_DID: u8 = 1;
+const EVENT_IDENTITY_WHITELIST: u8 = 2;
+
+/// Calls of `Utility`, call index is the position in this list. `as_derivative` is listed to keep
+/// the call indices of recorded runtimes, but cannot be applied by the mock.
+const UTILITY_CALLS: &[&str] = &["batch", "as_derivative", "batch_all"];
+
+/// Events of `Utility` with their argument types.
+const UTILITY_EVENTS: &[(&str, &[&str])] = &[
+    ("BatchInterrupted", &["u32", "DispatchError"]),
+    ("BatchCompleted", &[]),
+];
+const EVENT_BATCH_INTERRUPTED: u8 = 0;
+const EVENT_BATCH_COMPLETED: u8 = 1;
+
+/// Errors of `DidModule` with their documentation, error index is the position in this list.
+pub const DID_MODULE_ERRORS: &[(&str, &str)] = &[
+    (
+        "IdentityNotWhitelisted",
+        "Identity is not whitelisted for sending transactions",
+    ),
+    ("DidNotFound", "DID does not exist"),
+    ("NotDidOwner", "Sender is not the owner of the DID"),
+];
+
+/// `state_getMetadata` results of V12, V13 and V14 runtimes, that share the module, call and event
+/// indices of the mock, see `MockNode::start_with_metadata`.
+pub const METADATA_V12: &str = include_str!("../../tests/fixtures/metadata_v12.hex");
+pub const METADATA_V13: &str = include_str!("../../tests/fixtures/metadata_v13.hex");
+pub const METADATA_V14: &str = include_str!("../../tests/fixtures/metadata_v14.hex");
+
+/// Hex encoded `System::Events` of a block of the `METADATA_V14` runtime with a timestamp inherent,
+/// a created DID, an asset transfer with a topic, an interrupted batch, a failed extrinsic and a
+/// remark, see `MockNode::import_block_with_events`.
+pub const EVENTS_V14: &str = include_str!("../../tests/fixtures/events_v14.hex");
+
+/// Storage maps of `DidModule` with name, key type, value type and encoded default value.
+const DID_MODULE_STORAGE: &[(&str, &str, &str, &[u8])] = &[
+    ("DidsDetails", "(H256, u32)", "Vec<u8>", &[0]),
+    ("DidsDetailsCount", "H256", "u32", &[0, 0, 0, 0]),
+    ("WhitelistedIdentities", "(Vec<u8>, Vec<u8>)", "bool", &[0]),
+];
+
+/// Substrate node mock, stops serving requests when dropped.
+pub struct MockNode {
+    /// Endpoints to connect to mock with
+    pub endpoints: SubstrateEndpoints,
+    state: Arc<Mutex<MockState>>,
+    running: Arc<AtomicBool>,
+    http_address: SocketAddr,
+    ws_broadcaster: ws::Sender,
+}
+
+impl MockNode {
+    /// Starts a new mock node on random local ports.
+    pub fn start() -> Result<MockNode, Box<dyn Error>> {
+        MockNode::start_with(mock_metadata())
+    }
+
+    /// Starts a new mock node, that serves a hex encoded `state_getMetadata` result, e.g.
+    /// `METADATA_V14`, instead of the mock metadata.
+    pub fn start_with_metadata(metadata: &str) -> Result<MockNode, Box<dyn Error>> {
+        let bytes = hex::decode(metadata.trim().trim_start_matches("0x"))?;
+        MockNode::start_with(RuntimeMetadataPrefixed::decode(&mut &bytes[..])?)
+    }
+
+    fn start_with(metadata: RuntimeMetadataPrefixed) -> Result<MockNode, Box<dyn Error>> {
+        let state = Arc::new(Mutex::new(MockState::new(metadata)?));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let http_address = listener.local_addr()?;
+        let http_state = state.clone();
+        let http_running = running.clone();
+        thread::Builder::new()
+            .name("mock-node-http".to_owned())
+            .spawn(move || serve_http(listener, http_state, http_running))?;
+
+        let ws_state = state.clone();
+        let socket = ws::Builder::new()
+            .build(move |out| MockWsHandler {
+                out,
+                state: ws_state.clone(),
+            })?
+            .bind("127.0.0.1:0")?;
+        let ws_address = socket.local_addr()?;
+        let ws_broadcaster = socket.broadcaster();
+        thread::Builder::new()
+            .name("mock-node-ws".to_owned())
+            .spawn(move || {
+                if let Err(err) = socket.run() {
+                    error!("mock node websocket server stopped; {}", &err);
+                }
+            })?;
+
+        Ok(MockNode {
+            endpoints: SubstrateEndpoints {
+                http_rpc: format!("http://{}", http_address),
+                ws_rpc: format!("ws://{}", ws_address),
+                ipfs: format!("http://{}/ipfs", http_address),
+            },
+            state,
+            running,
+            http_address,
+            ws_broadcaster,
+        })
+    }
+
+    /// Serves given metadata, e.g. a recorded `state_getMetadata` result, instead of the mock
+    /// metadata. Extrinsics can only be applied while serving mock metadata. A new `spec_version` is
+    /// stored as `System::LastRuntimeUpgrade` with the next block like on runtime upgrades.
+    pub fn set_metadata(&self, metadata: Vec<u8>, spec_version: u32) {
+        let mut state = self.lock();
+        state.metadata = metadata;
+        state.spec_version = spec_version;
+    }
+
+    /// Imports a block without extrinsics, that stores given encoded `System::Events`, e.g.
+    /// `EVENTS_V14`, and returns its hash.
+    pub fn import_block_with_events(&self, events: Vec<u8>) -> String {
+        self.lock().import_block_with_events(events)
+    }
+
+    /// Rejects unsigned extrinsics from now on.
+    pub fn require_signed_extrinsics(&self) {
+        self.lock().require_signed = true;
+    }
+
+    /// Lets next extrinsic fail with given `DidModule` error, see `DID_MODULE_ERRORS`.
+    pub fn fail_next_extrinsic(&self, error: u8) {
+        self.lock().failing_call = Some((0, did_module_error(error)));
+    }
+
+    /// Lets next extrinsic fail with given `DispatchError`, e.g. an error outside of modules.
+    pub fn fail_next_extrinsic_with(&self, error: DispatchError) {
+        self.lock().failing_call = Some((0, error));
+    }
+
+    /// Lets the transaction pool drop the next extrinsic instead of including it in a block.
+    pub fn drop_next_extrinsic(&self) {
+        self.lock().drop_next_extrinsic = true;
+    }
+
+    /// Drops the storage notifications of the next `count` blocks.
+    pub fn drop_next_storage_notifications(&self, count: usize) {
+        self.lock().dropped_notifications = count;
+    }
+
+    /// Lets call at `position` of next batch fail with given `DidModule` error.
+    pub fn fail_next_batch_call(&self, position: usize, error: u8) {
+        self.lock().failing_call = Some((position, did_module_error(error)));
+    }
+
+    /// Gets the number of requests of an RPC method, that have been handled so far.
+    pub fn request_count(&self, method: &str) -> usize {
+        self.lock().request_counts.get(method).copied().unwrap_or(0)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock node state is poisoned")
+    }
+}
+
+impl Drop for MockNode {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        self.ws_broadcaster.shutdown().ok();
+        // wake up listener, so it can notice that it has been stopped
+        TcpStream::connect(self.http_address).ok();
+    }
+}
+
+type MockStorage = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// Chain state of the mock.
+struct MockState {
+    metadata: Vec<u8>,
+    spec_version: u32,
+    /// spec version stored as `System::LastRuntimeUpgrade`
+    upgraded_spec_version: u32,
+    parsed_metadata: Metadata,
+    storage: BTreeMap<Vec<u8>, Vec<u8>>,
+    content: HashMap<String, Vec<u8>>,
+    /// block number is position in list
+    blocks: Vec<MockBlock>,
+    next_subscription_id: u64,
+    storage_subscriptions: HashMap<String, StorageSubscription>,
+    /// position of call in next extrinsic and `DidModule` error it fails with
+    failing_call: Option<(usize, DispatchError)>,
+    /// nonces of accounts, that have sent signed extrinsics
+    account_nonces: HashMap<AccountId, u32>,
+    /// reject unsigned extrinsics like runtimes without unsigned DID calls do
+    require_signed: bool,
+    /// number of next storage notifications to drop, like nodes do for slow subscribers
+    dropped_notifications: usize,
+    /// report next extrinsic as dropped from transaction pool instead of applying it
+    drop_next_extrinsic: bool,
+    /// number of handled requests per RPC method
+    request_counts: HashMap<String, usize>,
+}
+
+struct MockBlock {
+    hash: String,
+    extrinsics: Vec<String>,
+    /// milliseconds since unix epoch, genesis has a timestamp of 0
+    timestamp: u64,
+    /// storage after block has been applied
+    storage: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+struct StorageSubscription {
+    out: ws::Sender,
+    keys: Vec<Vec<u8>>,
+}
+
+/// Result of an applied extrinsic.
+struct AppliedExtrinsic {
+    block_hash: String,
+    changes: Vec<Vec<u8>>,
+}
+
+impl MockState {
+    fn new(metadata: RuntimeMetadataPrefixed) -> Result<MockState, Box<dyn Error>> {
+        let encoded = metadata.encode();
+        let mut storage = BTreeMap::new();
+        storage.insert(
+            storage_value_key("System", "LastRuntimeUpgrade"),
+            last_runtime_upgrade(1),
+        );
+        Ok(MockState {
+            metadata: encoded,
+            spec_version: 1,
+            upgraded_spec_version: 1,
+            parsed_metadata: Metadata::parse(metadata)?,
+            storage: storage.clone(),
+            content: HashMap::new(),
+            blocks: vec![MockBlock {
+                hash: block_hash(0, &[]),
+                extrinsics: Vec::new(),
+                timestamp: 0,
+                storage,
+            }],
+            next_subscription_id: 1,
+            storage_subscriptions: HashMap::new(),
+            failing_call: None,
+            account_nonces: HashMap::new(),
+            require_signed: false,
+            dropped_notifications: 0,
+            drop_next_extrinsic: false,
+            request_counts: HashMap::new(),
+        })
+    }
+
+    fn import_block_with_events(&mut self, events: Vec<u8>) -> String {
+        let hash = block_hash(self.blocks.len(), &events);
+        let timestamp = self.blocks[self.blocks.len() - 1].timestamp + BLOCK_TIME;
+        self.storage
+            .insert(storage_value_key("Timestamp", "Now"), timestamp.encode());
+        self.storage
+            .insert(storage_value_key("System", "Events"), events);
+        self.enact_runtime_upgrade(&mut Vec::new());
+        self.blocks.push(MockBlock {
+            hash: hash.clone(),
+            extrinsics: Vec::new(),
+            timestamp,
+            storage: self.storage.clone(),
+        });
+        hash
+    }
+
+    /// Handles requests, that are available via HTTP and websocket.
+    fn handle_request(&mut self, method: &str, params: &Value) -> Result<Value, String> {
+        *self.request_counts.entry(method.to_string()).or_insert(0) += 1;
+        match method {
+            "state_getRuntimeVersion" => Ok(json!({
+                "specName": "mock-node",
+                "specVersion": self.spec_version,
+                "transactionVersion": 1,
+            })),
+            "state_getMetadata" => Ok(json!(format!("0x{}", hex::encode(&self.metadata)))),
+            "state_getStorage" => {
+                let key = parse_hex(&params[0])?;
+                let storage = match params[1].as_str() {
+                    Some(hash) => &self.block(hash).ok_or("unknown block")?.1.storage,
+                    None => &self.storage,
+                };
+                Ok(match storage.get(&key) {
+                    Some(value) => json!(format!("0x{}", hex::encode(value))),
+                    None => Value::Null,
+                })
+            }
+            "state_getKeysPaged" => {
+                let prefix = parse_hex(&params[0])?;
+                let count = params[1].as_u64().ok_or("missing count")? as usize;
+                let start_key = match params[2] {
+                    Value::Null => None,
+                    ref start_key => Some(parse_hex(start_key)?),
+                };
+                let (_, storage) = self.storage_at(&params[3])?;
+                Ok(json!(storage
+                    .keys()
+                    .filter(|key| key.starts_with(&prefix))
+                    .filter(|key| match &start_key {
+                        Some(start) => *key > start,
+                        None => true,
+                    })
+                    .take(count)
+                    .map(|key| format!("0x{}", hex::encode(key)))
+                    .collect::<Vec<String>>()))
+            }
+            "state_queryStorageAt" => {
+                let (block_hash, storage) = self.storage_at(&params[1])?;
+                let changes = params[0]
+                    .as_array()
+                    .ok_or("missing keys")?
+                    .iter()
+                    .map(|key| {
+                        let value = storage
+                            .get(&parse_hex(key)?)
+                            .map(|value| format!("0x{}", hex::encode(value)));
+                        Ok(json!([key, value]))
+                    })
+                    .collect::<Result<Vec<Value>, String>>()?;
+                Ok(json!([{ "block": block_hash, "changes": changes }]))
+            }
+            "chain_getBlockHash" => Ok(match params[0].as_u64() {
+                Some(number) => self
+                    .blocks
+                    .get(number as usize)
+                    .map_or(Value::Null, |block| json!(block.hash)),
+                None => json!(self.blocks[self.blocks.len() - 1].hash),
+            }),
+            "chain_getHeader" => {
+                let hash = params[0].as_str().ok_or("missing block hash")?;
+                Ok(match self.block(hash) {
+                    Some((number, _)) => json!({ "number": format!("0x{:x}", number) }),
+                    None => Value::Null,
+                })
+            }
+            "chain_getBlock" => {
+                let hash = params[0].as_str().ok_or("missing block hash")?;
+                Ok(match self.block(hash) {
+                    Some((number, block)) => json!({
+                        "block": {
+                            "header": { "number": format!("0x{:x}", number) },
+                            "extrinsics": block.extrinsics,
+                        },
+                        "justification": null,
+                    }),
+                    None => Value::Null,
+                })
+            }
+            "system_accountNextIndex" => {
+                let address = params[0].as_str().ok_or("missing account")?;
+                let bytes = Base::Base58Btc
+                    .decode(address)
+                    .map_err(|err| err.to_string())?;
+                if bytes.len() != 35 {
+                    return Err("invalid account".to_string());
+                }
+                let account_id: AccountId =
+                    bytes[1..33].try_into().map_err(|_| "invalid account")?;
+                Ok(json!(self.account_nonces.get(&account_id).unwrap_or(&0)))
+            }
+            "state_unsubscribeStorage" => {
+                let id = params[0].as_str().unwrap_or_default();
+                Ok(json!(self.storage_subscriptions.remove(id).is_some()))
+            }
+            "author_unwatchExtrinsic" => Ok(json!(true)),
+            _ => Err(format!("Method not found: {}", method)),
+        }
+    }
+
+    fn block(&self, hash: &str) -> Option<(usize, &MockBlock)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .find(|(_, block)| block.hash == hash)
+    }
+
+    /// Gets hash of and storage at block `hash` or at latest block if `hash` is null.
+    fn storage_at(&self, hash: &Value) -> Result<(&str, &MockStorage), String> {
+        match hash.as_str() {
+            Some(hash) => {
+                let (_, block) = self.block(hash).ok_or("unknown block")?;
+                Ok((&block.hash, &block.storage))
+            }
+            None => Ok((&self.blocks[self.blocks.len() - 1].hash, &self.storage)),
+        }
+    }
+
+    fn subscription_id(&mut self) -> String {
+        let id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+        format!("mock-{}", id)
+    }
+
+    /// Applies extrinsic in a new block, storage subscriptions have to be notified about its
+    /// changes afterwards.
+    fn apply_extrinsic(&mut self, xt_hex: &str) -> Result<AppliedExtrinsic, String> {
+        let bytes = parse_hex(&json!(xt_hex))?;
+        let xt: Vec<u8> = Decode::decode(&mut &bytes[..]).map_err(|err| err.to_string())?;
+        let mut input = &xt[..];
+        let signer = match decode::<u8>(&mut input)? {
+            0x04 if self.require_signed => return Err("extrinsic is not signed".to_string()),
+            0x04 => None,
+            0x84 => Some(self.check_signature(&mut input)?),
+            _ => return Err("unsupported extrinsic format".to_string()),
+        };
+        if input.len() < 2 {
+            return Err("unsupported extrinsic format".to_string());
+        }
+        let (module, call, mut input) = (input[0], input[1], &input[2..]);
+        let mut changes = Vec::new();
+        let outcome = match module {
+            UTILITY_MODULE if signer.is_none() => {
+                return Err("utility calls have to be signed".to_string())
+            }
+            UTILITY_MODULE => self.apply_batch(call, &mut input, &mut changes)?,
+            _ => self
+                .apply_call(0, module, call, &mut input, &mut changes)?
+                .map(|event| vec![event_record(DID_MODULE, event)]),
+        };
+        self.failing_call = None;
+        // accounts are created with their first extrinsic, as the mock does not track balances
+        let new_account = match signer {
+            Some(account_id) => {
+                let nonce = self.account_nonces.entry(account_id.clone()).or_insert(0);
+                *nonce += 1;
+                Some(account_id).filter(|_| *nonce == 1)
+            }
+            None => None,
+        };
+
+        let info = DispatchInfo {
+            weight: 0,
+            class: DispatchClass::Normal,
+            pays_fee: Pays::Yes,
+        };
+        let mut records: Vec<Vec<u8>> = new_account
+            .map(|account_id| event_record(SYSTEM_MODULE, (EVENT_NEW_ACCOUNT, account_id).encode()))
+            .into_iter()
+            .collect();
+        match outcome {
+            Ok(call_records) => {
+                records.extend(call_records);
+                records.push(event_record(
+                    SYSTEM_MODULE,
+                    (EVENT_EXTRINSIC_SUCCESS, info).encode(),
+                ));
+            }
+            Err(error) => records.push(event_record(
+                SYSTEM_MODULE,
+                (EVENT_EXTRINSIC_FAILED, error, info).encode(),
+            )),
+        }
+        let mut events = Compact(records.len() as u32).encode();
+        for record in records {
+            events.extend(record);
+        }
+        let events_key = storage_value_key("System", "Events");
+        self.storage.insert(events_key.clone(), events);
+        changes.push(events_key);
+        self.enact_runtime_upgrade(&mut changes);
+
+        // use current time, but keep timestamps of blocks apart like on a real chain
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| err.to_string())?
+            .as_millis() as u64;
+        let timestamp = now.max(self.blocks[self.blocks.len() - 1].timestamp + BLOCK_TIME);
+        let timestamp_key = storage_value_key("Timestamp", "Now");
+        self.storage
+            .insert(timestamp_key.clone(), timestamp.encode());
+        changes.push(timestamp_key);
+
+        let hash = block_hash(self.blocks.len(), &bytes);
+        self.blocks.push(MockBlock {
+            hash: hash.clone(),
+            extrinsics: vec![xt_hex.to_string()],
+            timestamp,
+            storage: self.storage.clone(),
+        });
+
+        Ok(AppliedExtrinsic {
+            block_hash: hash,
+            changes,
+        })
+    }
+
+    /// Decodes signature and signed extra of a signed extrinsic and checks them against the call,
+    /// that follows in `input`.
+    ///
+    /// # Returns
+    /// * `AccountId` - account, that signed the extrinsic
+    fn check_signature(&self, input: &mut &[u8]) -> Result<AccountId, String> {
+        let address: MultiAddress = decode(input)?;
+        let signature: MultiSignature = decode(input)?;
+        let extra: GenericExtra = decode(input)?;
+        let account_id = match address {
+            MultiAddress::Id(account_id) => account_id,
+            _ => return Err("unsupported address format".to_string()),
+        };
+        let expected_nonce = *self.account_nonces.get(&account_id).unwrap_or(&0);
+        if (extra.1).0 != expected_nonce {
+            return Err(format!(
+                "invalid nonce {}, expected {}",
+                (extra.1).0,
+                expected_nonce
+            ));
+        }
+
+        let genesis_hash = H256::from_slice(&parse_hex(&json!(self.blocks[0].hash))?);
+        let mut payload = input.to_vec();
+        extra.encode_to(&mut payload);
+        (self.spec_version, 1u32, genesis_hash, genesis_hash).encode_to(&mut payload);
+        if payload.len() > 256 {
+            payload = blake2_256(&payload).to_vec();
+        }
+        let valid = match signature {
+            MultiSignature::Sr25519(signature) => sr25519::Pair::verify(
+                &sr25519::Signature::from_raw(signature),
+                &payload,
+                &sr25519::Public::from_raw(account_id),
+            ),
+            MultiSignature::Ed25519(signature) => ed25519::Pair::verify(
+                &ed25519::Signature::from_raw(signature),
+                &payload,
+                &ed25519::Public::from_raw(account_id),
+            ),
+            MultiSignature::Ecdsa(signature) => {
+                let message = secp256k1::Message::parse(&blake2_256(&payload));
+                let recovery_id = secp256k1::RecoveryId::parse(signature[64])
+                    .map_err(|err| format!("{:?}", err))?;
+                let signature = secp256k1::Signature::parse_slice(&signature[..64])
+                    .map_err(|err| format!("{:?}", err))?;
+                match secp256k1::recover(&message, &signature, &recovery_id) {
+                    Ok(public) => blake2_256(&public.serialize_compressed()) == account_id,
+                    Err(_) => false,
+                }
+            }
+        };
+        if !valid {
+            return Err("bad signature".to_string());
+        }
+        Ok(account_id)
+    }
+
+    /// Applies the calls of a `Utility` batch. `batch` stops at the first failing call, `batch_all`
+    /// reverts all calls if one of them fails.
+    ///
+    /// # Returns
+    /// * `Result<Vec<Vec<u8>>, DispatchError>` - event records of batch or error of extrinsic
+    fn apply_batch(
+        &mut self,
+        call: u8,
+        input: &mut &[u8],
+        changes: &mut Vec<Vec<u8>>,
+    ) -> Result<Result<Vec<Vec<u8>>, DispatchError>, String> {
+        let atomic = match UTILITY_CALLS.get(call as usize) {
+            Some(&"batch") => false,
+            Some(&"batch_all") => true,
+            _ => return Err(format!("unsupported call {}", call)),
+        };
+        let storage = self.storage.clone();
+        let count: Compact<u32> = decode(input)?;
+        let mut records = Vec::new();
+        for position in 0..count.0 as usize {
+            let (module, call): (u8, u8) = decode(input)?;
+            match self.apply_call(position, module, call, input, changes)? {
+                Ok(event) => records.push(event_record(DID_MODULE, event)),
+                Err(error) if atomic => {
+                    self.storage = storage;
+                    changes.clear();
+                    return Ok(Err(error));
+                }
+                Err(error) => {
+                    records.push(event_record(
+                        UTILITY_MODULE,
+                        (EVENT_BATCH_INTERRUPTED, position as u32, error).encode(),
+                    ));
+                    return Ok(Ok(records));
+                }
+            }
+        }
+        records.push(event_record(UTILITY_MODULE, vec![EVENT_BATCH_COMPLETED]));
+        Ok(Ok(records))
+    }
+
+    /// Applies a `DidModule` call to storage.
+    ///
+    /// # Arguments
+    /// * `position` - position of call in extrinsic, used to check if it should fail
+    ///
+    /// # Returns
+    /// * `Result<Vec<u8>, DispatchError>` - encoded event variant and data or error of call
+    fn apply_call(
+        &mut self,
+        position: usize,
+        module: u8,
+        call: u8,
+        input: &mut &[u8],
+        changes: &mut Vec<Vec<u8>>,
+    ) -> Result<Result<Vec<u8>, DispatchError>, String> {
+        if module != DID_MODULE {
+            return Err(format!("unknown module {}", module));
+        }
+        if let Some((failing_position, error)) = &self.failing_call {
+            if *failing_position == position {
+                return Ok(Err(error.clone()));
+            }
+        }
+        let name = DID_MODULE_CALLS
+            .get(call as usize)
+            .ok_or_else(|| format!("unknown call {}", call))?
+            .0;
+        match name {
+            "create_did" | "create_did_with_detail" => {
+                let payload: Option<Vec<u8>> = match name {
+                    "create_did_with_detail" => Some(decode(input)?),
+                    _ => None,
+                };
+                let (_signature, _signed_message, identity, nonce): (
+                    Vec<u8>,
+                    Vec<u8>,
+                    Vec<u8>,
+                    u64,
+                ) = decode(input)?;
+                let mut hasher = Keccak256::new();
+                hasher.input(&identity);
+                hasher.input(nonce.encode());
+                let did = H256::from_slice(hasher.result().as_slice());
+                if let Some(payload) = payload {
+                    self.add_detail(did, None, payload, changes)?;
+                }
+                Ok(Ok((
+                    EVENT_CREATED,
+                    did.as_bytes().to_vec(),
+                    identity,
+                    nonce,
+                )
+                    .encode()))
+            }
+            "add_did_detail" | "update_did_detail" => {
+                let did: [u8; 32] = decode(input)?;
+                let payload: Vec<u8> = decode(input)?;
+                let index: Option<u32> = match name {
+                    "update_did_detail" => Some(decode(input)?),
+                    _ => None,
+                };
+                let (_signature, _signed_message, identity, nonce): (
+                    Vec<u8>,
+                    Vec<u8>,
+                    Vec<u8>,
+                    u64,
+                ) = decode(input)?;
+                let index = self.add_detail(H256::from(did), index, payload, changes)?;
+                Ok(Ok((EVENT_UPDATED_DID, did.to_vec(), index, nonce).encode()))
+            }
+            "whitelist_identity" => {
+                let (_method, signature, signed_message, identity, nonce): (
+                    u8,
+                    Vec<u8>,
+                    Vec<u8>,
+                    Vec<u8>,
+                    u64,
+                ) = decode(input)?;
+                let account = recover_ethereum_account(
+                    signature
+                        .as_slice()
+                        .try_into()
+                        .map_err(|_| "invalid signature length")?,
+                    signed_message
+                        .as_slice()
+                        .try_into()
+                        .map_err(|_| "invalid message length")?,
+                )
+                .map_err(|err| err.to_string())?;
+                let key = self.map_key::<(Vec<u8>, Vec<u8>), bool>(
+                    "WhitelistedIdentities",
+                    (keccak(&identity), keccak(&account)),
+                )?;
+                self.storage.insert(key.clone(), true.encode());
+                changes.push(key);
+                Ok(Ok((
+                    EVENT_IDENTITY_WHITELIST,
+                    identity,
+                    account.to_vec(),
+                    true,
+                    nonce,
+                )
+                    .encode()))
+            }
+            _ => Err(format!("unsupported call {}", name)),
+        }
+    }
+
+    /// Stores payload in IPFS mock and its CID as DID detail.
+    ///
+    /// # Returns
+    /// * `u32` - index of detail
+    fn add_detail(
+        &mut self,
+        did: H256,
+        index: Option<u32>,
+        payload: Vec<u8>,
+        changes: &mut Vec<Vec<u8>>,
+    ) -> Result<u32, String> {
+        let count_key = self.map_key::<H256, u32>("DidsDetailsCount", did)?;
+        let count: u32 = match self.storage.get(&count_key) {
+            Some(value) => decode(&mut value.as_slice())?,
+            None => 0,
+        };
+        let index = index.unwrap_or(count);
+        if index >= count {
+            self.storage.insert(count_key.clone(), (index + 1).encode());
+            changes.push(count_key);
+        }
+        let cid = cid_for(&payload);
+        self.content.insert(cid.clone(), payload);
+        let detail_key = self.map_key::<(H256, u32), Vec<u8>>("DidsDetails", (did, index))?;
+        self.storage
+            .insert(detail_key.clone(), cid.as_bytes().to_vec().encode());
+        changes.push(detail_key);
+        Ok(index)
+    }
+
+    fn map_key<K: Encode, V: Decode + Clone>(
+        &self,
+        storage: &'static str,
+        key: K,
+    ) -> Result<Vec<u8>, String> {
+        Ok(self
+            .parsed_metadata
+            .module("DidModule")
+            .and_then(|module| module.storage(storage))
+            .and_then(|storage| storage.get_map::<K, V>())
+            .map_err(|err| err.to_string())?
+            .key(key)
+            .0)
+    }
+
+    /// Stores the current spec version as `System::LastRuntimeUpgrade`, if it has changed since the
+    /// last block.
+    fn enact_runtime_upgrade(&mut self, changes: &mut Vec<Vec<u8>>) {
+        if self.spec_version == self.upgraded_spec_version {
+            return;
+        }
+        let key = storage_value_key("System", "LastRuntimeUpgrade");
+        self.storage
+            .insert(key.clone(), last_runtime_upgrade(self.spec_version));
+        changes.push(key);
+        self.upgraded_spec_version = self.spec_version;
+    }
+
+    fn notify_storage_subscriptions(&mut self, block_hash: &str, changed_keys: &[Vec<u8>]) {
+        if self.dropped_notifications > 0 {
+            self.dropped_notifications -= 1;
+            return;
+        }
+        for (id, subscription) in self.storage_subscriptions.iter() {
+            let changes: Vec<Value> = changed_keys
+                .iter()
+                .filter(|key| subscription.keys.is_empty() || subscription.keys.contains(key))
+                .map(|key| {
+                    json!([
+                        format!("0x{}", hex::encode(key)),
+                        self.storage
+                            .get(key)
+                            .map(|value| format!("0x{}", hex::encode(value))),
+                    ])
+                })
+                .collect();
+            if changes.is_empty() {
+                continue;
+            }
+            let message = notification(
+                "state_storage",
+                id,
+                json!({ "block": block_hash, "changes": changes }),
+            );
+            if let Err(err) = subscription.out.send(message) {
+                debug!("could not notify storage subscription; {}", &err);
+            }
+        }
+    }
+}
+
+struct MockWsHandler {
+    out: ws::Sender,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockWsHandler {
+    /// Handles a websocket request, including subscriptions.
+    ///
+    /// # Returns
+    /// * `Vec<String>` - messages to send in given order
+    fn handle(&self, request: &Value) -> Vec<String> {
+        let id = &request["id"];
+        let method = request["method"].as_str().unwrap_or_default();
+        let params = &request["params"];
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return vec![error_response(id, "mock node state is poisoned")],
+        };
+        match method {
+            "state_subscribeStorage" => {
+                let keys = match params[0].as_array() {
+                    Some(keys) => keys.iter().map(parse_hex).collect(),
+                    None => Ok(Vec::new()),
+                };
+                match keys {
+                    Ok(keys) => {
+                        let subscription_id = state.subscription_id();
+                        let mut messages = vec![response(id, json!(subscription_id))];
+                        // like nodes, send current values of subscribed keys right away
+                        if !keys.is_empty() {
+                            let changes: Vec<Value> = keys
+                                .iter()
+                                .map(|key| {
+                                    json!([
+                                        format!("0x{}", hex::encode(key)),
+                                        state
+                                            .storage
+                                            .get(key)
+                                            .map(|value| format!("0x{}", hex::encode(value))),
+                                    ])
+                                })
+                                .collect();
+                            messages.push(notification(
+                                "state_storage",
+                                &subscription_id,
+                                json!({
+                                    "block": state.blocks[state.blocks.len() - 1].hash,
+                                    "changes": changes,
+                                }),
+                            ));
+                        }
+                        state.storage_subscriptions.insert(
+                            subscription_id.clone(),
+                            StorageSubscription {
+                                out: self.out.clone(),
+                                keys,
+                            },
+                        );
+                        messages
+                    }
+                    Err(err) => vec![error_response(id, &err)],
+                }
+            }
+            "author_submitAndWatchExtrinsic" if state.drop_next_extrinsic => {
+                state.drop_next_extrinsic = false;
+                let subscription_id = state.subscription_id();
+                vec![
+                    response(id, json!(subscription_id)),
+                    notification("author_extrinsicUpdate", &subscription_id, json!("ready")),
+                    notification("author_extrinsicUpdate", &subscription_id, json!("dropped")),
+                ]
+            }
+            "author_submitAndWatchExtrinsic" => {
+                let xt_hex = params[0].as_str().unwrap_or_default();
+                match state.apply_extrinsic(xt_hex) {
+                    Ok(applied) => {
+                        debug!("mock node changed keys {:?}", applied.changes);
+                        let subscription_id = state.subscription_id();
+                        // respond to request before storage notifications are sent for the new block
+                        self.out
+                            .send(response(id, json!(subscription_id)))
+                            .ok();
+                        state.notify_storage_subscriptions(&applied.block_hash, &applied.changes);
+                        [
+                            json!("ready"),
+                            json!({ "broadcast": ["mock-peer"] }),
+                            json!({ "inBlock": applied.block_hash }),
+                            json!({ "finalized": applied.block_hash }),
+                        ]
+                        .iter()
+                        .map(|status| {
+                            notification("author_extrinsicUpdate", &subscription_id, status.clone())
+                        })
+                        .collect()
+                    }
+                    Err(err) => vec![json!({
+                        "jsonrpc": "2.0",
+                        "error": { "code": 1010, "message": format!("Invalid Transaction; {}", err) },
+                        "id": id,
+                    })
+                    .to_string()],
+                }
+            }
+            _ => vec![match state.handle_request(method, params) {
+                Ok(result) => response(id, result),
+                Err(err) => error_response(id, &err),
+            }],
+        }
+    }
+}
+
+impl ws::Handler for MockWsHandler {
+    fn on_message(&mut self, msg: ws::Message) -> ws::Result<()> {
+        let request: Value = match serde_json::from_str(msg.as_text()?) {
+            Ok(request) => request,
+            Err(err) => {
+                return self
+                    .out
+                    .send(error_response(&Value::Null, &err.to_string()))
+            }
+        };
+        for message in self.handle(&request) {
+            self.out.send(message)?;
+        }
+        Ok(())
+    }
+
+    fn on_close(&mut self, _: ws::CloseCode, _: &str) {
+        let connection_id = self.out.connection_id();
+        if let Ok(mut state) = self.state.lock() {
+            state
+                .storage_subscriptions
+                .retain(|_, subscription| subscription.out.connection_id() != connection_id);
+        }
+    }
+}
+
+fn serve_http(listener: TcpListener, state: Arc<Mutex<MockState>>, running: Arc<AtomicBool>) {
+    for stream in listener.incoming() {
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+        if let Ok(stream) = stream {
+            let state = state.clone();
+            thread::spawn(move || {
+                if let Err(err) = handle_http(stream, state) {
+                    debug!("mock node could not handle http request; {}", &err);
+                }
+            });
+        }
+    }
+}
+
+fn handle_http(stream: TcpStream, state: Arc<Mutex<MockState>>) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    let cid = if path.starts_with("/ipfs/") {
+        Some(path.trim_start_matches("/ipfs/"))
+    } else if path.starts_with("/api/v0/cat?arg=") {
+        Some(path.trim_start_matches("/api/v0/cat?arg="))
+    } else {
+        None
+    };
+    let (status, content_type, body) = match (method, cid) {
+        (_, Some(cid)) => match state.lock().map_err(|_| "poisoned")?.content.get(cid) {
+            Some(content) => ("200 OK", "application/octet-stream", content.clone()),
+            None => ("404 Not Found", "text/plain", b"not found".to_vec()),
+        },
+        ("POST", None) => {
+            let request: Value = serde_json::from_slice(&body)?;
+            let id = &request["id"];
+            let result = state.lock().map_err(|_| "poisoned")?.handle_request(
+                request["method"].as_str().unwrap_or_default(),
+                &request["params"],
+            );
+            let response = match result {
+                Ok(result) => response(id, result),
+                Err(err) => error_response(id, &err),
+            };
+            ("200 OK", "application/json", response.into_bytes())
+        }
+        _ => ("405 Method Not Allowed", "text/plain", Vec::new()),
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Builds V11 metadata with `System`, `DidModule` and `Timestamp`.
+pub fn mock_metadata() -> RuntimeMetadataPrefixed {
+    let strings =
+        |values: &[&str]| -> Vec<String> { values.iter().map(|value| value.to_string()).collect() };
+    let event = |name: &str, arguments: &[&str]| EventMetadata {
+        name: DecodeDifferent::Decoded(name.to_string()),
+        arguments: DecodeDifferent::Decoded(strings(arguments)),
+        documentation: DecodeDifferent::Decoded(Vec::new()),
+    };
+    let system = ModuleMetadata {
+        name: DecodeDifferent::Decoded("System".to_string()),
+        storage: Some(DecodeDifferent::Decoded(StorageMetadata {
+            prefix: DecodeDifferent::Decoded("System".to_string()),
+            entries: DecodeDifferent::Decoded(vec![StorageEntryMetadata {
+                name: DecodeDifferent::Decoded("Events".to_string()),
+                modifier: StorageEntryModifier::Default,
+                ty: StorageEntryType::Plain(DecodeDifferent::Decoded(
+                    "Vec<EventRecord<T::Event, T::Hash>>".to_string(),
+                )),
+                default: DecodeDifferent::Decoded(vec![0]),
+                documentation: DecodeDifferent::Decoded(Vec::new()),
+            }]),
+        })),
+        calls: Some(DecodeDifferent::Decoded(Vec::new())),
+        event: Some(DecodeDifferent::Decoded(
+            SYSTEM_EVENTS
+                .iter()
+                .map(|(name, arguments)| event(name, arguments))
+                .collect(),
+        )),
+        constants: DecodeDifferent::Decoded(Vec::new()),
+        errors: DecodeDifferent::Decoded(Vec::new()),
+    };
+    let did_module = ModuleMetadata {
+        name: DecodeDifferent::Decoded("DidModule".to_string()),
+        storage: Some(DecodeDifferent::Decoded(StorageMetadata {
+            prefix: DecodeDifferent::Decoded("DidModule".to_string()),
+            entries: DecodeDifferent::Decoded(
+                DID_MODULE_STORAGE
+                    .iter()
+                    .map(|(name, key, value, default)| StorageEntryMetadata {
+                        name: DecodeDifferent::Decoded(name.to_string()),
+                        modifier: StorageEntryModifier::Optional,
+                        ty: StorageEntryType::Map {
+                            hasher: StorageHasher::Blake2_128Concat,
+                            key: DecodeDifferent::Decoded(key.to_string()),
+                            value: DecodeDifferent::Decoded(value.to_string()),
+                            unused: false,
+                        },
+                        default: DecodeDifferent::Decoded(default.to_vec()),
+                        documentation: DecodeDifferent::Decoded(Vec::new()),
+                    })
+                    .collect(),
+            ),
+        })),
+        calls: Some(DecodeDifferent::Decoded(
+            DID_MODULE_CALLS
+                .iter()
+                .map(|(name, arguments)| FunctionMetadata {
+                    name: DecodeDifferent::Decoded(name.to_string()),
+                    arguments: DecodeDifferent::Decoded(
+                        arguments
+                            .iter()
+                            .map(|(name, ty)| FunctionArgumentMetadata {
+                                name: DecodeDifferent::Decoded(name.to_string()),
+                                ty: DecodeDifferent::Decoded(ty.to_string()),
+                            })
+                            .collect(),
+                    ),
+                    documentation: DecodeDifferent::Decoded(Vec::new()),
+                })
+                .collect(),
+        )),
+        event: Some(DecodeDifferent::Decoded(
+            DID_MODULE_EVENTS
+                .iter()
+                .map(|(name, arguments)| event(name, arguments))
+                .collect(),
+        )),
+        constants: DecodeDifferent::Decoded(Vec::new()),
+        errors: DecodeDifferent::Decoded(
+            DID_MODULE_ERRORS
+                .iter()
+                .map(|(name, documentation)| ErrorMetadata {
+                    name: DecodeDifferent::Decoded(name.to_string()),
+                    documentation: DecodeDifferent::Decoded(vec![documentation.to_string()]),
+                })
+                .collect(),
+        ),
+    };
+    let utility = ModuleMetadata {
+        name: DecodeDifferent::Decoded("Utility".to_string()),
+        storage: None,
+        calls: Some(DecodeDifferent::Decoded(
+            UTILITY_CALLS
+                .iter()
+                .map(|name| FunctionMetadata {
+                    name: DecodeDifferent::Decoded(name.to_string()),
+                    arguments: DecodeDifferent::Decoded(vec![FunctionArgumentMetadata {
+                        name: DecodeDifferent::Decoded("calls".to_string()),
+                        ty: DecodeDifferent::Decoded("Vec<<T as Trait>::Call>".to_string()),
+                    }]),
+                    documentation: DecodeDifferent::Decoded(Vec::new()),
+                })
+                .collect(),
+        )),
+        event: Some(DecodeDifferent::Decoded(
+            UTILITY_EVENTS
+                .iter()
+                .map(|(name, arguments)| event(name, arguments))
+                .collect(),
+        )),
+        constants: DecodeDifferent::Decoded(Vec::new()),
+        errors: DecodeDifferent::Decoded(Vec::new()),
+    };
+    let timestamp = ModuleMetadata {
+        name: DecodeDifferent::Decoded("Timestamp".to_string()),
+        storage: Some(DecodeDifferent::Decoded(StorageMetadata {
+            prefix: DecodeDifferent::Decoded("Timestamp".to_string()),
+            entries: DecodeDifferent::Decoded(vec![StorageEntryMetadata {
+                name: DecodeDifferent::Decoded("Now".to_string()),
+                modifier: StorageEntryModifier::Default,
+                ty: StorageEntryType::Plain(DecodeDifferent::Decoded("T::Moment".to_string())),
+                default: DecodeDifferent::Decoded(0u64.encode()),
+                documentation: DecodeDifferent::Decoded(Vec::new()),
+            }]),
+        })),
+        calls: Some(DecodeDifferent::Decoded(Vec::new())),
+        event: None,
+        constants: DecodeDifferent::Decoded(Vec::new()),
+        errors: DecodeDifferent::Decoded(Vec::new()),
+    };
+    RuntimeMetadataPrefixed(
+        META_RESERVED,
+        RuntimeMetadata::V11(RuntimeMetadataV11 {
+            modules: DecodeDifferent::Decoded(vec![system, did_module, utility, timestamp]),
+            extrinsic: ExtrinsicMetadata {
+                version: 4,
+                signed_extensions: [
+                    "CheckSpecVersion",
+                    "CheckTxVersion",
+                    "CheckGenesis",
+                    "CheckMortality",
+                    "CheckNonce",
+                    "CheckWeight",
+                    "ChargeTransactionPayment",
+                ]
+                .iter()
+                .map(|identifier| DecodeDifferent::Decoded(identifier.to_string()))
+                .collect(),
+            },
+        }),
+    )
+}
+
+/// Gets CIDv1 for raw content, as the IPFS mock stores content with it.
+pub fn cid_for(content: &[u8]) -> String {
+    let mut bytes = vec![0x01, 0x55, 0x12, 0x20];
+    bytes.extend(Sha256::digest(content).as_slice());
+    multibase::encode(Base::Base32Lower, bytes)
+}
+
+/// Encodes `LastRuntimeUpgradeInfo` of `spec_version`.
+fn last_runtime_upgrade(spec_version: u32) -> Vec<u8> {
+    (Compact(spec_version), "mock-node".to_string()).encode()
+}
+
+fn storage_value_key(module: &str, storage: &str) -> Vec<u8> {
+    let mut key = twox_128(module.as_bytes()).to_vec();
+    key.extend(&twox_128(storage.as_bytes())[..]);
+    key
+}
+
+fn did_module_error(error: u8) -> DispatchError {
+    DispatchError::Module {
+        index: DID_MODULE,
+        error,
+        message: None,
+    }
+}
+
+fn event_record(module: u8, event: Vec<u8>) -> Vec<u8> {
+    let mut record = Phase::ApplyExtrinsic(0).encode();
+    record.push(module);
+    record.extend(event);
+    record.extend(Vec::<H256>::new().encode());
+    record
+}
+
+fn block_hash(number: usize, data: &[u8]) -> String {
+    let mut input = (number as u64).encode();
+    input.extend(data);
+    format!("0x{}", hex::encode(blake2_256(&input)))
+}
+
+fn keccak(data: &[u8]) -> Vec<u8> {
+    let mut hasher = Keccak256::new();
+    hasher.input(data);
+    hasher.result().to_vec()
+}
+
+fn decode<T: Decode>(input: &mut &[u8]) -> Result<T, String> {
+    T::decode(input).map_err(|err| format!("could not decode call arguments; {}", err))
+}
+
+fn parse_hex(value: &Value) -> Result<Vec<u8>, String> {
+    let value = value.as_str().ok_or("expected hex string")?;
+    hex::decode(value.trim_start_matches("0x")).map_err(|err| err.to_string())
+}
+
+fn response(id: &Value, result: Value) -> String {
+    json!({ "jsonrpc": "2.0", "result": result, "id": id }).to_string()
+}
+
+fn error_response(id: &Value, message: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": -32601, "message": message },
+        "id": id,
+    })
+    .to_string()
+}
+
+fn notification(method: &str, subscription: &str, result: Value) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": { "subscription": subscription, "result": result },
+    })
+    .to_string()
+}
@@ -14,6 +14,12 @@
   limitations under the License.
 */
 
+pub mod content_store;
+pub mod did_events;
+pub mod errors;
 #[macro_use]
 pub mod extrinsic;
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub mod mock_node;
 pub mod substrate;
+pub mod timeouts;
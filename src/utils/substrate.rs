@@ -14,264 +14,1474 @@
   limitations under the License.
 */
 
-use crate::utils::extrinsic::{
-    events::{DispatchError, EventsDecoder, Phase, RawEvent, RuntimeEvent, SystemEvent},
-    frame_metadata::RuntimeMetadataPrefixed,
-    node_metadata::Metadata,
-    rpc::{
-        client::{
-            on_extrinsic_msg_until_broadcast,
-            on_extrinsic_msg_until_finalized,
-            on_extrinsic_msg_until_in_block,
-            on_extrinsic_msg_until_ready,
-            on_subscription_msg,
+use crate::utils::{
+    content_store::{verify_cid, ContentStore, IpfsGateway},
+    did_events::{
+        register_did_module_types,
+        Created,
+        DidModuleEvent,
+        IdentityWhitelist,
+        UpdatedDid,
+        DID_MODULE,
+    },
+    errors::SubstrateError,
+    extrinsic::{
+        events::{
+            DispatchError,
+            EventRecord,
+            EventTypes,
+            EventsDecoder,
+            Phase,
+            RawEvent,
+            SystemEvent,
+        },
+        frame_metadata::RuntimeMetadataPrefixed,
+        keypair::{to_ss58, ExtrinsicSigner},
+        node_metadata::Metadata,
+        rpc::{
+            client::{on_extrinsic_msg, parse_status, status_error},
+            RpcClient,
+            XtStatus,
+        },
+        xt_primitives::{
+            self,
+            AdditionalSigned,
+            Era,
+            GenericExtra,
+            MultiAddress,
+            SignedPayload,
+            UncheckedExtrinsicV4,
         },
-        start_rpc_client_thread,
-        XtStatus,
     },
-    xt_primitives,
+    timeouts::{timeout, RetryPolicy, Timeouts},
 };
 use futures::{
-    channel::mpsc::{channel, Receiver, Sender},
-    stream::StreamExt,
+    channel::mpsc::UnboundedReceiver,
+    future::{Future, FutureExt},
+    stream::{self, FuturesUnordered, Stream, StreamExt},
 };
-use parity_scale_codec::{Decode, Encode, Error as CodecError};
-use rand::Rng;
+use futures_timer::Delay;
+use parity_scale_codec::{Compact, Decode, Encode};
 use secp256k1::{Message, RecoveryId, Signature};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use sha2::Digest;
 use sha3::Keccak256;
+use sp_core::H256;
 use sp_std::prelude::*;
 use std::{
+    collections::{HashMap, HashSet, VecDeque},
     convert::{TryFrom, TryInto},
     error::Error,
     hash::Hasher,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 use vade_signer::Signer;
 
+/// URLs used to connect to a substrate node and to the IPFS gateway that holds DID documents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubstrateEndpoints {
+    /// URL for HTTP RPC requests, e.g. `http://127.0.0.1:9933`
+    pub http_rpc: String,
+    /// URL for websocket RPC requests and subscriptions, e.g. `ws://127.0.0.1:9944`
+    pub ws_rpc: String,
+    /// Base URL of IPFS gateway, content is fetched from `{ipfs}/{hash}`
+    pub ipfs: String,
+}
+
+impl SubstrateEndpoints {
+    /// Creates endpoints for a node setup with default layout, that serves HTTP RPC at
+    /// `{target}/rpc`, websocket RPC at `{target}/ws` and an IPFS gateway at `{target}/ipfs`.
+    /// Targets without scheme are served via TLS, `http://` and `ws://` targets without it.
+    ///
+    /// # Arguments
+    /// * `target` - substrate host, e.g. `substrate-dev.trust-trace.com` or `http://127.0.0.1:8080`
+    pub fn from_target(target: &str) -> SubstrateEndpoints {
+        let (secure, host) = match target.split_once("://") {
+            Some(("http", host)) | Some(("ws", host)) => (false, host),
+            Some((_, host)) => (true, host),
+            None => (true, target),
+        };
+        let host = host.trim_end_matches('/');
+        let (http, ws) = if secure {
+            ("https", "wss")
+        } else {
+            ("http", "ws")
+        };
+        SubstrateEndpoints {
+            http_rpc: format!("{}://{}/rpc", http, host),
+            ws_rpc: format!("{}://{}/ws", ws, host),
+            ipfs: format!("{}://{}/ipfs", http, host),
+        }
+    }
+}
+
+/// Connection to a substrate node, used for all requests against this node.
+pub struct SubstrateClient {
+    /// URLs of node and IPFS gateway
+    pub endpoints: SubstrateEndpoints,
+    /// Persistent websocket connection for RPC requests and subscriptions
+    pub rpc_client: RpcClient,
+    /// HTTP client for RPC requests, reused so connections to the node are pooled
+    pub http_client: reqwest::Client,
+    /// Runtime metadata fetched from this node
+    pub metadata_cache: MetadataCache,
+    /// Store DID documents are fetched from, defaults to IPFS gateway from `endpoints`
+    pub content_store: Box<dyn ContentStore>,
+    /// Substrate account extrinsics are signed with, extrinsics are sent unsigned if omitted
+    pub extrinsic_signer: Option<Box<dyn ExtrinsicSigner>>,
+    /// Nonces handed out to DID module calls per identity
+    pub identity_nonces: NonceTracker,
+    /// Nonces handed out to signed extrinsics per account
+    pub account_nonces: NonceTracker,
+    /// Extrinsics submitted without waiting for their status
+    pub transaction_tracker: TransactionTracker,
+    /// Timeouts for RPC requests, event waits and content fetches
+    pub timeouts: Timeouts,
+    /// Retries of storage and metadata requests, that have failed with transient errors
+    pub retry_policy: RetryPolicy,
+    /// Custom types used to decode event arguments in addition to the default types
+    pub event_types: EventTypes,
+}
+
+impl SubstrateClient {
+    /// Creates a new `SubstrateClient`, websocket connection is opened on its first usage.
+    ///
+    /// # Arguments
+    /// * `endpoints` - URLs of substrate node and IPFS gateway
+    pub fn new(endpoints: SubstrateEndpoints) -> SubstrateClient {
+        let mut event_types = EventTypes::default();
+        register_did_module_types(&mut event_types);
+        SubstrateClient {
+            rpc_client: RpcClient::new(&endpoints.ws_rpc),
+            http_client: reqwest::Client::new(),
+            content_store: Box::new(IpfsGateway::new(&endpoints.ipfs)),
+            endpoints,
+            metadata_cache: MetadataCache::default(),
+            extrinsic_signer: None,
+            identity_nonces: NonceTracker::default(),
+            account_nonces: NonceTracker::default(),
+            transaction_tracker: TransactionTracker::default(),
+            timeouts: Timeouts::default(),
+            retry_policy: RetryPolicy::default(),
+            event_types,
+        }
+    }
+
+    /// Creates a decoder for events of a runtime, that supports the custom types of `event_types`.
+    ///
+    /// # Arguments
+    /// * `metadata` - metadata of runtime
+    pub fn events_decoder(&self, metadata: Metadata) -> Result<EventsDecoder, SubstrateError> {
+        get_events_decoder(metadata, &self.event_types)
+    }
+
+    /// Gets metadata of the runtime currently used by the node. Metadata is only fetched again if
+    /// the runtime version has changed since the last call.
+    pub async fn get_metadata(&self) -> Result<Metadata, Box<dyn Error>> {
+        let url = &self.endpoints.http_rpc;
+        let spec_version = self
+            .retry_policy
+            .retry("fetching runtime version", || get_runtime_version(self))
+            .await?
+            .spec_version;
+        if let Some(metadata) = self.metadata_cache.get(url, spec_version)? {
+            return Ok(metadata);
+        }
+        debug!(
+            "fetching metadata for spec version {} from {}",
+            spec_version, url
+        );
+        let metadata = self
+            .retry_policy
+            .retry("fetching metadata", || get_metadata(self))
+            .await?;
+        self.metadata_cache
+            .insert(url, spec_version, metadata.clone())?;
+        Ok(metadata)
+    }
+}
+
+/// Creates a decoder for events of a runtime, that supports the custom types of `event_types`.
+fn get_events_decoder(
+    metadata: Metadata,
+    event_types: &EventTypes,
+) -> Result<EventsDecoder, SubstrateError> {
+    Ok(EventsDecoder::try_from(metadata)
+        .map_err(|err| SubstrateError::Metadata(format!("could not get decoder; {}", &err)))?
+        .with_types(event_types))
+}
+
+/// Parsed runtime metadata per endpoint, keyed by the runtime `specVersion` it belongs to. Clones
+/// share the same entries.
+#[derive(Clone, Default)]
+pub struct MetadataCache {
+    entries: Arc<Mutex<HashMap<String, (u32, Metadata)>>>,
+}
+
+impl MetadataCache {
+    /// Gets cached metadata for `url`, returns `None` if nothing has been cached yet or if cached
+    /// metadata belongs to another runtime version.
+    pub fn get(&self, url: &str, spec_version: u32) -> Result<Option<Metadata>, Box<dyn Error>> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|_| "metadata cache is poisoned")?;
+        Ok(match entries.get(url) {
+            Some((cached_version, metadata)) if *cached_version == spec_version => {
+                Some(metadata.clone())
+            }
+            _ => None,
+        })
+    }
+
+    /// Stores metadata for `url`, replaces metadata of previous runtime versions.
+    pub fn insert(
+        &self,
+        url: &str,
+        spec_version: u32,
+        metadata: Metadata,
+    ) -> Result<(), Box<dyn Error>> {
+        self.entries
+            .lock()
+            .map_err(|_| "metadata cache is poisoned")?
+            .insert(url.to_string(), (spec_version, metadata));
+        Ok(())
+    }
+}
+
+/// Nonces handed out per account or identity, so concurrent requests of one client get sequential
+/// nonces instead of reading the same value from chain. Nonces, that have been handed out, but are
+/// not on chain yet, are added to the nonce read from chain.
+#[derive(Default)]
+pub struct NonceTracker {
+    next: Mutex<HashMap<Vec<u8>, u64>>,
+}
+
+impl NonceTracker {
+    /// Takes the next nonce for `key`. Nonces are increased with every call, but never fall below
+    /// `chain_nonce`, the lowest nonce not used on chain yet.
+    pub fn take(&self, key: &[u8], chain_nonce: u64) -> Result<u64, Box<dyn Error>> {
+        let mut next = self.next.lock().map_err(|_| "nonce tracker is poisoned")?;
+        let entry = next.entry(key.to_vec()).or_insert(chain_nonce);
+        let nonce = (*entry).max(chain_nonce);
+        *entry = nonce + 1;
+        Ok(nonce)
+    }
+
+    /// Forgets nonces handed out for `key`, next nonce is taken from chain again.
+    pub fn reset(&self, key: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.next
+            .lock()
+            .map_err(|_| "nonce tracker is poisoned")?
+            .remove(key);
+        Ok(())
+    }
+}
+
+/// Number of blocks, whose events are kept while creating multiple DIDs, as submissions may report
+/// their block after its events have been received.
+const BUFFERED_BLOCKS: usize = 16;
+
+/// Status of an extrinsic submitted with [`submit_extrinsic`](submit_extrinsic).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum TransactionStatus {
+    /// Extrinsic has been submitted, but node has not reported a status yet
+    Submitted,
+    /// Extrinsic is in the transaction pool of the node
+    Ready,
+    /// Extrinsic has been sent to other nodes
+    Broadcast,
+    /// Extrinsic has been included in a block and has been dispatched successfully
+    #[serde(rename_all = "camelCase")]
+    InBlock { block_hash: String },
+    /// Block of extrinsic has been finalized, extrinsic has been dispatched successfully
+    #[serde(rename_all = "camelCase")]
+    Finalized { block_hash: String },
+    /// Extrinsic has been rejected or its dispatch has failed
+    #[serde(rename_all = "camelCase")]
+    Failed {
+        block_hash: Option<String>,
+        error: String,
+    },
+}
+
+/// Extrinsic, whose status is watched by a [`TransactionTracker`](TransactionTracker).
+struct WatchedTransaction {
+    xthex_prefixed: String,
+    status: TransactionStatus,
+    /// true if current status has been handled, e.g. if dispatch result has been checked
+    handled: bool,
+    /// status updates of node, dropped after final status has been received
+    receiver: Option<UnboundedReceiver<String>>,
+    /// time of submission in milliseconds since unix epoch
+    submitted_at: u64,
+}
+
+/// Time to keep tracking extrinsics, whose final status has not been requested, e.g. because their
+/// submitter does not check their status anymore.
+const TRANSACTION_TTL: Duration = Duration::from_secs(600);
+
+/// Current time in milliseconds since unix epoch.
 #[cfg(not(target_arch = "wasm32"))]
-use chrono::Utc;
+fn now_millis() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+/// Current time in milliseconds since unix epoch.
 #[cfg(target_arch = "wasm32")]
-use instant::Instant;
-#[cfg(not(target_arch = "wasm32"))]
-use std::time::Instant;
+fn now_millis() -> u64 {
+    js_sys::Date::now() as u64
+}
 
-const SUBSTRATE_TIMEOUT: u64 = 60;
+/// Extrinsics submitted without waiting for their status, keyed by extrinsic hash. Extrinsics are
+/// removed once their final status has been handled or after `TRANSACTION_TTL`.
+#[derive(Default)]
+pub struct TransactionTracker {
+    transactions: Mutex<HashMap<String, WatchedTransaction>>,
+}
+
+impl TransactionTracker {
+    /// Starts tracking the status updates of an extrinsic and stops tracking extrinsics, that have
+    /// been submitted more than `TRANSACTION_TTL` ago.
+    fn insert(
+        &self,
+        hash: &str,
+        xthex_prefixed: &str,
+        receiver: UnboundedReceiver<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut transactions = self
+            .transactions
+            .lock()
+            .map_err(|_| "transaction tracker is poisoned")?;
+        let now = now_millis();
+        transactions.retain(|_, transaction| {
+            now.saturating_sub(transaction.submitted_at) < TRANSACTION_TTL.as_millis() as u64
+        });
+        transactions.insert(
+            hash.to_string(),
+            WatchedTransaction {
+                xthex_prefixed: xthex_prefixed.to_string(),
+                status: TransactionStatus::Submitted,
+                handled: false,
+                receiver: Some(receiver),
+                submitted_at: now,
+            },
+        );
+        Ok(())
+    }
 
+    /// Applies all status updates received for extrinsic `hash` since the last call.
+    ///
+    /// # Returns
+    /// * `(TransactionStatus, String, bool)` - latest status, hex encoded extrinsic and if status has
+    ///   been handled already
+    fn update(&self, hash: &str) -> Result<(TransactionStatus, String, bool), Box<dyn Error>> {
+        let mut transactions = self
+            .transactions
+            .lock()
+            .map_err(|_| "transaction tracker is poisoned")?;
+        let transaction = transactions
+            .get_mut(hash)
+            .ok_or_else(|| format!("unknown transaction {}", hash))?;
+        while let Some(receiver) = &mut transaction.receiver {
+            let msg = match receiver.next().now_or_never() {
+                Some(Some(msg)) => msg,
+                // subscription has been closed
+                Some(None) => {
+                    transaction.receiver = None;
+                    break;
+                }
+                // no new status updates
+                None => break,
+            };
+            let status = match parse_status(&msg) {
+                (XtStatus::Ready, _) => TransactionStatus::Ready,
+                (XtStatus::Broadcast, _) => TransactionStatus::Broadcast,
+                (XtStatus::InBlock, Some(block_hash)) => TransactionStatus::InBlock { block_hash },
+                (XtStatus::Finalized, Some(block_hash)) => {
+                    TransactionStatus::Finalized { block_hash }
+                }
+                // extrinsics of retracted blocks are put back into the pool
+                (XtStatus::Retracted, _) => TransactionStatus::Ready,
+                (status, data) => match status_error(status, data.as_deref()) {
+                    Some(error) => TransactionStatus::Failed {
+                        block_hash: match status {
+                            XtStatus::FinalityTimeout => data,
+                            _ => None,
+                        },
+                        error,
+                    },
+                    None => continue,
+                },
+            };
+            if status != transaction.status {
+                transaction.status = status;
+                transaction.handled = false;
+            }
+            if let TransactionStatus::Finalized { .. } | TransactionStatus::Failed { .. } =
+                transaction.status
+            {
+                // stop watching extrinsic
+                transaction.receiver = None;
+            }
+        }
+        Ok((
+            transaction.status.clone(),
+            transaction.xthex_prefixed.clone(),
+            transaction.handled,
+        ))
+    }
+
+    /// Marks the current status of extrinsic `hash` as handled, extrinsics with a final status are
+    /// not tracked anymore.
+    fn set_handled(&self, hash: &str) -> Result<(), Box<dyn Error>> {
+        let mut transactions = self
+            .transactions
+            .lock()
+            .map_err(|_| "transaction tracker is poisoned")?;
+        match transactions.get_mut(hash) {
+            Some(WatchedTransaction {
+                status: TransactionStatus::Finalized { .. },
+                ..
+            })
+            | Some(WatchedTransaction {
+                status: TransactionStatus::Failed { .. },
+                ..
+            }) => {
+                transactions.remove(hash);
+            }
+            Some(transaction) => transaction.handled = true,
+            None => (),
+        }
+        Ok(())
+    }
+
+    /// Stops tracking extrinsic `hash`, as its dispatch has failed.
+    ///
+    /// # Returns
+    /// * `TransactionStatus` - final `Failed` status of extrinsic
+    fn fail(
+        &self,
+        hash: &str,
+        block_hash: &str,
+        error: String,
+    ) -> Result<TransactionStatus, Box<dyn Error>> {
+        self.transactions
+            .lock()
+            .map_err(|_| "transaction tracker is poisoned")?
+            .remove(hash);
+        Ok(TransactionStatus::Failed {
+            block_hash: Some(block_hash.to_string()),
+            error,
+        })
+    }
+}
+
+/// Sends a JSON-RPC request via HTTP and returns the `result` field of its response.
+async fn http_rpc_request(client: &SubstrateClient, json: Value) -> Result<Value, SubstrateError> {
+    let request = async {
+        client
+            .http_client
+            .post(&client.endpoints.http_rpc)
+            .header("Content-Type", "application/json")
+            .body(json.to_string())
+            .send()
+            .await
+            .map_err(|err| SubstrateError::Transport(err.to_string()))?
+            .text()
+            .await
+            .map_err(|err| SubstrateError::Transport(err.to_string()))
+    };
+    let description = format!("waiting for response for '{}'", json["method"]);
+    let body = timeout(client.timeouts.rpc, &description, request).await??;
+    let mut parsed: Value = serde_json::from_str(&body)
+        .map_err(|err| SubstrateError::Transport(format!("could not parse response; {}", &err)))?;
+    if !parsed["error"].is_null() {
+        return Err(SubstrateError::Rpc(format!(
+            "error in response for '{}'; {}",
+            json["method"], parsed["error"]
+        )));
+    }
+    Ok(parsed["result"].take())
+}
+
+/// Reads a value from a storage map. Requests, that have failed with transient errors, are retried
+/// according to the `retry_policy` of `client`.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `metadata` - metadata of runtime
+/// * `storage_prefix` - module of storage, e.g. `DidModule`
+/// * `storage_key_name` - name of storage map, e.g. `DidsDetails`
+/// * `map_key` - key of entry to read
+/// * `at` - hash of block to read storage at, reads at latest block if omitted
 pub async fn get_storage_map<K: Encode + std::fmt::Debug, V: Decode + Clone>(
-    url: &str,
-    metadata: Metadata,
+    client: &SubstrateClient,
+    metadata: &Metadata,
     storage_prefix: &'static str,
     storage_key_name: &'static str,
     map_key: K,
+    at: Option<&str>,
 ) -> Result<Option<V>, Box<dyn Error>> {
     let storagekey: sp_core::storage::StorageKey = metadata
         .module(storage_prefix)?
         .storage(storage_key_name)?
         .get_map::<K, V>()?
         .key(map_key);
-    let hex_string = format!("0x{}", hex::encode(storagekey.0.clone()));
-    let json = json_req("state_getStorage", &hex_string.to_string(), 1);
-    let client = reqwest::Client::new();
-    let body = client
-        .post(&format!("https://{}/rpc", url).to_string())
-        .header("Content-Type", "application/json")
-        .body(json.to_string())
-        .send()
+    client
+        .retry_policy
+        .retry("reading storage map", || {
+            get_storage(client, storagekey.clone(), at)
+        })
+        .await
+}
+
+/// Reads a plain storage value. Requests, that have failed with transient errors, are retried
+/// according to the `retry_policy` of `client`.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `metadata` - metadata of runtime
+/// * `storage_prefix` - module of storage, e.g. `Timestamp`
+/// * `storage_key_name` - name of storage value, e.g. `Now`
+/// * `at` - hash of block to read storage at, reads at latest block if omitted
+pub async fn get_storage_value<V: Decode>(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    storage_prefix: &'static str,
+    storage_key_name: &'static str,
+    at: Option<&str>,
+) -> Result<Option<V>, Box<dyn Error>> {
+    let storagekey: sp_core::storage::StorageKey = metadata
+        .module(storage_prefix)?
+        .storage(storage_key_name)?
+        .get_value()?
+        .key();
+    client
+        .retry_policy
+        .retry("reading storage value", || {
+            get_storage(client, storagekey.clone(), at)
+        })
+        .await
+}
+
+/// Reads a value from a storage double map. Requests, that have failed with transient errors, are
+/// retried according to the `retry_policy` of `client`.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `metadata` - metadata of runtime
+/// * `storage_prefix` - module of storage
+/// * `storage_key_name` - name of storage double map
+/// * `key1` - first key of entry to read
+/// * `key2` - second key of entry to read
+/// * `at` - hash of block to read storage at, reads at latest block if omitted
+pub async fn get_storage_double_map<K: Encode, Q: Encode, V: Decode + Clone>(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    storage_prefix: &'static str,
+    storage_key_name: &'static str,
+    key1: K,
+    key2: Q,
+    at: Option<&str>,
+) -> Result<Option<V>, Box<dyn Error>> {
+    let storagekey: sp_core::storage::StorageKey = metadata
+        .module(storage_prefix)?
+        .storage(storage_key_name)?
+        .get_double_map::<K, Q, V>()?
+        .key(key1, key2);
+    client
+        .retry_policy
+        .retry("reading storage double map", || {
+            get_storage(client, storagekey.clone(), at)
+        })
+        .await
+}
+
+/// Reads all entries of a storage map. Keys can only be read from maps, that use the `Identity`,
+/// `Blake2_128Concat` or `Twox64Concat` hasher.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `metadata` - metadata of runtime
+/// * `storage_prefix` - module of storage, e.g. `DidModule`
+/// * `storage_key_name` - name of storage map, e.g. `WhitelistedIdentities`
+/// * `at` - hash of block to read storage at, reads at latest block if omitted
+///
+/// # Returns
+/// * `Vec<(K, V)>` - keys and values of all entries, ordered by their storage keys
+pub async fn get_storage_map_entries<K: Encode + Decode, V: Decode + Clone>(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    storage_prefix: &'static str,
+    storage_key_name: &'static str,
+    at: Option<&str>,
+) -> Result<Vec<(K, V)>, Box<dyn Error>> {
+    let map = metadata
+        .module(storage_prefix)?
+        .storage(storage_key_name)?
+        .get_map::<K, V>()?;
+    get_storage_entries(client, map.prefix(), at)
         .await?
-        .text()
+        .into_iter()
+        .map(|(key, value)| {
+            Ok((
+                map.decode_key(&key)?,
+                Decode::decode(&mut value.as_slice()).map_err(SubstrateError::Codec)?,
+            ))
+        })
+        .collect()
+}
+
+/// Reads all entries of a storage double map, that share their first key. Second keys can only be
+/// read from maps, that use the `Identity`, `Blake2_128Concat` or `Twox64Concat` hasher for them.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `metadata` - metadata of runtime
+/// * `storage_prefix` - module of storage
+/// * `storage_key_name` - name of storage double map
+/// * `key1` - first key of entries to read
+/// * `at` - hash of block to read storage at, reads at latest block if omitted
+///
+/// # Returns
+/// * `Vec<(Q, V)>` - second keys and values of all entries, ordered by their storage keys
+pub async fn get_storage_double_map_entries<
+    K: Encode + Clone,
+    Q: Encode + Decode,
+    V: Decode + Clone,
+>(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    storage_prefix: &'static str,
+    storage_key_name: &'static str,
+    key1: K,
+    at: Option<&str>,
+) -> Result<Vec<(Q, V)>, Box<dyn Error>> {
+    let map = metadata
+        .module(storage_prefix)?
+        .storage(storage_key_name)?
+        .get_double_map::<K, Q, V>()?;
+    get_storage_entries(client, map.prefix(key1.clone()), at)
+        .await?
+        .into_iter()
+        .map(|(key, value)| {
+            Ok((
+                map.decode_key2(key1.clone(), &key)?,
+                Decode::decode(&mut value.as_slice()).map_err(SubstrateError::Codec)?,
+            ))
+        })
+        .collect()
+}
+
+/// Number of keys, that are requested per page when iterating over storage entries.
+const STORAGE_KEYS_PAGE_SIZE: u32 = 512;
+
+/// Reads keys and values of all storage entries with a common prefix. All pages are read at the
+/// same block, so entries are consistent even if new blocks are created while reading them.
+async fn get_storage_entries(
+    client: &SubstrateClient,
+    prefix: sp_core::storage::StorageKey,
+    at: Option<&str>,
+) -> Result<Vec<(sp_core::storage::StorageKey, Vec<u8>)>, Box<dyn Error>> {
+    let block_hash = match at {
+        Some(block_hash) => block_hash.to_string(),
+        None => get_block_hash(client, None)
+            .await?
+            .ok_or_else(|| SubstrateError::Rpc("latest block not found".to_string()))?,
+    };
+    let mut entries = Vec::new();
+    let mut start_key = None;
+    loop {
+        let keys = get_storage_keys_paged(
+            client,
+            prefix.clone(),
+            STORAGE_KEYS_PAGE_SIZE,
+            start_key.clone(),
+            Some(&block_hash),
+        )
         .await?;
-    let parsed: Value = serde_json::from_str(&body)?;
-    let result = match parsed["result"].as_str() {
-        None => None,
-        _ => Some(hex::decode(
-            &parsed["result"]
+        if keys.is_empty() {
+            break;
+        }
+        let page_size = keys.len();
+        start_key = keys.last().cloned();
+        for (key, value) in query_storage_at(client, keys, Some(&block_hash)).await? {
+            if let Some(value) = value {
+                entries.push((key, value));
+            }
+        }
+        if page_size < STORAGE_KEYS_PAGE_SIZE as usize {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+/// Gets a page of the storage keys, that start with a given prefix, via `state_getKeysPaged`.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `prefix` - prefix of keys, e.g. the prefix of a storage map
+/// * `count` - maximum number of keys to return
+/// * `start_key` - return only keys after this key, starts with first key if omitted
+/// * `at` - hash of block to read keys at, reads at latest block if omitted
+///
+/// # Returns
+/// * `Vec<StorageKey>` - keys in ascending order
+pub async fn get_storage_keys_paged(
+    client: &SubstrateClient,
+    prefix: sp_core::storage::StorageKey,
+    count: u32,
+    start_key: Option<sp_core::storage::StorageKey>,
+    at: Option<&str>,
+) -> Result<Vec<sp_core::storage::StorageKey>, Box<dyn Error>> {
+    let json = json!({
+        "method": "state_getKeysPaged",
+        "params": [
+            format!("0x{}", hex::encode(&prefix.0)),
+            count,
+            start_key.map(|key| format!("0x{}", hex::encode(&key.0))),
+            at,
+        ],
+        "jsonrpc": "2.0",
+        "id": "1",
+    });
+    let result = client
+        .retry_policy
+        .retry("reading storage keys", || {
+            http_rpc_request(client, json.clone()).map(|result| result.map_err(Into::into))
+        })
+        .await?;
+    result
+        .as_array()
+        .ok_or("invalid response for 'state_getKeysPaged'")?
+        .iter()
+        .map(|key| {
+            let key = key
+                .as_str()
+                .ok_or("invalid key in response for 'state_getKeysPaged'")?;
+            Ok(sp_core::storage::StorageKey(hex::decode(
+                key.trim_start_matches("0x"),
+            )?))
+        })
+        .collect()
+}
+
+/// Reads the values of multiple storage keys at once via `state_queryStorageAt`.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `keys` - keys to read
+/// * `at` - hash of block to read storage at, reads at latest block if omitted
+///
+/// # Returns
+/// * `Vec<(StorageKey, Option<Vec<u8>>)>` - keys with their raw values, `None` for empty keys
+pub async fn query_storage_at(
+    client: &SubstrateClient,
+    keys: Vec<sp_core::storage::StorageKey>,
+    at: Option<&str>,
+) -> Result<Vec<(sp_core::storage::StorageKey, Option<Vec<u8>>)>, Box<dyn Error>> {
+    let json = json!({
+        "method": "state_queryStorageAt",
+        "params": [
+            keys.iter()
+                .map(|key| format!("0x{}", hex::encode(&key.0)))
+                .collect::<Vec<String>>(),
+            at,
+        ],
+        "jsonrpc": "2.0",
+        "id": "1",
+    });
+    let result = client
+        .retry_policy
+        .retry("querying storage", || {
+            http_rpc_request(client, json.clone()).map(|result| result.map_err(Into::into))
+        })
+        .await?;
+    let mut values = Vec::with_capacity(keys.len());
+    for change_set in result
+        .as_array()
+        .ok_or("invalid response for 'state_queryStorageAt'")?
+    {
+        for change in change_set["changes"]
+            .as_array()
+            .ok_or("invalid change set in response for 'state_queryStorageAt'")?
+        {
+            let key = change[0]
                 .as_str()
-                .ok_or("could not parse storage map result")?
-                .trim_start_matches("0x"),
-        )?),
+                .ok_or("invalid key in response for 'state_queryStorageAt'")?;
+            let value = match change[1].as_str() {
+                Some(value) => Some(hex::decode(value.trim_start_matches("0x"))?),
+                None => None,
+            };
+            values.push((
+                sp_core::storage::StorageKey(hex::decode(key.trim_start_matches("0x"))?),
+                value,
+            ));
+        }
+    }
+    Ok(values)
+}
+
+async fn get_storage<V: Decode>(
+    client: &SubstrateClient,
+    storagekey: sp_core::storage::StorageKey,
+    at: Option<&str>,
+) -> Result<Option<V>, Box<dyn Error>> {
+    match get_storage_bytes(client, storagekey, at).await? {
+        Some(value) => Ok(Some(
+            Decode::decode(&mut value.as_slice()).map_err(SubstrateError::Codec)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+async fn get_storage_bytes(
+    client: &SubstrateClient,
+    storagekey: sp_core::storage::StorageKey,
+    at: Option<&str>,
+) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    let hex_string = format!("0x{}", hex::encode(storagekey.0));
+    let json = match at {
+        Some(block_hash) => json!({
+            "method": "state_getStorage",
+            "params": [hex_string, block_hash],
+            "jsonrpc": "2.0",
+            "id": "1",
+        }),
+        None => json_req("state_getStorage", &hex_string, 1),
+    };
+    Ok(match http_rpc_request(client, json).await?.as_str() {
+        None => None,
+        Some(value) => Some(hex::decode(value.trim_start_matches("0x"))?),
+    })
+}
+
+/// Gets the hash of a block.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `number` - number of block, gets hash of latest block if omitted
+///
+/// # Returns
+/// * `Option<String>` - hex encoded block hash or `None` if block does not exist
+pub async fn get_block_hash(
+    client: &SubstrateClient,
+    number: Option<u64>,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let json = json!({
+        "method": "chain_getBlockHash",
+        "params": number.map(|number| vec![number]).unwrap_or_default(),
+        "jsonrpc": "2.0",
+        "id": "1",
+    });
+    Ok(http_rpc_request(client, json)
+        .await?
+        .as_str()
+        .map(|hash| hash.to_string()))
+}
+
+/// Gets the number of a block.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `block_hash` - hash of block
+pub async fn get_block_number(
+    client: &SubstrateClient,
+    block_hash: &str,
+) -> Result<u64, Box<dyn Error>> {
+    let header = http_rpc_request(client, json_req("chain_getHeader", block_hash, 1)).await?;
+    let number = header["number"]
+        .as_str()
+        .ok_or_else(|| SubstrateError::BlockNotFound(block_hash.to_string()))?;
+    Ok(u64::from_str_radix(number.trim_start_matches("0x"), 16)?)
+}
+
+/// Gets the latest block, that has been created at or before a given time.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `timestamp` - time in milliseconds since unix epoch
+///
+/// # Returns
+/// * `String` - hex encoded block hash
+pub async fn get_block_hash_at_time(
+    client: &SubstrateClient,
+    timestamp: u64,
+) -> Result<String, Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    let head = get_block_hash(client, None)
+        .await?
+        .ok_or("could not get latest block")?;
+    let metadata = &metadata;
+    let block_time = |number: u64| {
+        async move {
+            let hash = get_block_hash(client, Some(number))
+                .await?
+                .ok_or_else(|| format!("could not get hash of block {}", number))?;
+            // genesis block has no timestamp
+            let time: u64 = get_storage_value(client, metadata, "Timestamp", "Now", Some(&hash))
+                .await?
+                .unwrap_or(0);
+            Ok::<_, Box<dyn Error>>((hash, time))
+        }
     };
+    // find last block with a timestamp not after given time
+    let (mut low, mut high) = (0, get_block_number(client, &head).await?);
+    let (head_hash, head_time) = block_time(high).await?;
+    if head_time <= timestamp {
+        return Ok(head_hash);
+    }
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if block_time(middle).await?.1 <= timestamp {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(block_time(low).await?.0)
+}
 
-    if let Some(v) = result {
-        match Decode::decode(&mut v.as_slice()) {
-            Ok(ok) => {
-                return Ok(Some(ok));
+/// Fetches the metadata of the runtime currently used by the node, use
+/// [`SubstrateClient::get_metadata`](SubstrateClient::get_metadata) to get cached metadata.
+pub async fn get_metadata(client: &SubstrateClient) -> Result<Metadata, Box<dyn Error>> {
+    let json = json!({
+        "method": "state_getMetadata",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": "1",
+    });
+    parse_metadata(&http_rpc_request(client, json).await?)
+}
+
+/// Parses the hex encoded metadata of a `state_getMetadata` result.
+fn parse_metadata(result: &Value) -> Result<Metadata, Box<dyn Error>> {
+    let hex_value = result
+        .as_str()
+        .ok_or_else(|| SubstrateError::Transport("could parse metadata result".to_string()))?
+        .to_string();
+    let _unhex = hexstr_to_vec(hex_value)?;
+    let mut _om = _unhex.as_slice();
+    let meta = RuntimeMetadataPrefixed::decode(&mut _om).map_err(SubstrateError::Codec)?;
+    let metadata2 =
+        Metadata::parse(meta).map_err(|err| SubstrateError::Metadata(err.to_string()))?;
+    Ok(metadata2)
+}
+
+/// Versions of the runtime currently used by a node.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RuntimeVersion {
+    /// Version of the runtime specification, changes with every runtime upgrade
+    pub spec_version: u32,
+    /// Version of the extrinsic format, changes if calls or signed extras change
+    pub transaction_version: u32,
+}
+
+/// Gets the `specVersion` and `transactionVersion` of the runtime currently used by the node.
+pub async fn get_runtime_version(
+    client: &SubstrateClient,
+) -> Result<RuntimeVersion, Box<dyn Error>> {
+    let json = json!({
+        "method": "state_getRuntimeVersion",
+        "params": null,
+        "jsonrpc": "2.0",
+        "id": "1",
+    });
+    Ok(parse_runtime_version(
+        &http_rpc_request(client, json).await?,
+    )?)
+}
+
+/// Parses the versions of a `state_getRuntimeVersion` result.
+fn parse_runtime_version(result: &Value) -> Result<RuntimeVersion, SubstrateError> {
+    let get_version = |name: &str| {
+        result[name]
+            .as_u64()
+            .map(|version| version as u32)
+            .ok_or_else(|| {
+                SubstrateError::Transport("could not parse runtime version result".to_string())
+            })
+    };
+    Ok(RuntimeVersion {
+        spec_version: get_version("specVersion")?,
+        transaction_version: get_version("transactionVersion")?,
+    })
+}
+
+/// Gets the nonce of the next extrinsic of an account, including extrinsics in the pool.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `account_id` - account to get nonce for
+pub async fn get_account_next_index(
+    client: &SubstrateClient,
+    account_id: &xt_primitives::AccountId,
+) -> Result<u32, Box<dyn Error>> {
+    let json = json!({
+        "method": "system_accountNextIndex",
+        "params": [to_ss58(account_id)],
+        "jsonrpc": "2.0",
+        "id": "1",
+    });
+    let nonce = http_rpc_request(client, json)
+        .await?
+        .as_u64()
+        .ok_or_else(|| SubstrateError::Transport("could not parse account nonce".to_string()))?;
+    Ok(nonce as u32)
+}
+
+/// Signs an extrinsic with the `extrinsic_signer` of `client`. Extrinsics are immortal and do not
+/// include a tip. If `client` has no `extrinsic_signer`, the extrinsic is returned unsigned. The
+/// signed payload is built from the signed extensions in `metadata`, runtimes with extensions,
+/// that need data this crate does not provide, are rejected with `SubstrateError::Metadata`.
+///
+/// Account nonces are tracked by `client`, call
+/// [`reset_account_nonce`](reset_account_nonce) if a signed extrinsic could not be submitted.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `metadata` - metadata of the runtime
+/// * `xt` - unsigned extrinsic
+///
+/// # Returns
+/// * `UncheckedExtrinsicV4` - signed extrinsic
+pub async fn sign_extrinsic<Call: Encode + Clone>(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    xt: UncheckedExtrinsicV4<Call>,
+) -> Result<UncheckedExtrinsicV4<Call>, Box<dyn Error>> {
+    let extrinsic_signer = match &client.extrinsic_signer {
+        Some(extrinsic_signer) => extrinsic_signer,
+        None => return Ok(xt),
+    };
+    let account_id = extrinsic_signer.account_id();
+    let chain_nonce = get_account_next_index(client, &account_id).await?;
+    let runtime_version = get_runtime_version(client).await?;
+    let genesis_hash = get_block_hash(client, Some(0))
+        .await?
+        .ok_or_else(|| SubstrateError::Transport("could not get genesis hash".to_string()))?;
+    let genesis_hash = H256::from_slice(&hexstr_to_vec(genesis_hash)?);
+    let additional_signed = AdditionalSigned::new(
+        metadata.signed_extensions(),
+        runtime_version.spec_version,
+        runtime_version.transaction_version,
+        genesis_hash,
+        genesis_hash,
+    )?;
+    // take nonce after last request, so extrinsics are submitted in the order of their nonces
+    let nonce = u32::try_from(
+        client
+            .account_nonces
+            .take(&account_id, chain_nonce.into())?,
+    )?;
+
+    let extra = GenericExtra::new(Era::Immortal, nonce);
+    let payload = SignedPayload::from_raw(xt.function.clone(), extra.clone(), additional_signed);
+    let signature = payload.using_encoded(|payload| extrinsic_signer.sign(payload));
+
+    Ok(UncheckedExtrinsicV4::new_signed(
+        xt.function,
+        MultiAddress::Id(account_id),
+        signature,
+        extra,
+    ))
+}
+
+/// Lets the next signed extrinsic of `client` read its account nonce from chain again, so nonces of
+/// extrinsics, that could not be submitted, are not skipped.
+pub fn reset_account_nonce(client: &SubstrateClient) -> Result<(), Box<dyn Error>> {
+    match &client.extrinsic_signer {
+        Some(extrinsic_signer) => client.account_nonces.reset(&extrinsic_signer.account_id()),
+        None => Ok(()),
+    }
+}
+
+/// Status of an extrinsic sent with [`send_extrinsic`](send_extrinsic).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtrinsicStatus {
+    /// Hash of the extrinsic
+    pub hash: String,
+    /// Status the extrinsic has reached, `Finalized` may be reported instead of an earlier status
+    pub status: XtStatus,
+    /// Hash of the block, that includes the extrinsic, set for `InBlock` and `Finalized` status
+    pub block_hash: Option<String>,
+}
+
+/// Returns true if an extrinsic watched until `status` has been included in a block, so its events
+/// have been emitted.
+pub fn is_in_block(status: XtStatus) -> bool {
+    matches!(status, XtStatus::InBlock | XtStatus::Finalized)
+}
+
+/// Submits an extrinsic and waits until it has reached `exit_on`. For `InBlock` and `Finalized`,
+/// the dispatch result of the extrinsic is checked and failed extrinsics are returned as error. For
+/// `Ready` and `Broadcast`, the extrinsic has not been dispatched yet, so it may still fail.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `metadata` - metadata of runtime, used to decode the events of the extrinsic
+/// * `xthex_prefixed` - hex encoded extrinsic
+/// * `exit_on` - status of extrinsic to wait for, e.g. `XtStatus::Finalized`
+///
+/// # Returns
+/// * `ExtrinsicStatus` - hash and reached status of extrinsic
+pub async fn send_extrinsic(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    xthex_prefixed: String,
+    exit_on: XtStatus,
+) -> Result<ExtrinsicStatus, Box<dyn Error>> {
+    match exit_on {
+        XtStatus::Finalized | XtStatus::InBlock | XtStatus::Broadcast | XtStatus::Ready => (),
+        _ => {
+            return Err(Box::from(format!(
+            "can only wait for finalized, in block, broadcast and ready extrinsic status, got {:?}",
+            exit_on
+        )))
+        }
+    };
+    // subscribe before submitting extrinsic, so the events of its block cannot be missed
+    let events_receiver = match exit_on {
+        XtStatus::InBlock => Some(subscribe_events(client)?),
+        _ => None,
+    };
+    let hash = get_extrinsic_hash(&xthex_prefixed)?;
+    let (status, block_hash) = watch_extrinsic(client, &xthex_prefixed, exit_on).await?;
+    if !is_in_block(exit_on) {
+        return Ok(ExtrinsicStatus {
+            hash,
+            status,
+            block_hash,
+        });
+    }
+    let block_hash = block_hash.ok_or("could not get block hash of extrinsic")?;
+    if exit_on == XtStatus::Finalized {
+        // events of finalized blocks may have been sent long before, so read them from storage
+        let events = get_extrinsic_events(client, metadata, &block_hash, &xthex_prefixed).await?;
+        for event in events {
+            if let Some(SystemEvent::ExtrinsicFailed(dispatch_error, _)) =
+                SystemEvent::from_raw(&event)?
+            {
+                return Err(Box::new(to_substrate_error(metadata, dispatch_error)?));
             }
-            Err(err) => {
-                return Err(Box::from(err));
+        }
+        return Ok(ExtrinsicStatus {
+            hash,
+            status,
+            block_hash: Some(block_hash),
+        });
+    }
+    let events_receiver = events_receiver.ok_or("could not subscribe to events")?;
+    let block = timeout(
+        client.timeouts.rpc,
+        "waiting for response for 'chain_getBlock'",
+        client
+            .rpc_client
+            .request("chain_getBlock", json!([block_hash])),
+    )
+    .await??;
+    trace!("block for extrinsic: {}", &block);
+    let extrinsics = block["block"]["extrinsics"]
+        .as_array()
+        .ok_or("could not parse block result")?
+        .iter()
+        .position(|ext| match ext.as_str() {
+            Some(value) => value == xthex_prefixed,
+            None => false,
+        })
+        .ok_or_else(|| {
+            let msg = format!("Failed to find Extrinsic with hash {:?}", xthex_prefixed);
+            info!("{}", &msg);
+            msg
+        })?;
+
+    let ext_status = wait_for_extrinsic_status(
+        metadata.clone(),
+        &block_hash,
+        extrinsics,
+        Some(client.events_decoder(metadata.clone())?),
+        events_receiver,
+        client.timeouts.events,
+    )
+    .await?;
+    match ext_status {
+        SystemEvent::ExtrinsicFailed(dispatch_error, _) => {
+            Err(Box::new(to_substrate_error(metadata, dispatch_error)?))
+        }
+        SystemEvent::ExtrinsicSuccess(_info) => Ok(ExtrinsicStatus {
+            hash,
+            status,
+            block_hash: Some(block_hash),
+        }),
+    }
+}
+
+/// Submits an extrinsic and watches its status until `exit_on` has been reached.
+///
+/// # Returns
+/// * `(XtStatus, Option<String>)` - reached status and hash of block for `InBlock` and `Finalized`
+async fn watch_extrinsic(
+    client: &SubstrateClient,
+    xthex_prefixed: &str,
+    exit_on: XtStatus,
+) -> Result<(XtStatus, Option<String>), Box<dyn Error>> {
+    let mut receiver = client.rpc_client.subscribe(
+        "author_submitAndWatchExtrinsic",
+        json!([xthex_prefixed]),
+        "author_unwatchExtrinsic",
+    )?;
+    let watch = async {
+        while let Some(msg) = receiver.next().await {
+            if let Some(result) = on_extrinsic_msg(&msg, exit_on) {
+                return Some(result);
             }
         }
+        None
+    };
+    let status_result = timeout(client.timeouts.events, "watching extrinsic", watch).await?;
+    // stop watching extrinsic
+    drop(receiver);
+    let (status, data) = status_result.ok_or_else(|| {
+        SubstrateError::Transport("connection closed while watching extrinsic".to_string())
+    })?;
+    match status_error(status, data.as_deref()) {
+        // e.g. dropped or usurped extrinsics will not reach `exit_on` anymore
+        Some(error) => Err(Box::new(SubstrateError::Dispatch(error))),
+        // only block hashes are kept, e.g. peers of `Broadcast` are no status result
+        None if is_in_block(status) => Ok((status, data)),
+        None => Ok((status, None)),
+    }
+}
+
+/// Gets the hash of a hex encoded extrinsic, as used by node to identify it.
+fn get_extrinsic_hash(xthex_prefixed: &str) -> Result<String, Box<dyn Error>> {
+    Ok(format!(
+        "0x{}",
+        hex::encode(blake2_256(&hexstr_to_vec(xthex_prefixed.to_string())?))
+    ))
+}
+
+/// Submits an extrinsic without waiting for its status, the status can be checked with
+/// [`get_transaction_status`](get_transaction_status).
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `xthex_prefixed` - hex encoded extrinsic
+///
+/// # Returns
+/// * `String` - hash of extrinsic
+pub fn submit_extrinsic(
+    client: &SubstrateClient,
+    xthex_prefixed: &str,
+) -> Result<String, Box<dyn Error>> {
+    let hash = get_extrinsic_hash(xthex_prefixed)?;
+    let receiver = client.rpc_client.subscribe(
+        "author_submitAndWatchExtrinsic",
+        json!([xthex_prefixed]),
+        "author_unwatchExtrinsic",
+    )?;
+    client
+        .transaction_tracker
+        .insert(&hash, xthex_prefixed, receiver)?;
+    Ok(hash)
+}
+
+/// Gets the status of an extrinsic submitted with [`submit_extrinsic`](submit_extrinsic). Once the
+/// extrinsic has been included in a block, its `SystemEvent` is checked, so extrinsics, that have
+/// failed to dispatch, are reported as `Failed`. A final status (`Finalized` or `Failed`) is only
+/// reported once, afterwards the extrinsic is not tracked anymore and its hash is unknown.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `hash` - hash of extrinsic as returned by `submit_extrinsic`
+pub async fn get_transaction_status(
+    client: &SubstrateClient,
+    hash: &str,
+) -> Result<TransactionStatus, Box<dyn Error>> {
+    let (status, xthex_prefixed, handled) = client.transaction_tracker.update(hash)?;
+    if handled {
+        return Ok(status);
+    }
+    let block_hash = match &status {
+        TransactionStatus::InBlock { block_hash } | TransactionStatus::Finalized { block_hash } => {
+            block_hash
+        }
+        TransactionStatus::Failed {
+            block_hash: None, ..
+        } => {
+            // extrinsic has been rejected, so its nonce can be used again
+            reset_account_nonce(client)?;
+            client.transaction_tracker.set_handled(hash)?;
+            return Ok(status);
+        }
+        _ => return Ok(status),
+    };
+    let metadata = client.get_metadata().await?;
+    let events = get_extrinsic_events(client, &metadata, block_hash, &xthex_prefixed).await?;
+    for event in events {
+        if let Some(SystemEvent::ExtrinsicFailed(dispatch_error, _)) =
+            SystemEvent::from_raw(&event)?
+        {
+            let error = to_substrate_error(&metadata, dispatch_error)?.to_string();
+            return client.transaction_tracker.fail(hash, block_hash, error);
+        }
+    }
+    client.transaction_tracker.set_handled(hash)?;
+    Ok(status)
+}
+
+/// Converts a `DispatchError` to a `SubstrateError`, module errors are resolved to their module,
+/// name and documentation with the metadata.
+fn to_substrate_error(
+    metadata: &Metadata,
+    dispatch_error: DispatchError,
+) -> Result<SubstrateError, Box<dyn Error>> {
+    Ok(match dispatch_error {
+        DispatchError::Module { index, error, .. } => {
+            let module = metadata.module_with_errors(index)?;
+            let error_metadata = module.error(error)?;
+            SubstrateError::DispatchModule {
+                module_index: index,
+                error_index: error,
+                module: module.name().to_string(),
+                name: error_metadata.name.to_string(),
+                documentation: error_metadata.documentation(),
+            }
+        }
+        dispatch_error => SubstrateError::DispatchSystem {
+            name: dispatch_error.name(),
+            documentation: dispatch_error.documentation().to_string(),
+        },
+    })
+}
+
+/// Gets the events an extrinsic has emitted.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `metadata` - metadata of runtime
+/// * `block_hash` - hash of block, that contains extrinsic
+/// * `xthex_prefixed` - hex encoded extrinsic
+pub async fn get_extrinsic_events(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    block_hash: &str,
+    xthex_prefixed: &str,
+) -> Result<Vec<RawEvent>, Box<dyn Error>> {
+    let block = timeout(
+        client.timeouts.rpc,
+        "waiting for response for 'chain_getBlock'",
+        client
+            .rpc_client
+            .request("chain_getBlock", json!([block_hash])),
+    )
+    .await??;
+    let index = block["block"]["extrinsics"]
+        .as_array()
+        .ok_or("could not parse block result")?
+        .iter()
+        .position(|xt| xt.as_str() == Some(xthex_prefixed))
+        .ok_or_else(|| format!("could not find extrinsic in block {}", block_hash))?;
+    Ok(get_block_events(client, metadata, block_hash)
+        .await?
+        .into_iter()
+        .filter(|record| matches!(record.phase, Phase::ApplyExtrinsic(i) if i as usize == index))
+        .map(|record| record.event)
+        .collect())
+}
+
+/// Gets all `DidModule` events of a block, ordered like they have been emitted.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `block_hash` - hash of block to get events of
+pub async fn get_did_module_events(
+    client: &SubstrateClient,
+    block_hash: &str,
+) -> Result<Vec<DidModuleEvent>, Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    let mut did_events = Vec::new();
+    for record in get_block_events(client, &metadata, block_hash).await? {
+        if let Some(did_event) =
+            DidModuleEvent::from_raw(&record.event).map_err(SubstrateError::Codec)?
+        {
+            did_events.push(did_event);
+        }
     }
-    Ok(None)
+    Ok(did_events)
 }
 
-pub async fn get_metadata(url: &str) -> Result<Metadata, Box<dyn Error>> {
-    let json = json!({
-        "method": "state_getMetadata",
-        "params": null,
-        "jsonrpc": "2.0",
-        "id": "1",
-    });
-    let client = reqwest::Client::new();
-    let body = client
-        .post(&format!("https://{}/rpc", url).to_string())
-        .header("Content-Type", "application/json")
-        .body(json.to_string())
-        .send()
-        .await?
-        .text()
-        .await?;
-    let parsed: Value = serde_json::from_str(&body)?;
-    let hex_value = parsed["result"]
-        .as_str()
-        .ok_or("could parse metadata result")?
-        .to_string();
-    let _unhex = hexstr_to_vec(hex_value)?;
-    let mut _om = _unhex.as_slice();
-    let meta = RuntimeMetadataPrefixed::decode(&mut _om)?;
-    let metadata2 = Metadata::parse(meta)?;
-    Ok(metadata2)
+/// Gets all events of a block with the phase they have been emitted in and their topics, ordered
+/// like they have been emitted.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `block_hash` - hash of block to get events of
+pub async fn get_events(
+    client: &SubstrateClient,
+    block_hash: &str,
+) -> Result<Vec<EventRecord>, Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    get_block_events(client, &metadata, block_hash).await
 }
 
-pub async fn send_extrinsic(
-    url: &str,
-    xthex_prefixed: String,
-    exit_on: XtStatus,
-) -> Result<Option<String>, Box<dyn Error>> {
-    let json = json!({
-        "method": "author_submitAndWatchExtrinsic",
-        "params": [xthex_prefixed],
-        "jsonrpc": "2.0",
-        "id": "4",
-    });
+/// Reads and decodes the `System::Events` storage of a block.
+async fn get_block_events(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    block_hash: &str,
+) -> Result<Vec<EventRecord>, Box<dyn Error>> {
+    let storagekey = metadata
+        .module("System")?
+        .storage("Events")?
+        .get_value()?
+        .key();
+    let events = get_storage_bytes(client, storagekey, Some(block_hash))
+        .await?
+        .ok_or_else(|| format!("could not get events of block {}", block_hash))?;
+    let decoder = client.events_decoder(metadata.clone())?;
+    Ok(decoder
+        .decode_events(&mut events.as_slice())
+        .map_err(|err| SubstrateError::Metadata(format!("could not decode events; {}", &err)))?)
+}
 
-    let (sender, mut receiver) = channel::<String>(100);
-    match exit_on {
-        XtStatus::Finalized => {
-            start_rpc_client_thread(
-                format!("wss://{}/ws", url),
-                json.to_string(),
-                sender,
-                on_extrinsic_msg_until_finalized,
-            );
-            if let Some(data) = receiver.next().await {
-                return Ok(Some(data));
-            }
-            Ok(Some("Nope".to_string()))
-        }
-        XtStatus::InBlock => {
-            let metadata = get_metadata(url).await?;
-            start_rpc_client_thread(
-                format!("wss://{}/ws", url),
-                json.to_string(),
-                sender,
-                on_extrinsic_msg_until_in_block,
-            );
-            let (sender_status, receiver_status) = channel::<String>(100);
-            subscribe_events(url, sender_status).await;
-            if let Some(data) = receiver.next().await {
-                let json = json_req("chain_getBlock", data.as_str(), 1);
-                let client = reqwest::Client::new();
-                let body = client
-                    .post(&format!("https://{}/rpc", url).to_string())
-                    .header("Content-Type", "application/json")
-                    .body(json.to_string())
-                    .send()
-                    .await?
-                    .text()
-                    .await?;
-
-                trace!("response for extrinsic: {}", &body);
-                let parsed: Value = serde_json::from_str(&body)?;
-                let extrinsics = &parsed["result"]["block"]["extrinsics"]
-                    .as_array()
-                    .ok_or("could not parse block result")?
-                    .iter()
-                    .position(|ext| match ext.as_str() {
-                        Some(value) => value == xthex_prefixed,
-                        None => false,
-                    })
-                    .ok_or_else(|| {
-                        let msg =
-                            format!("Failed to find Extrinsic with hash {:?}", xthex_prefixed);
-                        info!("{}", &msg);
-                        msg
-                    })?;
-
-                let ext_status = wait_for_extrinsic_status(
-                    metadata.clone(),
-                    &data,
-                    *extrinsics,
-                    None,
-                    receiver_status,
-                )
-                .await
-                .ok_or("could not get extrinsic status")?;
-                match ext_status {
-                    SystemEvent::ExtrinsicFailed(
-                        DispatchError::Module {
-                            index,
-                            error,
-                            message: _,
-                        },
-                        _,
-                    ) => {
-                        let clear_error = metadata.module_with_errors(index)?;
-                        return Err(Box::from(clear_error.event(error)?.name.to_string()));
-                    }
-                    SystemEvent::ExtrinsicFailed(_, _) => return Err(Box::from("other error")),
-                    SystemEvent::ExtrinsicSuccess(_info) => {
-                        return Ok(Some(data));
-                    }
-                }
-            }
-            Err(Box::from("other error"))
-        }
-        XtStatus::Broadcast => {
-            start_rpc_client_thread(
-                format!("wss://{}/ws", url),
-                json.to_string(),
-                sender,
-                on_extrinsic_msg_until_broadcast,
-            );
-            if let Some(data) = receiver.next().await {
-                return Ok(Some(data));
-            }
-            Ok(Some("Nope".to_string()))
-        }
-        XtStatus::Ready => {
-            start_rpc_client_thread(
-                format!("wss://{}/ws", url),
-                json.to_string(),
-                sender,
-                on_extrinsic_msg_until_ready,
-            );
-            if let Some(data) = receiver.next().await {
-                return Ok(Some(data));
-            }
-            Ok(Some("Nope".to_string()))
-        }
-        _ => panic!("can only wait for finalized, in block, broadcast and ready extrinsic status"),
-    }
+/// Subscribes to changes of `System::Events` storage.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+///
+/// # Returns
+/// * `UnboundedReceiver<String>` - receiver for subscription messages, subscription ends when
+///   dropped
+pub fn subscribe_events(
+    client: &SubstrateClient,
+) -> Result<UnboundedReceiver<String>, Box<dyn Error>> {
+    client.rpc_client.subscribe(
+        "state_subscribeStorage",
+        json!([[system_storage_key("Events")]]),
+        "state_unsubscribeStorage",
+    )
 }
 
-pub async fn subscribe_events(url: &str, sender: Sender<String>) {
+/// Gets the hex encoded key of storage value `name` of `System`, e.g. of `Events`.
+fn system_storage_key(name: &str) -> String {
     let mut bytes = twox_128(b"System").to_vec();
-    bytes.extend(&twox_128(b"Events")[..]);
-    let key = format!("0x{}", hex::encode(bytes));
-    let jsonreq = json!({
-        "method": "state_subscribeStorage",
-        "params": [[key]],
-        "jsonrpc": "2.0",
-        "id": "1",
-    });
-    start_rpc_client_thread(
-        format!("wss://{}/ws", url),
-        jsonreq.to_string(),
-        sender,
-        on_subscription_msg,
-    );
+    bytes.extend(&twox_128(name.as_bytes())[..]);
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Gets the hex encoded value of storage `key` in a storage notification.
+fn get_storage_change<'a>(result: &'a Value, key: &str) -> Option<&'a str> {
+    result["changes"]
+        .as_array()?
+        .iter()
+        .find(|change| change[0].as_str() == Some(key))?[1]
+        .as_str()
 }
 
 pub async fn wait_for_event<E: Decode>(
@@ -279,12 +1489,21 @@ pub async fn wait_for_event<E: Decode>(
     module: &str,
     variant: &str,
     decoder: Option<EventsDecoder>,
-    receiver: Receiver<String>,
+    receiver: UnboundedReceiver<String>,
     on_event_check: impl Fn(&RawEvent) -> bool,
-) -> Option<Result<E, CodecError>> {
-    wait_for_raw_event(metadata, module, variant, decoder, receiver, on_event_check)
-        .await
-        .map(|raw| E::decode(&mut &raw.data[..]))
+    timeout_duration: Duration,
+) -> Result<E, SubstrateError> {
+    let raw = wait_for_raw_event(
+        metadata,
+        module,
+        variant,
+        decoder,
+        receiver,
+        on_event_check,
+        timeout_duration,
+    )
+    .await?;
+    Ok(E::decode(&mut &raw.data[..])?)
 }
 
 pub async fn wait_for_raw_event(
@@ -292,79 +1511,57 @@ pub async fn wait_for_raw_event(
     module: &str,
     variant: &str,
     decoder: Option<EventsDecoder>,
-    mut receiver: Receiver<String>,
+    mut receiver: UnboundedReceiver<String>,
     on_event_check: impl Fn(&RawEvent) -> bool,
-) -> Option<RawEvent> {
+    timeout_duration: Duration,
+) -> Result<RawEvent, SubstrateError> {
     let event_decoder = match decoder {
         Some(decoder) => decoder,
-        None => match EventsDecoder::try_from(metadata.clone()) {
-            Ok(decoder) => decoder,
-            Err(err) => {
-                error!("could not get decoder; {}", &err);
-                return None;
-            }
-        },
+        None => EventsDecoder::try_from(metadata.clone())
+            .map_err(|err| SubstrateError::Metadata(format!("could not get decoder; {}", &err)))?,
     };
-    let start = Instant::now();
-    loop {
-        // check if timeout reached
-        let duration: Duration = start.elapsed();
-        if duration.as_secs() > SUBSTRATE_TIMEOUT {
-            error!(
-                "substrate timeout for module '{}', variant '{}', after; {}s",
-                &module, &variant, SUBSTRATE_TIMEOUT
-            );
-            return None;
-        }
-
-        if let Some(data) = receiver.next().await {
-            let value: Value = match serde_json::from_str(&data) {
-                Ok(result) => result,
-                Err(err) => {
-                    error!("could not parse received data; {}", &err);
-                    return None;
-                }
-            };
+    let wait = async {
+        loop {
+            let data = receiver.next().await.ok_or_else(|| {
+                SubstrateError::Transport("event subscription has been closed".to_string())
+            })?;
+            let value: Value = serde_json::from_str::<Value>(&data).map_err(|err| {
+                SubstrateError::Transport(format!("could not parse received data; {}", &err))
+            })?["params"]["result"]
+                .clone();
             let changes = &value["changes"];
             let event_str = match changes[0][1].as_str() {
-                Some(change_set) => Some(change_set),
+                Some(change_set) => change_set,
                 None => {
                     debug!("No events happened");
-                    None
-                }
-            };
-            let unhex = match hexstr_to_vec(event_str?.to_string()) {
-                Ok(result) => result,
-                Err(err) => {
-                    error!("could not parse hex string; {}", &err);
-                    return None;
+                    continue;
                 }
             };
+            let unhex = hexstr_to_vec(event_str.to_string()).map_err(|err| {
+                SubstrateError::Transport(format!("could not parse hex string; {}", &err))
+            })?;
             let mut er_enc = unhex.as_slice();
 
             let _events = event_decoder.decode_events(&mut er_enc);
             match _events {
                 Ok(raw_events) => {
-                    for (_phase, event) in raw_events.into_iter() {
-                        match event {
-                            RuntimeEvent::Raw(raw)
-                                if raw.module == module && raw.variant == variant =>
-                            {
-                                match on_event_check(&raw) {
-                                    true => return Some(raw),
-                                    _ => debug!("on_event_check not match for event: {:?}", raw),
-                                }
-                            }
-                            _ => {
-                                debug!("ignoring unsupported module event: {:?}", event);
+                    for EventRecord { event: raw, .. } in raw_events.into_iter() {
+                        if raw.module == module && raw.variant == variant {
+                            match on_event_check(&raw) {
+                                true => return Ok(raw),
+                                _ => debug!("on_event_check not match for event: {:?}", raw),
                             }
+                        } else {
+                            debug!("ignoring unsupported module event: {:?}", raw);
                         }
                     }
                 }
                 Err(_) => error!("couldn't decode event record list"),
             }
         }
-    }
+    };
+    let description = format!("waiting for event '{}' of module '{}'", &variant, &module);
+    timeout(timeout_duration, &description, wait).await?
 }
 
 pub async fn wait_for_extrinsic_status(
@@ -372,65 +1569,45 @@ pub async fn wait_for_extrinsic_status(
     block: &str,
     index: usize,
     decoder: Option<EventsDecoder>,
-    mut receiver: Receiver<String>,
-) -> Option<SystemEvent> {
+    mut receiver: UnboundedReceiver<String>,
+    timeout_duration: Duration,
+) -> Result<SystemEvent, SubstrateError> {
     let event_decoder = match decoder {
         Some(decoder) => decoder,
-        None => match EventsDecoder::try_from(metadata.clone()) {
-            Ok(decoder) => decoder,
-            Err(err) => {
-                error!("could not get decoder; {}", &err);
-                return None;
-            }
-        },
+        None => EventsDecoder::try_from(metadata.clone())
+            .map_err(|err| SubstrateError::Metadata(format!("could not get decoder; {}", &err)))?,
     };
-    let start = Instant::now();
-    loop {
-        // check if timeout reached
-        let duration: Duration = start.elapsed();
-        if duration.as_secs() > SUBSTRATE_TIMEOUT {
-            error!(
-                "substrate timeout while waiting for extrinsic status, after; {}s",
-                SUBSTRATE_TIMEOUT
-            );
-            return None;
-        }
-        if let Some(data) = receiver.next().await {
-            let value: Value = match serde_json::from_str(&data) {
-                Ok(result) => result,
-                Err(err) => {
-                    error!("json parsing error; {}", &err);
-                    return None;
-                }
-            };
+    let wait = async {
+        loop {
+            let data = receiver.next().await.ok_or_else(|| {
+                SubstrateError::Transport("event subscription has been closed".to_string())
+            })?;
+            let value: Value = serde_json::from_str::<Value>(&data).map_err(|err| {
+                SubstrateError::Transport(format!("json parsing error; {}", &err))
+            })?["params"]["result"]
+                .clone();
             let changes = &value["changes"];
             let event_str = match changes[0][1].as_str() {
-                Some(change_set) => Some(change_set),
+                Some(change_set) => change_set,
                 None => {
                     debug!("No events happened");
-                    None
-                }
-            };
-            let _unhex = match hexstr_to_vec(event_str?.to_string()) {
-                Ok(result) => result,
-                Err(err) => {
-                    error!("hexstr_to_vec error; {}", &err);
-                    return None;
+                    continue;
                 }
             };
+            let _unhex = hexstr_to_vec(event_str.to_string()).map_err(|err| {
+                SubstrateError::Transport(format!("hexstr_to_vec error; {}", &err))
+            })?;
             let mut _er_enc = _unhex.as_slice();
             let _events = event_decoder.decode_events(&mut _er_enc);
             match _events {
                 Ok(raw_events) => {
-                    for (phase, event) in raw_events.into_iter() {
+                    for EventRecord { phase, event, .. } in raw_events.into_iter() {
                         debug!("Decoded Event: {:?}, {:?}", phase, event);
                         if let Phase::ApplyExtrinsic(i) = phase {
-                            if i as usize == index && value["block"].as_str()? == block {
-                                match event {
-                                    RuntimeEvent::System(raw) => {
-                                        return Some(raw);
-                                    }
-                                    _ => {
+                            if i as usize == index && value["block"].as_str() == Some(block) {
+                                match SystemEvent::from_raw(&event)? {
+                                    Some(system_event) => return Ok(system_event),
+                                    None => {
                                         debug!("ignoring unsupported module event: {:?}", event);
                                     }
                                 }
@@ -441,94 +1618,342 @@ pub async fn wait_for_extrinsic_status(
                 Err(err) => error!("couldn't decode event record list; {}", &err),
             }
         }
+    };
+    timeout(timeout_duration, "waiting for extrinsic status", wait).await?
+}
+
+/// Change of a DID, that has been included in a block.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum DidChange {
+    /// DID has been created by `owner`
+    #[serde(rename_all = "camelCase")]
+    Created {
+        did: String,
+        owner: String,
+        block_hash: String,
+    },
+    /// Payload at `index` of DID has been added or updated
+    #[serde(rename_all = "camelCase")]
+    Updated {
+        did: String,
+        index: u32,
+        block_hash: String,
+    },
+}
+
+impl DidChange {
+    /// Gets the hex encoded DID, that has been changed.
+    pub fn did(&self) -> &str {
+        match self {
+            DidChange::Created { did, .. } | DidChange::Updated { did, .. } => did,
+        }
     }
 }
 
-#[derive(Decode)]
-struct IdentityWhitelist {
-    identity: Vec<u8>,
-    _account: Vec<u8>,
-    approved: bool,
-    nonce: u64,
+/// Watches DIDs for changes, that are included in new blocks. Events are decoded with the metadata
+/// of the runtime version of their block. Runtime upgrades are followed with
+/// `System::LastRuntimeUpgrade`, which is subscribed together with the events and changes in the
+/// first block of a new runtime version, metadata is fetched again then. The stream does not borrow
+/// `client`, it shares its connection and metadata cache instead.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `did` - hex encoded DID to watch, watches all DIDs if omitted
+///
+/// # Returns
+/// * `Stream` - changes in order of their blocks, ends if connection to node is closed, errors are
+///   returned for blocks, whose changes may be missing, e.g. as their events could not be decoded
+pub async fn watch_did(
+    client: &SubstrateClient,
+    did: Option<String>,
+) -> Result<impl Stream<Item = Result<DidChange, SubstrateError>>, Box<dyn Error>> {
+    let did = match did {
+        Some(did) => Some(format!("0x{}", hex::encode(get_did_bytes_array(&did)?))),
+        None => None,
+    };
+    let spec_version = client
+        .retry_policy
+        .retry("fetching runtime version", || get_runtime_version(client))
+        .await?
+        .spec_version;
+    let metadata = client.get_metadata().await?;
+    let receiver = client.rpc_client.subscribe(
+        "state_subscribeStorage",
+        json!([[
+            system_storage_key("Events"),
+            system_storage_key("LastRuntimeUpgrade")
+        ]]),
+        "state_unsubscribeStorage",
+    )?;
+    let watch = DidWatch {
+        receiver,
+        did,
+        start_block: None,
+        rpc_client: client.rpc_client.clone(),
+        metadata_cache: client.metadata_cache.clone(),
+        url: client.endpoints.http_rpc.clone(),
+        event_types: client.event_types.clone(),
+        timeouts: client.timeouts,
+        retry_policy: client.retry_policy,
+        spec_version,
+        decoder: client.events_decoder(metadata)?,
+    };
+    Ok(stream::unfold(watch, |mut watch| async move {
+        let data = watch.receiver.next().await?;
+        let changes = watch.on_notification(&data).await;
+        Some((stream::iter(changes), watch))
+    })
+    .flatten())
 }
 
-#[derive(Decode)]
-struct Created {
-    hash: Vec<u8>,
-    _owner: Vec<u8>,
-    nonce: u64,
+/// State of a [`watch_did`](watch_did) stream.
+struct DidWatch {
+    receiver: UnboundedReceiver<String>,
+    did: Option<String>,
+    /// block of the first notification, nodes send the events of their current block on
+    /// subscribing, these are not new changes
+    start_block: Option<String>,
+    rpc_client: RpcClient,
+    metadata_cache: MetadataCache,
+    /// endpoint `metadata_cache` entries are stored for
+    url: String,
+    event_types: EventTypes,
+    timeouts: Timeouts,
+    retry_policy: RetryPolicy,
+    /// runtime version `decoder` has been created for
+    spec_version: u32,
+    decoder: EventsDecoder,
+}
+
+impl DidWatch {
+    /// Decodes the changes of a `System::Events` storage notification.
+    async fn on_notification(&mut self, data: &str) -> Vec<Result<DidChange, SubstrateError>> {
+        let result = serde_json::from_str::<Value>(data)
+            .map(|mut value| value["params"]["result"].take())
+            .unwrap_or_default();
+        let block_hash = result["block"].as_str().unwrap_or_default().to_string();
+        match &self.start_block {
+            None => {
+                self.start_block = Some(block_hash);
+                return Vec::new();
+            }
+            Some(start_block) if *start_block == block_hash => return Vec::new(),
+            _ => (),
+        }
+        let upgrade = get_storage_change(&result, &system_storage_key("LastRuntimeUpgrade"));
+        if let Some(upgrade) = upgrade {
+            if let Err(err) = self.update_decoder(&block_hash, upgrade).await {
+                error!("could not update metadata for DID changes; {}", &err);
+                return vec![Err(into_substrate_error(err))];
+            }
+        }
+        decode_did_changes(&self.decoder, data, self.did.as_deref())
+    }
+
+    /// Creates a new decoder, if the runtime version of `System::LastRuntimeUpgrade` of block
+    /// `block_hash` differs from the one of the current decoder. Metadata is taken from the metadata
+    /// cache, if it has been fetched before.
+    ///
+    /// # Arguments
+    /// * `block_hash` - hash of block, that has changed `System::LastRuntimeUpgrade`
+    /// * `upgrade` - hex encoded `LastRuntimeUpgradeInfo`, starting with the compact spec version
+    async fn update_decoder(
+        &mut self,
+        block_hash: &str,
+        upgrade: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let upgrade = hexstr_to_vec(upgrade.to_string())?;
+        let spec_version = <Compact<u32>>::decode(&mut upgrade.as_slice())?.0;
+        if spec_version == self.spec_version {
+            return Ok(());
+        }
+        let metadata = match self.metadata_cache.get(&self.url, spec_version)? {
+            Some(metadata) => metadata,
+            None => {
+                debug!(
+                    "fetching metadata for spec version {} from {}",
+                    spec_version, &self.url
+                );
+                let metadata =
+                    parse_metadata(&self.request("state_getMetadata", block_hash).await?)?;
+                self.metadata_cache
+                    .insert(&self.url, spec_version, metadata.clone())?;
+                metadata
+            }
+        };
+        self.decoder = get_events_decoder(metadata, &self.event_types)?;
+        self.spec_version = spec_version;
+        Ok(())
+    }
+
+    /// Requests the state of block `block_hash` via the websocket connection, requests, that have
+    /// failed with transient errors, are retried.
+    async fn request(&self, method: &str, block_hash: &str) -> Result<Value, Box<dyn Error>> {
+        let description = format!("waiting for response for '{}'", method);
+        self.retry_policy
+            .retry(&description, || async {
+                timeout(
+                    self.timeouts.rpc,
+                    &description,
+                    self.rpc_client.request(method, json!([block_hash])),
+                )
+                .await?
+            })
+            .await
+    }
+}
+
+/// Decodes `DidModule` events of a `System::Events` storage notification into changes of `did`
+/// or of all DIDs if omitted. `DidModule` events, that cannot be decoded, are reported as errors at
+/// their position. Events after an event, whose size is unknown, cannot be located, so decoding
+/// stops there and an error is reported after the changes decoded so far, as changes of the block
+/// may be missing.
+fn decode_did_changes(
+    decoder: &EventsDecoder,
+    data: &str,
+    did: Option<&str>,
+) -> Vec<Result<DidChange, SubstrateError>> {
+    let decode = || -> Result<Vec<Result<DidChange, SubstrateError>>, SubstrateError> {
+        let value = serde_json::from_str::<Value>(data).map_err(|err| {
+            SubstrateError::Transport(format!("could not parse received data; {}", &err))
+        })?["params"]["result"]
+            .take();
+        let block_hash = value["block"].as_str().unwrap_or_default().to_string();
+        let events = match get_storage_change(&value, &system_storage_key("Events")) {
+            Some(events) => hexstr_to_vec(events.to_string()).map_err(|err| {
+                SubstrateError::Transport(format!("could not parse hex string; {}", &err))
+            })?,
+            None => return Ok(Vec::new()),
+        };
+        let mut changes = Vec::new();
+        let (events, error) = decoder.decode_events_until_error(&mut events.as_slice());
+        for EventRecord { event: raw, .. } in events {
+            let did_event = match DidModuleEvent::from_raw(&raw) {
+                Ok(did_event) => did_event,
+                Err(err) => {
+                    error!(
+                        "could not decode event '{}::{}' of block {}; {}",
+                        &raw.module, &raw.variant, &block_hash, &err
+                    );
+                    changes.push(Err(SubstrateError::Codec(err)));
+                    continue;
+                }
+            };
+            let change = match did_event {
+                Some(DidModuleEvent::Created(created)) => DidChange::Created {
+                    did: format!("0x{}", hex::encode(created.did)),
+                    owner: format!("0x{}", hex::encode(created.owner)),
+                    block_hash: block_hash.clone(),
+                },
+                Some(DidModuleEvent::UpdatedDid(updated)) => DidChange::Updated {
+                    did: format!("0x{}", hex::encode(updated.did)),
+                    index: updated.index,
+                    block_hash: block_hash.clone(),
+                },
+                _ => continue,
+            };
+            match did {
+                Some(did) if did != change.did() => {}
+                _ => changes.push(Ok(change)),
+            }
+        }
+        if let Some(err) = error {
+            error!(
+                "could not decode all events of block {}, DID changes may be missing; {}",
+                &block_hash, &err
+            );
+            changes.push(Err(SubstrateError::Metadata(format!(
+                "could not decode all events of block {}, DID changes may be missing; {}",
+                &block_hash, &err
+            ))));
+        }
+        Ok(changes)
+    };
+    match decode() {
+        Ok(changes) => changes,
+        Err(err) => {
+            error!("could not decode DID changes; {}", &err);
+            vec![Err(err)]
+        }
+    }
 }
 
 #[derive(Decode)]
-struct UpdatedDid {
-    hash: Vec<u8>,
-    _index: u32,
-    nonce: u64,
+struct BatchInterrupted {
+    index: u32,
+    error: DispatchError,
+}
+
+/// `DidModule` call, that can be sent together with other calls with [`send_batch`](send_batch).
+#[derive(Clone, Debug)]
+pub enum DidCall {
+    /// Whitelists `identity`
+    WhitelistIdentity { method: u8, identity: Vec<u8> },
+    /// Creates a DID for `identity`, with `payload` as its first payload
+    CreateDid {
+        identity: Vec<u8>,
+        payload: Option<String>,
+    },
+    /// Adds `payload` to `did`
+    AddPayload {
+        did: String,
+        identity: Vec<u8>,
+        payload: String,
+    },
+}
+
+impl DidCall {
+    fn identity(&self) -> &[u8] {
+        match self {
+            DidCall::WhitelistIdentity { identity, .. }
+            | DidCall::CreateDid { identity, .. }
+            | DidCall::AddPayload { identity, .. } => identity,
+        }
+    }
 }
 
 /// Anchors a new DID on the chain.
 ///
 /// # Arguments
-/// * `url` - Substrate URL
+/// * `client` - connection to substrate node
 /// * `private_key` - Private key used to sign a message
 /// * `signer` - `Signer` to sign with
 /// * `identity` - Identity requesting the DID
 /// * `payload` - optional payload to set as DID document
+/// * `exit_on` - status of extrinsic to wait for, e.g. `XtStatus::Finalized`
 ///
 /// # Returns
-/// * `String` - The anchored DID
+/// * `Option<String>` - The anchored DID, `None` for `exit_on` `Ready` and `Broadcast`, as the DID
+///   is only known after the extrinsic has been included in a block
 pub async fn create_did(
-    url: String,
+    client: &SubstrateClient,
     private_key: String,
     signer: &Box<dyn Signer>,
     identity: Vec<u8>,
     payload: Option<&str>,
-) -> Result<String, Box<dyn Error>> {
-    let metadata = get_metadata(url.as_str()).await?;
-    let nonce: u64 = get_nonce();
-    let (signature, signed_message) = signer
-        .sign_message(&nonce.to_string(), &private_key.to_string())
-        .await?;
-    let (sender, receiver) = channel::<String>(100);
-    subscribe_events(url.as_str(), sender).await;
-    let xt: String = match payload {
-        Some(payload) => {
-            let payload_hex = hex::decode(hex::encode(payload))?;
-            compose_extrinsic!(
-                metadata.clone(),
-                "DidModule",
-                "create_did_with_detail",
-                payload_hex,
-                signature.to_vec(),
-                signed_message.to_vec(),
-                identity.to_vec(),
-                nonce
-            )
-            .hex_encode()
-        }
-        None => compose_extrinsic!(
-            metadata.clone(),
-            "DidModule",
-            "create_did",
-            signature.to_vec(),
-            signed_message.to_vec(),
-            identity.to_vec(),
-            nonce
-        )
-        .hex_encode(),
+    exit_on: XtStatus,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    let receiver = match is_in_block(exit_on) {
+        true => Some(subscribe_events(client)?),
+        false => None,
     };
-    let ext_error = send_extrinsic(url.as_str(), xt, XtStatus::InBlock)
-        .await
-        .map_err(|_e| {
-            format!(
-                "Error creating DID with identity: {:?} and error; {}",
-                hex::encode(identity.clone()),
-                _e
-            )
-        });
-    if let Err(e) = ext_error {
-        return Err(Box::from(e));
+    let (nonce, xt) =
+        compose_did_creation(client, &metadata, &private_key, signer, &identity, payload).await?;
+    if let Err(err) = send_extrinsic(client, &metadata, xt, exit_on).await {
+        error!(
+            "Error creating DID with identity: {:?} and error; {}",
+            hex::encode(identity.clone()),
+            &err
+        );
+        reset_account_nonce(client)?;
+        return Err(err);
     }
+    let receiver = match receiver {
+        Some(receiver) => receiver,
+        None => return Ok(None),
+    };
     let event_watch = move |raw: &RawEvent| -> bool {
         let decoded_event: Created = match Decode::decode(&mut &raw.data[..]) {
             Ok(result) => result,
@@ -540,101 +1965,548 @@ pub async fn create_did(
         if nonce == decoded_event.nonce {
             return true;
         }
-        false
+        false
+    };
+    let event_wait: Created = wait_for_event(
+        metadata.clone(),
+        "DidModule",
+        "Created",
+        Some(client.events_decoder(metadata.clone())?),
+        receiver,
+        event_watch,
+        client.timeouts.events,
+    )
+    .await?;
+    Ok(Some(format!("0x{}", hex::encode(event_wait.did))))
+}
+
+/// Anchors multiple new DIDs on the chain. Up to `concurrency` DIDs are created at the same time,
+/// all creations share one event subscription and are matched to their `Created` event by nonce.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `private_key` - Private key used to sign a message
+/// * `signer` - `Signer` to sign with
+/// * `identity` - Identity requesting the DIDs
+/// * `payloads` - optional payload to set as DID document, one entry per DID to create
+/// * `concurrency` - maximum number of DID creations, that are submitted at the same time
+///
+/// # Returns
+/// * `Vec<Result<String, SubstrateError>>` - anchored DID or error, in order of `payloads`
+pub async fn create_dids(
+    client: &SubstrateClient,
+    private_key: String,
+    signer: &Box<dyn Signer>,
+    identity: Vec<u8>,
+    payloads: Vec<Option<String>>,
+    concurrency: usize,
+) -> Result<Vec<Result<String, SubstrateError>>, Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    let decoder = client.events_decoder(metadata.clone())?;
+    let mut events_receiver = subscribe_events(client)?.fuse();
+    let mut submissions = stream::iter(payloads.iter().enumerate())
+        .map(|(index, payload)| {
+            submit_did_creation(
+                client,
+                &metadata,
+                &private_key,
+                signer,
+                &identity,
+                payload.as_deref(),
+            )
+            .map(move |result| (index, result))
+        })
+        .buffer_unordered(concurrency.max(1))
+        .fuse();
+
+    let mut results: Vec<Option<Result<String, SubstrateError>>> =
+        payloads.iter().map(|_| None).collect();
+    // submitted creations as (index, nonce, extrinsic, block hash), waiting for events of block
+    let mut pending: Vec<(usize, u64, String, String)> = Vec::new();
+    // events of latest blocks, that have been received from subscription, oldest first
+    let mut blocks: VecDeque<(String, Vec<EventRecord>)> = VecDeque::new();
+    // blocks, whose events have been dropped after resolving creations included in them
+    let mut resolved_blocks: VecDeque<String> = VecDeque::new();
+    // creations, whose block events have not been received within the events timeout
+    let mut deadlines = FuturesUnordered::new();
+    let mut expired = HashSet::new();
+    while results.iter().any(Option::is_none) {
+        futures::select! {
+            submission = submissions.next() => match submission {
+                Some((index, Ok((nonce, xt, block_hash)))) => {
+                    deadlines.push(Delay::new(client.timeouts.events).map(move |_| index));
+                    pending.push((index, nonce, xt, block_hash));
+                }
+                Some((index, Err(err))) => {
+                    error!(
+                        "Error creating DID with identity: {:?} and error; {}",
+                        hex::encode(&identity),
+                        &err
+                    );
+                    results[index] = Some(Err(into_substrate_error(err)));
+                }
+                None => (),
+            },
+            data = events_receiver.next() => {
+                let data = data.ok_or_else(|| {
+                    SubstrateError::Transport("event subscription has been closed".to_string())
+                })?;
+                let value: Value = serde_json::from_str::<Value>(&data).map_err(|err| {
+                    SubstrateError::Transport(format!("could not parse received data; {}", &err))
+                })?["params"]["result"]
+                    .clone();
+                let (block_hash, event_str) =
+                    match (value["block"].as_str(), value["changes"][0][1].as_str()) {
+                        (Some(block_hash), Some(event_str)) => (block_hash, event_str),
+                        _ => {
+                            debug!("No events happened");
+                            continue;
+                        }
+                    };
+                let events = hexstr_to_vec(event_str.to_string()).map_err(|err| {
+                    SubstrateError::Transport(format!("could not parse hex string; {}", &err))
+                })?;
+                match decoder.decode_events(&mut events.as_slice()) {
+                    Ok(events) => {
+                        blocks.push_back((block_hash.to_string(), events));
+                        if blocks.len() > BUFFERED_BLOCKS {
+                            blocks.pop_front();
+                        }
+                    }
+                    Err(err) => error!("couldn't decode event record list; {}", &err),
+                }
+            },
+            index = deadlines.select_next_some() => {
+                expired.insert(index);
+            },
+        };
+
+        // resolve creations, whose block events have been received or have to be fetched
+        let mut index = 0;
+        while index < pending.len() {
+            let block_hash = &pending[index].3;
+            let position = blocks.iter().position(|(hash, _)| hash == block_hash);
+            let fetch = position.is_none()
+                && (expired.contains(&pending[index].0) || resolved_blocks.contains(block_hash));
+            if position.is_none() && !fetch {
+                index += 1;
+                continue;
+            }
+            let (result_index, nonce, xt, block_hash) = pending.remove(index);
+            if fetch {
+                debug!(
+                    "fetching events of block {}, that have not been received",
+                    &block_hash
+                );
+            }
+            let events = position.map(|position| &blocks[position].1[..]);
+            results[result_index] =
+                Some(get_created_did(client, &metadata, &block_hash, &xt, nonce, events).await);
+            // events of other creations in this block are fetched, if they are reported later
+            if let Some(position) = position {
+                if !pending.iter().any(|(_, _, _, hash)| *hash == block_hash) {
+                    blocks.remove(position);
+                    resolved_blocks.push_back(block_hash);
+                    if resolved_blocks.len() > BUFFERED_BLOCKS {
+                        resolved_blocks.pop_front();
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Gets the DID anchored by a creation extrinsic from the events of its block, fetches the events
+/// of the extrinsic from node if `block_events` are omitted or do not contain its `Created` event.
+async fn get_created_did(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    block_hash: &str,
+    xt: &str,
+    nonce: u64,
+    block_events: Option<&[EventRecord]>,
+) -> Result<String, SubstrateError> {
+    fn find_created<'a>(events: impl Iterator<Item = &'a RawEvent>, nonce: u64) -> Option<Created> {
+        events
+            .filter(|raw| raw.module == DID_MODULE && raw.variant == "Created")
+            .find_map(|raw| match Created::decode(&mut &raw.data[..]) {
+                Ok(created) if created.nonce == nonce => Some(created),
+                _ => None,
+            })
+    }
+    let mut created = block_events
+        .and_then(|events| find_created(events.iter().map(|record| &record.event), nonce));
+    let mut failure = None;
+    if created.is_none() {
+        let events = get_extrinsic_events(client, metadata, block_hash, xt)
+            .await
+            .map_err(into_substrate_error)?;
+        created = find_created(events.iter(), nonce);
+        for event in events.iter() {
+            if let Some(SystemEvent::ExtrinsicFailed(dispatch_error, _)) =
+                SystemEvent::from_raw(event).map_err(SubstrateError::Codec)?
+            {
+                failure = Some(dispatch_error);
+                break;
+            }
+        }
+    }
+    match (created, failure) {
+        (Some(created), _) => Ok(format!("0x{}", hex::encode(&created.did))),
+        (None, Some(dispatch_error)) => match to_substrate_error(metadata, dispatch_error) {
+            Ok(err) => Err(err),
+            Err(err) => Err(into_substrate_error(err)),
+        },
+        (None, None) => Err(SubstrateError::Dispatch(
+            "extrinsic did not emit a 'Created' event".to_string(),
+        )),
+    }
+}
+
+/// Signs the nonce of a DID creation and composes its extrinsic.
+///
+/// # Returns
+/// * `(u64, String)` - nonce of DID creation and hex encoded extrinsic
+pub async fn compose_did_creation(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    private_key: &str,
+    signer: &Box<dyn Signer>,
+    identity: &[u8],
+    payload: Option<&str>,
+) -> Result<(u64, String), Box<dyn Error>> {
+    let nonce: u64 = get_nonce(client, metadata, identity).await?;
+    let (signature, signed_message) = signer
+        .sign_message(&nonce.to_string(), private_key)
+        .await
+        .map_err(|err| SubstrateError::Signer(err.to_string()))?;
+    let xt: String = match payload {
+        Some(payload) => {
+            let payload_hex = hex::decode(hex::encode(payload))?;
+            let xt = compose_extrinsic!(
+                metadata,
+                "DidModule",
+                "create_did_with_detail",
+                payload_hex,
+                signature.to_vec(),
+                signed_message.to_vec(),
+                identity.to_vec(),
+                nonce
+            );
+            sign_extrinsic(client, metadata, xt).await?.hex_encode()
+        }
+        None => {
+            let xt = compose_extrinsic!(
+                metadata,
+                "DidModule",
+                "create_did",
+                signature.to_vec(),
+                signed_message.to_vec(),
+                identity.to_vec(),
+                nonce
+            );
+            sign_extrinsic(client, metadata, xt).await?.hex_encode()
+        }
     };
-    let event_wait: Created = wait_for_event(
-        metadata.clone(),
-        "DidModule",
-        "Created",
-        None,
-        receiver,
-        event_watch,
-    )
-    .await
-    .ok_or("could not create did")??;
-    Ok(format!("0x{}", hex::encode(event_wait.hash)))
+    Ok((nonce, xt))
+}
+
+/// Submits a DID creation and waits until it has been included in a block.
+///
+/// # Returns
+/// * `(u64, String, String)` - nonce of DID creation, hex encoded extrinsic and hash of block
+async fn submit_did_creation(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    private_key: &str,
+    signer: &Box<dyn Signer>,
+    identity: &[u8],
+    payload: Option<&str>,
+) -> Result<(u64, String, String), Box<dyn Error>> {
+    let (nonce, xt) =
+        compose_did_creation(client, metadata, private_key, signer, identity, payload).await?;
+    match watch_extrinsic(client, &xt, XtStatus::InBlock).await {
+        Ok((_, Some(block_hash))) => Ok((nonce, xt, block_hash)),
+        Ok((_, None)) => Err(Box::from("could not get block hash of extrinsic")),
+        Err(err) => {
+            reset_account_nonce(client)?;
+            Err(err)
+        }
+    }
 }
 
-/// Retrieve the content saved at a DID reference.
+/// Keeps `SubstrateError`s, e.g. of failed DID creations, other errors are reported as transport
+/// errors.
+fn into_substrate_error(err: Box<dyn Error>) -> SubstrateError {
+    match err.downcast::<SubstrateError>() {
+        Ok(err) => *err,
+        Err(err) => SubstrateError::Transport(err.to_string()),
+    }
+}
+
+/// Retrieve the content saved at a DID reference, which is the payload with the highest index.
 ///
 /// # Arguments
-/// * `url` - Substrate URL
+/// * `client` - connection to substrate node
 /// * `did` - DID to resolve
+/// * `at` - hash of block to resolve DID at, resolves at latest block if omitted
 ///
 /// # Returns
 /// * `String` - Content saved behind the DID
-pub async fn get_did(url: String, did: String) -> Result<String, Box<dyn Error>> {
-    let bytes_did_arr = get_did_bytes_array(&did)?;
+pub async fn get_did(
+    client: &SubstrateClient,
+    did: String,
+    at: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    match read_payload_count(client, &metadata, &did, at).await? {
+        0 => Err(Box::new(SubstrateError::DidNotFound(did))),
+        payload_count => read_payload(client, &metadata, &did, payload_count - 1, at).await,
+    }
+}
+
+/// Retrieve the payload saved at an index of a DID.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `did` - DID to resolve
+/// * `index` - index of payload to retrieve
+/// * `at` - hash of block to resolve DID at, resolves at latest block if omitted
+///
+/// # Returns
+/// * `String` - Payload saved at given index
+pub async fn get_payload_of_did(
+    client: &SubstrateClient,
+    did: String,
+    index: u32,
+    at: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    read_payload(client, &metadata, &did, index, at).await
+}
+
+/// Reads the payload at an index of a DID with the metadata of the caller.
+async fn read_payload(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    did: &str,
+    index: u32,
+    at: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    let bytes_did_arr = get_did_bytes_array(&did.to_string())?;
     let bytes_did = sp_core::H256::from(bytes_did_arr);
-    let metadata = get_metadata(url.as_str()).await?;
     let detail_hash = get_storage_map::<(sp_core::H256, u32), Vec<u8>>(
-        url.as_str(),
-        metadata.clone(),
+        client,
+        metadata,
         "DidModule",
         "DidsDetails",
-        (bytes_did, 0),
+        (bytes_did, index),
+        at,
     )
     .await?
-    .ok_or("DID not found")?;
-    let did_url = format!(
-        "https://{}/ipfs/{}",
-        url,
-        std::str::from_utf8(&detail_hash)?
+    .ok_or_else(|| match index {
+        0 => SubstrateError::DidNotFound(did.to_string()),
+        _ => SubstrateError::DidNotFound(format!("{} with payload index {}", did, index)),
+    })?;
+    let cid = std::str::from_utf8(&detail_hash)?;
+    trace!("fetching payload {} with cid: {}", index, cid);
+    let content = timeout(
+        client.timeouts.ipfs,
+        "fetching content",
+        client.content_store.get(cid),
     )
-    .to_string();
-    trace!("fetching DID document at: {}", &did_url);
-    let body = reqwest::get(&did_url).await?.text().await?;
-    Ok(body)
+    .await??;
+    verify_cid(cid, &content)?;
+    Ok(String::from_utf8(content)?)
+}
+
+/// Retrieve all payloads saved at a DID, ordered by their index.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `did` - DID to resolve
+/// * `at` - hash of block to resolve DID at, resolves at latest block if omitted
+///
+/// # Returns
+/// * `Vec<String>` - Payloads saved behind the DID
+pub async fn get_payloads_of_did(
+    client: &SubstrateClient,
+    did: String,
+    at: Option<&str>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    let payload_count = read_payload_count(client, &metadata, &did, at).await?;
+    let mut payloads = Vec::with_capacity(payload_count as usize);
+    for index in 0..payload_count {
+        payloads.push(read_payload(client, &metadata, &did, index, at).await?);
+    }
+    Ok(payloads)
+}
+
+/// Finds the blocks, in which a DID has been created and in which a payload of it has been set to
+/// its current value. Blocks are searched with a binary search over the blocks up to `at`, which
+/// requires a node, that keeps the state of past blocks. Payloads, that have been set to a previous
+/// value again, may be reported with the block of an earlier change.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `did` - DID to find blocks for
+/// * `index` - index of payload
+/// * `at` - hash of block to use as current block, uses latest block if omitted
+///
+/// # Returns
+/// * `(Option<u64>, Option<u64>)` - numbers of blocks, in which DID has been created, i.e. its first
+///   payload has been added, and in which payload has been set
+pub async fn get_payload_blocks(
+    client: &SubstrateClient,
+    did: String,
+    index: u32,
+    at: Option<&str>,
+) -> Result<(Option<u64>, Option<u64>), Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    let bytes_did = sp_core::H256::from(get_did_bytes_array(&did)?);
+    let head = match at {
+        Some(block_hash) => block_hash.to_string(),
+        None => get_block_hash(client, None)
+            .await?
+            .ok_or("could not get latest block")?,
+    };
+    let metadata = &metadata;
+    let detail_at = |block_hash: String| async move {
+        get_storage_map::<(H256, u32), Vec<u8>>(
+            client,
+            metadata,
+            DID_MODULE,
+            "DidsDetails",
+            (bytes_did, index),
+            Some(block_hash.as_str()),
+        )
+        .await
+    };
+    let current_detail = match detail_at(head.clone()).await? {
+        Some(detail) => detail,
+        None => return Err(Box::new(SubstrateError::DidNotFound(did))),
+    };
+    let head_number = get_block_number(client, &head).await?;
+
+    let created = find_first_block(client, head_number, |block_hash| async move {
+        let count: Option<u32> = get_storage_map(
+            client,
+            metadata,
+            DID_MODULE,
+            "DidsDetailsCount",
+            bytes_did,
+            Some(block_hash.as_str()),
+        )
+        .await?;
+        Ok::<_, Box<dyn Error>>(count.unwrap_or(0) > 0)
+    })
+    .await?;
+    let current_detail = &current_detail;
+    let updated = find_first_block(client, head_number, |block_hash| {
+        let detail = detail_at(block_hash);
+        async move { Ok::<_, Box<dyn Error>>(detail.await?.as_ref() == Some(current_detail)) }
+    })
+    .await?;
+    Ok((created, updated))
+}
+
+/// Finds the first block up to block `last`, at which `holds_at` returns true, with a binary
+/// search. `holds_at` has to return true for all blocks after this block as well.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `last` - number of last block to check
+/// * `holds_at` - checks state at a block, gets hash of block
+///
+/// # Returns
+/// * `Option<u64>` - number of block, `None` if `holds_at` is false for block `last`
+async fn find_first_block<F, R>(
+    client: &SubstrateClient,
+    last: u64,
+    holds_at: F,
+) -> Result<Option<u64>, Box<dyn Error>>
+where
+    F: Fn(String) -> R,
+    R: Future<Output = Result<bool, Box<dyn Error>>>,
+{
+    let block_hash = |number: u64| async move {
+        let hash = get_block_hash(client, Some(number))
+            .await?
+            .ok_or_else(|| SubstrateError::BlockNotFound(number.to_string()))?;
+        Ok::<_, Box<dyn Error>>(hash)
+    };
+    if !holds_at(block_hash(last).await?).await? {
+        return Ok(None);
+    }
+    let (mut low, mut high) = (0, last);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if holds_at(block_hash(middle).await?).await? {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Ok(Some(high))
 }
 
 /// Add a new payload under a DID
 ///
 /// # Arguments
-/// * `url` - Substrate URL
+/// * `client` - connection to substrate node
 /// * `payload` - Payload to save
 /// * `did` - DID to save payload under
 /// * `private_key` - key reference to sign with
 /// * `signer` - `Signer` to sign with
 /// * `identity` - Identity of the caller
+/// * `exit_on` - status of extrinsic to wait for, e.g. `XtStatus::Finalized`, DidModule events are
+///   only checked from `InBlock` on, so the extrinsic may still fail for `Ready` and `Broadcast`
 pub async fn add_payload_to_did(
-    url: String,
+    client: &SubstrateClient,
     payload: String,
     did: String,
     private_key: String,
     signer: &Box<dyn Signer>,
     identity: Vec<u8>,
+    exit_on: XtStatus,
 ) -> Result<(), Box<dyn Error>> {
-    let metadata = get_metadata(url.as_str()).await?;
+    let metadata = client.get_metadata().await?;
     let did = did.trim_start_matches("0x").to_string();
     let bytes_did_arr = get_did_bytes_array(&did)?;
     let bytes_did = sp_core::H256::from(bytes_did_arr);
     let bytes_did_string = hex::encode(&bytes_did);
-    let nonce: u64 = get_nonce();
-    let (signature, signed_message) = signer
-        .sign_message(&nonce.to_string(), &private_key.to_string())
-        .await?;
-    let payload_hex = hex::decode(hex::encode(payload.clone()))?;
-
-    let (sender, receiver) = channel::<String>(100);
-    subscribe_events(url.as_str(), sender).await;
-
-    let xt: xt_primitives::UncheckedExtrinsicV4<_> = compose_extrinsic!(
-        metadata.clone(),
-        "DidModule",
-        "add_did_detail",
-        bytes_did.clone().to_fixed_bytes(),
-        payload_hex,
-        signature.to_vec(),
-        signed_message.to_vec(),
-        identity.to_vec(),
-        nonce
-    );
-    let ext_error = send_extrinsic(url.as_str(), xt.hex_encode(), XtStatus::InBlock )
-        .await
-        .map_err(|_e| format!("Error adding payload to DID: {:?} with payload: {:?} and identity: {:?} and error; {}", did.clone(), payload.clone(), hex::encode(identity.clone()), _e));
-    if let Err(e) = ext_error {
-        return Err(Box::from(e));
+    let receiver = match is_in_block(exit_on) {
+        true => Some(subscribe_events(client)?),
+        false => None,
+    };
+    let (nonce, xt) = compose_add_payload(
+        client,
+        &metadata,
+        &payload,
+        &did,
+        &private_key,
+        signer,
+        &identity,
+    )
+    .await?;
+    if let Err(err) = send_extrinsic(client, &metadata, xt, exit_on).await {
+        error!(
+            "Error adding payload to DID: {:?} with payload: {:?} and identity: {:?} and error; {}",
+            &did,
+            &payload,
+            hex::encode(identity.clone()),
+            &err
+        );
+        reset_account_nonce(client)?;
+        return Err(err);
     }
+    let receiver = match receiver {
+        Some(receiver) => receiver,
+        None => return Ok(()),
+    };
 
     fn event_watch(substrate_did: &String, nonce: u64) -> impl Fn(&RawEvent) -> bool + '_ {
         move |raw: &RawEvent| -> bool {
@@ -645,7 +2517,7 @@ pub async fn add_payload_to_did(
                     return false;
                 }
             };
-            if decoded_event.nonce == nonce && &hex::encode(decoded_event.hash) == substrate_did {
+            if decoded_event.nonce == nonce && &hex::encode(decoded_event.did) == substrate_did {
                 return true;
             }
             false
@@ -655,64 +2527,108 @@ pub async fn add_payload_to_did(
         metadata.clone(),
         "DidModule",
         "UpdatedDid",
-        None,
+        Some(client.events_decoder(metadata.clone())?),
         receiver,
         event_watch(&bytes_did_string, nonce),
+        client.timeouts.events,
     )
-    .await
-    .ok_or("could not get event for updated did")??;
+    .await?;
     Ok(())
 }
 
+/// Signs the nonce of a payload addition and composes its extrinsic.
+///
+/// # Returns
+/// * `(u64, String)` - nonce of call and hex encoded extrinsic
+pub async fn compose_add_payload(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    payload: &str,
+    did: &str,
+    private_key: &str,
+    signer: &Box<dyn Signer>,
+    identity: &[u8],
+) -> Result<(u64, String), Box<dyn Error>> {
+    let bytes_did = sp_core::H256::from(get_did_bytes_array(&did.to_string())?);
+    let nonce: u64 = get_nonce(client, metadata, identity).await?;
+    let (signature, signed_message) = signer
+        .sign_message(&nonce.to_string(), private_key)
+        .await
+        .map_err(|err| SubstrateError::Signer(err.to_string()))?;
+    let payload_hex = hex::decode(hex::encode(payload))?;
+    let xt: UncheckedExtrinsicV4<_> = compose_extrinsic!(
+        metadata,
+        "DidModule",
+        "add_did_detail",
+        bytes_did.to_fixed_bytes(),
+        payload_hex,
+        signature.to_vec(),
+        signed_message.to_vec(),
+        identity.to_vec(),
+        nonce
+    );
+    Ok((
+        nonce,
+        sign_extrinsic(client, metadata, xt).await?.hex_encode(),
+    ))
+}
+
 /// Updates the object at the index in the payload array at this DID
 ///
 /// # Arguments
-/// * `url` - Substrate URL
+/// * `client` - connection to substrate node
 /// * `index` - Index of the payload to update
 /// * `payload` - Payload to save
 /// * `did` - DID to save payload under
 /// * `private_key` - Private key used to sign a message
 /// * `signer` - `Signer` to sign with
 /// * `identity` - Identity of the caller
+/// * `exit_on` - status of extrinsic to wait for, e.g. `XtStatus::Finalized`, DidModule events are
+///   only checked from `InBlock` on, so the extrinsic may still fail for `Ready` and `Broadcast`
+#[allow(clippy::too_many_arguments)]
 pub async fn update_payload_in_did(
-    url: String,
+    client: &SubstrateClient,
     index: u32,
     payload: String,
     did: String,
     private_key: String,
     signer: &Box<dyn Signer>,
     identity: Vec<u8>,
+    exit_on: XtStatus,
 ) -> Result<(), Box<dyn Error>> {
-    let metadata = get_metadata(url.as_str()).await?;
+    let metadata = client.get_metadata().await?;
     let bytes_did_arr = get_did_bytes_array(&did)?;
-    let bytes_did = sp_core::H256::from(bytes_did_arr);
-    let nonce: u64 = get_nonce();
-    let (signature, signed_message) = signer
-        .sign_message(&nonce.to_string(), &private_key.to_string())
-        .await?;
-    let payload_hex = hex::decode(hex::encode(payload.clone()))?;
-
-    let (sender, receiver) = channel::<String>(100);
-    subscribe_events(url.as_str(), sender).await;
-
-    let xt: xt_primitives::UncheckedExtrinsicV4<_> = compose_extrinsic!(
-        metadata.clone(),
-        "DidModule",
-        "update_did_detail",
-        bytes_did.clone().to_fixed_bytes(),
-        payload_hex,
+    let receiver = match is_in_block(exit_on) {
+        true => Some(subscribe_events(client)?),
+        false => None,
+    };
+    let (nonce, xt) = compose_update_payload(
+        client,
+        &metadata,
         index,
-        signature.to_vec(),
-        signed_message.to_vec(),
-        identity.clone(),
-        nonce
-    );
-    let ext_error = send_extrinsic(url.as_str(), xt.hex_encode(), XtStatus::InBlock)
-        .await
-        .map_err(|_e| format!("Error updating payload in DID: {:?} on index: {} with payload: {:?} and identity: {:?} and error; {}",did.clone(), index.clone(), payload.clone(), hex::encode(identity.clone()), _e));
-    if let Err(e) = ext_error {
-        return Err(Box::from(e));
+        &payload,
+        &did,
+        &private_key,
+        signer,
+        &identity,
+    )
+    .await?;
+    if let Err(err) = send_extrinsic(client, &metadata, xt, exit_on).await {
+        error!(
+            "Error updating payload in DID: {:?} on index: {} with payload: {:?} and identity: {:?} and error; {}",
+            &did,
+            index,
+            &payload,
+            hex::encode(identity.clone()),
+            &err
+        );
+        reset_account_nonce(client)?;
+        return Err(err);
     }
+    let receiver = match receiver {
+        Some(receiver) => receiver,
+        None => return Ok(()),
+    };
 
     fn event_watch(did: &String, nonce: u64) -> impl Fn(&RawEvent) -> bool + '_ {
         move |raw: &RawEvent| -> bool {
@@ -723,7 +2639,7 @@ pub async fn update_payload_in_did(
                     return false;
                 }
             };
-            if decoded_event.nonce == nonce && &hex::encode(decoded_event.hash) == did {
+            if decoded_event.nonce == nonce && &hex::encode(decoded_event.did) == did {
                 return true;
             }
             false
@@ -734,60 +2650,92 @@ pub async fn update_payload_in_did(
         metadata.clone(),
         "DidModule",
         "UpdatedDid",
-        None,
+        Some(client.events_decoder(metadata.clone())?),
         receiver,
         event_watch(&hex::encode(bytes_did_arr), nonce),
+        client.timeouts.events,
     )
-    .await
-    .ok_or("could not could not get updated did event")??;
+    .await?;
     Ok(())
 }
 
+/// Signs the nonce of a payload update and composes its extrinsic.
+///
+/// # Returns
+/// * `(u64, String)` - nonce of call and hex encoded extrinsic
+pub async fn compose_update_payload(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    index: u32,
+    payload: &str,
+    did: &str,
+    private_key: &str,
+    signer: &Box<dyn Signer>,
+    identity: &[u8],
+) -> Result<(u64, String), Box<dyn Error>> {
+    let bytes_did = sp_core::H256::from(get_did_bytes_array(&did.to_string())?);
+    let nonce: u64 = get_nonce(client, metadata, identity).await?;
+    let (signature, signed_message) = signer
+        .sign_message(&nonce.to_string(), private_key)
+        .await
+        .map_err(|err| SubstrateError::Signer(err.to_string()))?;
+    let payload_hex = hex::decode(hex::encode(payload))?;
+    let xt: UncheckedExtrinsicV4<_> = compose_extrinsic!(
+        metadata,
+        "DidModule",
+        "update_did_detail",
+        bytes_did.to_fixed_bytes(),
+        payload_hex,
+        index,
+        signature.to_vec(),
+        signed_message.to_vec(),
+        identity.to_vec(),
+        nonce
+    );
+    Ok((
+        nonce,
+        sign_extrinsic(client, metadata, xt).await?.hex_encode(),
+    ))
+}
+
 /// Whitelists an identity to send transactions to the substrate chain.
 ///
 /// # Arguments
-/// * `url` - Substrate URL
+/// * `client` - connection to substrate node
 /// * `private_key` - Private key used to sign a message
 /// * `signer` - `Signer` to sign with
 /// * `identity` - Identity of the caller
+/// * `exit_on` - status of extrinsic to wait for, e.g. `XtStatus::Finalized`, DidModule events are
+///   only checked from `InBlock` on, so the extrinsic may still fail for `Ready` and `Broadcast`
 pub async fn whitelist_identity(
-    url: String,
+    client: &SubstrateClient,
     private_key: String,
     signer: &Box<dyn Signer>,
     method: u8,
     identity: Vec<u8>,
+    exit_on: XtStatus,
 ) -> Result<(), Box<dyn Error>> {
-    let metadata = get_metadata(url.as_str()).await?;
-    let nonce: u64 = get_nonce();
-    let (signature, signed_message) = signer
-        .sign_message(&nonce.to_string(), &private_key.to_string())
-        .await?;
-
-    let (sender, receiver) = channel::<String>(100);
-    subscribe_events(url.as_str(), sender).await;
-
-    let xt: xt_primitives::UncheckedExtrinsicV4<_> = compose_extrinsic!(
-        metadata.clone(),
-        "DidModule",
-        "whitelist_identity",
-        method,
-        signature.to_vec(),
-        signed_message.to_vec(),
-        identity.clone(),
-        nonce
-    );
-    let ext_error = send_extrinsic(url.as_str(), xt.hex_encode(), XtStatus::InBlock)
-        .await
-        .map_err(|_e| {
-            format!(
-                "Error whitelisting identity: {:?} with error; {}",
-                hex::encode(identity.clone()),
-                _e
-            )
-        });
-    if let Err(e) = ext_error {
-        return Err(Box::from(e));
+    let metadata = client.get_metadata().await?;
+    let receiver = match is_in_block(exit_on) {
+        true => Some(subscribe_events(client)?),
+        false => None,
+    };
+    let (nonce, xt) =
+        compose_whitelist_identity(client, &metadata, &private_key, signer, method, &identity)
+            .await?;
+    if let Err(err) = send_extrinsic(client, &metadata, xt, exit_on).await {
+        error!(
+            "Error whitelisting identity: {:?} with error; {}",
+            hex::encode(identity.clone()),
+            &err
+        );
+        reset_account_nonce(client)?;
+        return Err(err);
     }
+    let receiver = match receiver {
+        Some(receiver) => receiver,
+        None => return Ok(()),
+    };
     fn event_watch(identity: &Vec<u8>, nonce: u64) -> impl Fn(&RawEvent) -> bool + '_ {
         move |raw: &RawEvent| -> bool {
             let decoded_event: IdentityWhitelist = match Decode::decode(&mut &raw.data[..]) {
@@ -807,37 +2755,255 @@ pub async fn whitelist_identity(
         metadata.clone(),
         "DidModule",
         "IdentityWhitelist",
-        None,
+        Some(client.events_decoder(metadata.clone())?),
         receiver,
         event_watch(&identity, nonce),
+        client.timeouts.events,
     )
-    .await
-    .ok_or("could not get whitelist identity event")??;
+    .await?;
     if event_result.approved {
         Ok(())
     } else {
-        Err(Box::from(format!(
-            "Error whitelisting identity: {:?}",
-            hex::encode(identity.clone())
+        Err(Box::new(SubstrateError::IdentityNotWhitelisted(
+            hex::encode(identity),
         )))
     }
 }
 
+/// Signs the nonce of an identity whitelisting and composes its extrinsic.
+///
+/// # Returns
+/// * `(u64, String)` - nonce of call and hex encoded extrinsic
+pub async fn compose_whitelist_identity(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    private_key: &str,
+    signer: &Box<dyn Signer>,
+    method: u8,
+    identity: &[u8],
+) -> Result<(u64, String), Box<dyn Error>> {
+    let nonce: u64 = get_nonce(client, metadata, identity).await?;
+    let (signature, signed_message) = signer
+        .sign_message(&nonce.to_string(), private_key)
+        .await
+        .map_err(|err| SubstrateError::Signer(err.to_string()))?;
+    let xt: UncheckedExtrinsicV4<_> = compose_extrinsic!(
+        metadata,
+        "DidModule",
+        "whitelist_identity",
+        method,
+        signature.to_vec(),
+        signed_message.to_vec(),
+        identity.to_vec(),
+        nonce
+    );
+    Ok((
+        nonce,
+        sign_extrinsic(client, metadata, xt).await?.hex_encode(),
+    ))
+}
+
+/// Sends multiple `DidModule` calls in one `Utility` batch extrinsic.
+///
+/// With `atomic` set, calls are sent with `batch_all` and either all calls or none of them are
+/// applied, a failing call fails the whole extrinsic. Otherwise calls are sent with `batch`, which
+/// stops at the first failing call, calls before it stay applied.
+///
+/// `Utility` calls cannot be sent unsigned, so `client` needs an `extrinsic_signer`.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `private_key` - key reference to sign calls with
+/// * `signer` - `Signer` to sign with
+/// * `calls` - calls to send, dispatched in given order
+/// * `atomic` - apply either all calls or none of them
+///
+/// # Returns
+/// * `Vec<Result<Option<String>, SubstrateError>>` - result per call, anchored DID for
+///   `CreateDid`, errors for failed calls and calls not dispatched after them
+pub async fn send_batch(
+    client: &SubstrateClient,
+    private_key: String,
+    signer: &Box<dyn Signer>,
+    calls: Vec<DidCall>,
+    atomic: bool,
+) -> Result<Vec<Result<Option<String>, SubstrateError>>, Box<dyn Error>> {
+    if client.extrinsic_signer.is_none() {
+        return Err(Box::new(SubstrateError::Signer(
+            "batches have to be sent as signed extrinsics, but no extrinsic signer is set"
+                .to_string(),
+        )));
+    }
+    let metadata = client.get_metadata().await?;
+    let mut nonces = Vec::new();
+    let mut encoded_calls = Vec::new();
+    for call in &calls {
+        let nonce: u64 = get_nonce(client, &metadata, call.identity()).await?;
+        let (signature, signed_message) = signer
+            .sign_message(&nonce.to_string(), &private_key)
+            .await
+            .map_err(|err| SubstrateError::Signer(err.to_string()))?;
+        let encoded = match call {
+            DidCall::WhitelistIdentity { method, identity } => compose_call!(
+                metadata,
+                "DidModule",
+                "whitelist_identity",
+                *method,
+                signature.to_vec(),
+                signed_message.to_vec(),
+                identity.clone(),
+                nonce
+            )
+            .encode(),
+            DidCall::CreateDid {
+                identity,
+                payload: Some(payload),
+            } => compose_call!(
+                metadata,
+                "DidModule",
+                "create_did_with_detail",
+                payload.as_bytes().to_vec(),
+                signature.to_vec(),
+                signed_message.to_vec(),
+                identity.clone(),
+                nonce
+            )
+            .encode(),
+            DidCall::CreateDid {
+                identity,
+                payload: None,
+            } => compose_call!(
+                metadata,
+                "DidModule",
+                "create_did",
+                signature.to_vec(),
+                signed_message.to_vec(),
+                identity.clone(),
+                nonce
+            )
+            .encode(),
+            DidCall::AddPayload {
+                did,
+                identity,
+                payload,
+            } => compose_call!(
+                metadata,
+                "DidModule",
+                "add_did_detail",
+                get_did_bytes_array(&did.trim_start_matches("0x").to_string())?,
+                payload.as_bytes().to_vec(),
+                signature.to_vec(),
+                signed_message.to_vec(),
+                identity.clone(),
+                nonce
+            )
+            .encode(),
+        };
+        nonces.push(nonce);
+        encoded_calls.push(xt_primitives::EncodedCall(encoded));
+    }
+
+    let batch_call = if atomic { "batch_all" } else { "batch" };
+    let xt: UncheckedExtrinsicV4<_> =
+        compose_extrinsic!(metadata.clone(), "Utility", batch_call, encoded_calls);
+    let xt = sign_extrinsic(client, &metadata, xt).await?.hex_encode();
+    let block_hash = match send_extrinsic(client, &metadata, xt.clone(), XtStatus::InBlock).await {
+        Ok(status) => status
+            .block_hash
+            .ok_or("could not get block hash of batch")?,
+        Err(err) => {
+            error!("Error sending batch of {} calls; {}", calls.len(), &err);
+            reset_account_nonce(client)?;
+            return Err(err);
+        }
+    };
+
+    // every dispatched call emits one DidModule event, an interrupted batch emits no further events
+    let mut results = Vec::new();
+    let mut interrupted_at = None;
+    let mut interruption_error = None;
+    let mut did_events = Vec::new();
+    for event in get_extrinsic_events(client, &metadata, &block_hash, &xt).await? {
+        if event.module == DID_MODULE {
+            did_events.extend(DidModuleEvent::from_raw(&event)?)
+        } else if event.module == "Utility" && event.variant == "BatchInterrupted" {
+            let decoded: BatchInterrupted = Decode::decode(&mut &event.data[..])?;
+            interrupted_at = Some(decoded.index as usize);
+            interruption_error = Some(to_substrate_error(&metadata, decoded.error)?);
+        }
+    }
+    let mut did_events = did_events.into_iter();
+    for (position, (call, nonce)) in calls.iter().zip(nonces).enumerate() {
+        match interrupted_at {
+            Some(index) if position == index => {
+                results.push(Err(interruption_error
+                    .take()
+                    .ok_or("missing batch error")?));
+                continue;
+            }
+            Some(index) if position > index => {
+                results.push(Err(SubstrateError::Dispatch(format!(
+                    "call has not been dispatched, batch has been interrupted at call {}",
+                    index
+                ))));
+                continue;
+            }
+            _ => (),
+        }
+        let event = did_events
+            .next()
+            .ok_or_else(|| format!("missing event for call {} of batch", position))?;
+        let (matches_call, did) = match (call, &event) {
+            (DidCall::WhitelistIdentity { .. }, DidModuleEvent::IdentityWhitelist(_))
+            | (DidCall::AddPayload { .. }, DidModuleEvent::UpdatedDid(_)) => (true, None),
+            (DidCall::CreateDid { .. }, DidModuleEvent::Created(created)) => {
+                (true, Some(format!("0x{}", hex::encode(&created.did))))
+            }
+            _ => (false, None),
+        };
+        if !matches_call || event.nonce() != nonce {
+            return Err(Box::from(format!(
+                "event '{}' does not belong to call {} of batch",
+                event.variant(),
+                position
+            )));
+        }
+        results.push(Ok(did));
+    }
+    Ok(results)
+}
+
 /// Retrieves the number of payloads attached to a DID.
 ///
 /// # Arguments
-/// * `url` - Substrate URL
+/// * `client` - connection to substrate node
 /// * `did` - DID to retrieve the count for
-pub async fn get_payload_count_for_did(url: String, did: String) -> Result<u32, Box<dyn Error>> {
-    let metadata = get_metadata(url.as_str()).await?;
-    let bytes_did_arr = get_did_bytes_array(&did)?;
+/// * `at` - hash of block to get count at, gets count at latest block if omitted
+pub async fn get_payload_count_for_did(
+    client: &SubstrateClient,
+    did: String,
+    at: Option<&str>,
+) -> Result<u32, Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    read_payload_count(client, &metadata, &did, at).await
+}
+
+/// Reads the number of payloads of a DID with the metadata of the caller.
+async fn read_payload_count(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    did: &str,
+    at: Option<&str>,
+) -> Result<u32, Box<dyn Error>> {
+    let bytes_did_arr = get_did_bytes_array(&did.to_string())?;
     let bytes_did = sp_core::H256::from(bytes_did_arr);
     let detail_count = get_storage_map::<sp_core::H256, u32>(
-        url.as_str(),
-        metadata.clone(),
+        client,
+        metadata,
         "DidModule",
         "DidsDetailsCount",
         bytes_did,
+        at,
     )
     .await?;
     if detail_count.is_none() {
@@ -847,20 +3013,48 @@ pub async fn get_payload_count_for_did(url: String, did: String) -> Result<u32,
     }
 }
 
+/// Lists all DIDs, that have at least one payload.
+///
+/// # Arguments
+/// * `client` - connection to substrate node
+/// * `at` - hash of block to list DIDs at, lists DIDs at latest block if omitted
+///
+/// # Returns
+/// * `Vec<String>` - hex encoded DIDs without DID method, e.g. `0x1234...`
+pub async fn get_dids(
+    client: &SubstrateClient,
+    at: Option<&str>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let metadata = client.get_metadata().await?;
+    Ok(get_storage_map_entries::<sp_core::H256, u32>(
+        client,
+        &metadata,
+        "DidModule",
+        "DidsDetailsCount",
+        at,
+    )
+    .await?
+    .into_iter()
+    .filter(|(_, detail_count)| *detail_count > 0)
+    .map(|(did, _)| format!("0x{}", hex::encode(did)))
+    .collect())
+}
+
 /// Checks whether a given identity for a given account is whitelisted
 pub async fn is_whitelisted(
-    url: String,
+    client: &SubstrateClient,
     private_key: String,
     signer: &Box<dyn Signer>,
     identity: Vec<u8>,
 ) -> Result<bool, Box<dyn Error>> {
-    let metadata = get_metadata(url.as_str()).await?;
-    let nonce: u64 = get_nonce();
+    let metadata = client.get_metadata().await?;
 
-    // Sign a message to use for retrieving the account ID
+    // Sign a message to use for retrieving the account ID, any message will do, so no nonce of
+    // identity is used up for it
     let (signature, signed_message) = signer
-        .sign_message(&nonce.to_string(), &private_key.to_string())
-        .await?;
+        .sign_message(&hex::encode(&identity), &private_key.to_string())
+        .await
+        .map_err(|err| SubstrateError::Signer(err.to_string()))?;
 
     let account = recover_ethereum_account(signature, signed_message)
         .map_err(|err| format!("Error recovering etherum account: {}", err))?;
@@ -875,11 +3069,12 @@ pub async fn is_whitelisted(
     let identity_hash = hasher.result();
 
     let is_whitelisted = get_storage_map::<(Vec<u8>, Vec<u8>), bool>(
-        url.as_str(),
-        metadata.clone(),
+        client,
+        &metadata,
         "DidModule",
         "WhitelistedIdentities",
         (identity_hash.to_vec(), acc_hash.to_vec()),
+        None,
     )
     .await?
     .unwrap_or_else(|| false);
@@ -991,7 +3186,8 @@ fn json_req(method: &str, params: &str, id: u32) -> Value {
     })
 }
 
-fn recover_ethereum_account(
+/// Recovers ethereum account that signed `signed_message`.
+pub fn recover_ethereum_account(
     full_signature: [u8; 65],
     signed_message: [u8; 32],
 ) -> Result<[u8; 20], Box<dyn Error>> {
@@ -1020,93 +3216,924 @@ fn recover_ethereum_account(
     let mut account_id: [u8; 20] = [0; 20];
     account_id.copy_from_slice(&hash[12..32]);
 
-    Ok(account_id)
-}
+    Ok(account_id)
+}
+
+/// Gets the nonce for the next DID module call of `identity`. `DidModule` does not count nonces per
+/// identity, so nonces are derived from a counter on chain in their upper 32 bits:
+///
+/// - signed extrinsics use the account nonce of the `extrinsic_signer`, which increases with every
+///   extrinsic of the account, bits 16 to 31 hold a tag of the account, so signers acting for the
+///   same identity only collide if their account nonces and tags are equal
+/// - unsigned extrinsics use the time of the latest block in seconds, so only calls of one client
+///   within the same second do not collide
+///
+/// The lower 16 bits number the calls, that have been handed out for the same counter value, e.g.
+/// the calls of one batch or concurrent calls of `client`, which adds nonces, that are not on chain
+/// yet.
+async fn get_nonce(
+    client: &SubstrateClient,
+    metadata: &Metadata,
+    identity: &[u8],
+) -> Result<u64, Box<dyn Error>> {
+    let chain_nonce = match &client.extrinsic_signer {
+        Some(extrinsic_signer) => {
+            let account_id = extrinsic_signer.account_id();
+            let account_nonce = get_account_next_index(client, &account_id).await?;
+            let tag = u16::from_le_bytes([account_id[0], account_id[1]]);
+            u64::from(account_nonce) << 32 | u64::from(tag) << 16
+        }
+        None => {
+            let now: u64 = get_storage_value(client, metadata, "Timestamp", "Now", None)
+                .await?
+                .unwrap_or(0);
+            (now / 1000) << 32
+        }
+    };
+    client.identity_nonces.take(identity, chain_nonce)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        extrinsic::events::{DispatchClass, DispatchInfo, Hash, Pays, TokenError},
+        mock_node::{mock_metadata, MockNode, EVENTS_V14, METADATA_V14},
+    };
+    use futures::future::join_all;
+    use regex::Regex;
+    use sp_core::{ecdsa, sr25519, Pair};
+    use std::{collections::HashSet, env, error::Error, sync::Once};
+    use vade_signer::{LocalSigner, Signer};
+
+    static INIT: Once = Once::new();
+
+    const METHOD_REGEX: &str = r#"^(.*):0x(.*)$"#;
+
+    const SIGNER_1_DID: &str = "did:evan:testcore:0x0d87204c3957d73b68ae28d0af961d3c72403906";
+    const SIGNER_1_PRIVATE_KEY: &str =
+        "dfcdcb6d5d09411ae9cbe1b0fd9751ba8803dd4b276d5bf9488ae4ede2669106";
+
+    fn convert_did_to_substrate_did(did: &str) -> Result<(u8, String), Box<dyn Error>> {
+        let re = Regex::new(METHOD_REGEX)?;
+        let result = re.captures(&did);
+        if let Some(caps) = result {
+            match &caps[1] {
+                "did:evan" => Ok((1, caps[2].to_string())),
+                "did:evan:testcore" => Ok((2, caps[2].to_string())),
+                "did:evan:zkp" => Ok((0, caps[2].to_string())),
+                _ => Err(Box::from(format!("unknown DID format; {}", did))),
+            }
+        } else {
+            Err(Box::from(format!("could not parse DID; {}", did)))
+        }
+    }
+
+    fn enable_logging() {
+        INIT.call_once(|| {
+            env_logger::try_init().ok();
+        });
+    }
+
+    fn get_signer() -> Box<dyn Signer> {
+        Box::new(LocalSigner::new())
+    }
+
+    /// Gets client for node at `VADE_EVAN_SUBSTRATE_IP` if set, otherwise for a new mock node, that
+    /// has to be kept alive while the client is used. Extrinsics are signed with the key of the test
+    /// identity.
+    fn get_client() -> (SubstrateClient, Option<MockNode>) {
+        let (mut client, node) = match env::var("VADE_EVAN_SUBSTRATE_IP") {
+            Ok(target) => (
+                SubstrateClient::new(SubstrateEndpoints::from_target(&target)),
+                None,
+            ),
+            Err(_) => {
+                let node = MockNode::start().expect("could not start mock node");
+                (SubstrateClient::new(node.endpoints.clone()), Some(node))
+            }
+        };
+        client.extrinsic_signer = Some(get_extrinsic_signer());
+        (client, node)
+    }
+
+    /// Gets client for `node`, that signs extrinsics with `extrinsic_signer`.
+    fn get_client_for(
+        node: &MockNode,
+        extrinsic_signer: Option<Box<dyn ExtrinsicSigner>>,
+    ) -> SubstrateClient {
+        let mut client = SubstrateClient::new(node.endpoints.clone());
+        client.extrinsic_signer = extrinsic_signer;
+        client
+    }
+
+    /// Gets the nonce of the call at `offset` of an extrinsic signed by `account_id` with
+    /// `account_nonce`.
+    fn signed_nonce(account_id: &xt_primitives::AccountId, account_nonce: u32, offset: u16) -> u64 {
+        let tag = u16::from_le_bytes([account_id[0], account_id[1]]);
+        u64::from(account_nonce) << 32 | u64::from(tag) << 16 | u64::from(offset)
+    }
+
+    /// Gets the DID created by `identity` with `nonce`.
+    fn did_of(identity: &[u8], nonce: u64) -> String {
+        let mut hasher = Keccak256::new();
+        hasher.input(identity);
+        hasher.input(nonce.encode());
+        format!("0x{}", hex::encode(hasher.result()))
+    }
+
+    fn get_extrinsic_signer() -> Box<dyn ExtrinsicSigner> {
+        let seed = hex::decode(SIGNER_1_PRIVATE_KEY).expect("could not decode private key");
+        Box::new(ecdsa::Pair::from_seed_slice(&seed).expect("could not create extrinsic signer"))
+    }
+
+    #[test]
+    fn creates_endpoints_for_bare_host() {
+        assert_eq!(
+            SubstrateEndpoints::from_target("substrate-dev.trust-trace.com"),
+            SubstrateEndpoints {
+                http_rpc: "https://substrate-dev.trust-trace.com/rpc".to_string(),
+                ws_rpc: "wss://substrate-dev.trust-trace.com/ws".to_string(),
+                ipfs: "https://substrate-dev.trust-trace.com/ipfs".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn creates_endpoints_for_scheme_of_target() {
+        assert_eq!(
+            SubstrateEndpoints::from_target("http://127.0.0.1:8080/"),
+            SubstrateEndpoints {
+                http_rpc: "http://127.0.0.1:8080/rpc".to_string(),
+                ws_rpc: "ws://127.0.0.1:8080/ws".to_string(),
+                ipfs: "http://127.0.0.1:8080/ipfs".to_string(),
+            }
+        );
+        assert_eq!(
+            SubstrateEndpoints::from_target("ws://127.0.0.1:8080"),
+            SubstrateEndpoints::from_target("http://127.0.0.1:8080")
+        );
+        assert_eq!(
+            SubstrateEndpoints::from_target("https://substrate-dev.trust-trace.com"),
+            SubstrateEndpoints::from_target("substrate-dev.trust-trace.com")
+        );
+        assert_eq!(
+            SubstrateEndpoints::from_target("wss://substrate-dev.trust-trace.com"),
+            SubstrateEndpoints::from_target("substrate-dev.trust-trace.com")
+        );
+    }
+
+    #[test]
+    fn fails_transactions_with_final_pool_status() -> Result<(), Box<dyn Error>> {
+        let tracker = TransactionTracker::default();
+        let block_hash = "0x4ba2c3a6e3f1e0a3a0fb7b07a7ba7a4f8f3b5c5d9d0e2f7d5c1b3a2e1f0d9c8b";
+        let cases = vec![
+            (json!("dropped"), None),
+            (json!("invalid"), None),
+            (json!({ "usurped": format!("0x{}", "11".repeat(32)) }), None),
+            (
+                json!({ "finalityTimeout": block_hash }),
+                Some(block_hash.to_string()),
+            ),
+        ];
+        for (index, (result, expected_block_hash)) in cases.into_iter().enumerate() {
+            let hash = format!("0x{:064x}", index);
+            let (sender, receiver) = futures::channel::mpsc::unbounded();
+            tracker.insert(&hash, "0x00", receiver)?;
+            for status in vec![json!("ready"), result] {
+                sender.unbounded_send(
+                    json!({
+                        "jsonrpc": "2.0",
+                        "method": "author_extrinsicUpdate",
+                        "params": { "subscription": "sub-1", "result": status },
+                    })
+                    .to_string(),
+                )?;
+            }
+            match tracker.update(&hash)? {
+                (TransactionStatus::Failed { block_hash, .. }, _, false) => {
+                    assert_eq!(block_hash, expected_block_hash)
+                }
+                status => panic!("expected failed transaction, got {:?}", status),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_tracking_transactions_of_retracted_blocks() -> Result<(), Box<dyn Error>> {
+        let tracker = TransactionTracker::default();
+        let hash = format!("0x{:064x}", 1);
+        let retracted_block = format!("0x{}", "11".repeat(32));
+        let block_hash = format!("0x{}", "22".repeat(32));
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        tracker.insert(&hash, "0x00", receiver)?;
+        let send = |status: Value| {
+            sender.unbounded_send(
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "author_extrinsicUpdate",
+                    "params": { "subscription": "sub-1", "result": status },
+                })
+                .to_string(),
+            )
+        };
+        send(json!({ "inBlock": retracted_block }))?;
+        send(json!({ "retracted": retracted_block }))?;
+        assert_eq!(tracker.update(&hash)?.0, TransactionStatus::Ready);
+
+        send(json!({ "inBlock": block_hash }))?;
+        send(json!({ "finalized": block_hash }))?;
+        assert_eq!(
+            tracker.update(&hash)?.0,
+            TransactionStatus::Finalized { block_hash }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refetches_metadata_when_runtime_version_changes() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (client, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // runtime upgrades cannot be triggered on a live node
+        };
+        client.get_metadata().await?;
+        // same runtime version, so cached metadata is used instead of the invalid blob
+        node.set_metadata(vec![0, 1, 2], 1);
+        client.get_metadata().await?;
+        node.set_metadata(vec![0, 1, 2], 2);
+        assert!(client.get_metadata().await.is_err());
+        node.set_metadata(mock_metadata().encode(), 3);
+        client
+            .get_metadata()
+            .await?
+            .module_with_calls("DidModule")?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reads_runtime_version_once_per_did_operation() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let identity = hex::decode(substrate_did)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // requests of live nodes cannot be counted
+        };
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity.clone(),
+            Some("first"),
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+
+        let before = node.request_count("state_getRuntimeVersion");
+        add_payload_to_did(
+            &client,
+            "second".to_string(),
+            did.clone(),
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity,
+            XtStatus::InBlock,
+        )
+        .await?;
+        // one for the metadata and one for the signed extra of the extrinsic
+        assert_eq!(node.request_count("state_getRuntimeVersion") - before, 2);
+
+        let before = node.request_count("state_getRuntimeVersion");
+        assert_eq!(get_payloads_of_did(&client, did, None).await?.len(), 2);
+        assert_eq!(node.request_count("state_getRuntimeVersion") - before, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_whitelist_identity() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (method, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, _node) = get_client();
+        whitelist_identity(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            method,
+            hex::decode(substrate_did)?,
+            XtStatus::InBlock,
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_create_a_did() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, _node) = get_client();
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            hex::decode(substrate_did)?,
+            None,
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+
+        println!("DID: {:?}", did);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn skips_events_before_extrinsic_is_in_block() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let identity = hex::decode(substrate_did)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, node) = get_client();
+        if node.is_none() {
+            return Ok(()); // live nodes may not broadcast extrinsics
+        }
+        let metadata = client.get_metadata().await?;
+        let (_, xt) = compose_did_creation(
+            &client,
+            &metadata,
+            SIGNER_1_PRIVATE_KEY,
+            &signer,
+            &identity,
+            None,
+        )
+        .await?;
+        let status = send_extrinsic(&client, &metadata, xt.clone(), XtStatus::Ready).await?;
+        assert_eq!(status.hash, get_extrinsic_hash(&xt)?);
+        assert_eq!(status.status, XtStatus::Ready);
+        assert_eq!(status.block_hash, None);
+
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity,
+            None,
+            XtStatus::Broadcast,
+        )
+        .await?;
+        assert_eq!(did, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_create_a_did_with_signed_extrinsics() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (mut client, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // accounts on live nodes are not funded by tests
+        };
+        node.require_signed_extrinsics();
+        let identity = hex::decode(substrate_did)?;
+        let extrinsic_signer = client.extrinsic_signer.take();
+        assert!(create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity.clone(),
+            None,
+            XtStatus::InBlock
+        )
+        .await
+        .is_err());
+
+        client.extrinsic_signer = extrinsic_signer;
+        let account_id = client
+            .extrinsic_signer
+            .as_ref()
+            .ok_or("missing extrinsic signer")?
+            .account_id();
+        for _ in 0..2 {
+            create_did(
+                &client,
+                SIGNER_1_PRIVATE_KEY.to_string(),
+                &signer,
+                identity.clone(),
+                None,
+                XtStatus::InBlock,
+            )
+            .await?;
+        }
+        assert_eq!(get_account_next_index(&client, &account_id).await?, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn takes_identity_nonces_from_chain() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let identity = hex::decode(substrate_did)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // nonces of identities on live nodes are unknown
+        };
+        is_whitelisted(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity.clone(),
+        )
+        .await?;
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity.clone(),
+            None,
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+        // checking the whitelist has not used up the first nonce of account nonce 0
+        let account_id = get_extrinsic_signer().account_id();
+        assert_eq!(did, did_of(&identity, signed_nonce(&account_id, 0, 0)));
+
+        // other clients, e.g. of other processes, continue with the account nonce on chain
+        let mut other_client = SubstrateClient::new(node.endpoints.clone());
+        other_client.extrinsic_signer = Some(get_extrinsic_signer());
+        assert_eq!(
+            get_nonce(&other_client, &client.get_metadata().await?, &identity).await?,
+            signed_nonce(&account_id, 1, 0),
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn creates_dids_after_batches_in_new_clients() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let identity = hex::decode(substrate_did)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // accounts on live nodes are not funded by tests
+        };
+        let create_call = DidCall::CreateDid {
+            identity: identity.clone(),
+            payload: None,
+        };
+        let mut dids = HashSet::new();
+        for result in send_batch(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            vec![create_call.clone(), create_call.clone(), create_call],
+            true,
+        )
+        .await?
+        {
+            dids.insert(result?.ok_or("DID has not been created")?);
+        }
+        assert_eq!(dids.len(), 3);
+
+        // batch has increased the account nonce only once, but used three nonces of the identity
+        let new_client = get_client_for(&node, Some(get_extrinsic_signer()));
+        let did = create_did(
+            &new_client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity.clone(),
+            None,
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+        assert!(dids.insert(did));
+
+        // other signers acting for the same identity start at the same account nonce
+        let other_signer = sr25519::Pair::from_seed(&[7; 32]);
+        let other_client = get_client_for(&node, Some(Box::new(other_signer)));
+        for _ in 0..2 {
+            let did = create_did(
+                &other_client,
+                SIGNER_1_PRIVATE_KEY.to_string(),
+                &signer,
+                identity.clone(),
+                None,
+                XtStatus::InBlock,
+            )
+            .await?
+            .ok_or("DID has not been created")?;
+            assert!(dids.insert(did));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_create_dids_with_unsigned_extrinsics() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let identity = hex::decode(substrate_did)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (_, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // block time of live nodes may change while creating DIDs
+        };
+        let client = get_client_for(&node, None);
+        let metadata = client.get_metadata().await?;
+        let now: u64 = get_storage_value(&client, &metadata, "Timestamp", "Now", None)
+            .await?
+            .ok_or("missing timestamp")?;
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity.clone(),
+            None,
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+        assert_eq!(did, did_of(&identity, (now / 1000) << 32));
+
+        let dids = join_all((0..2).map(|_| {
+            create_did(
+                &client,
+                SIGNER_1_PRIVATE_KEY.to_string(),
+                &signer,
+                identity.clone(),
+                None,
+                XtStatus::InBlock,
+            )
+        }))
+        .await
+        .into_iter()
+        .collect::<Result<HashSet<Option<String>>, _>>()?;
+        assert_eq!(dids.len(), 2);
+        assert!(!dids.contains(&None));
+        assert!(!dids.contains(&Some(did)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_create_dids_concurrently() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let identity = hex::decode(substrate_did)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, _node) = get_client();
+        let dids = join_all((0..3).map(|_| {
+            create_did(
+                &client,
+                SIGNER_1_PRIVATE_KEY.to_string(),
+                &signer,
+                identity.clone(),
+                None,
+                XtStatus::InBlock,
+            )
+        }))
+        .await
+        .into_iter()
+        .collect::<Result<HashSet<Option<String>>, _>>()?;
+        assert_eq!(dids.len(), 3);
+        assert!(!dids.contains(&None));
+
+        Ok(())
+    }
 
-fn get_nonce() -> u64 {
-    let mut rng = rand::thread_rng();
-    #[cfg(target_arch = "wasm32")]
-    let now_timestamp = js_sys::Date::new_0().get_time() as u64;
-    #[cfg(not(target_arch = "wasm32"))]
-    let now_timestamp: u64 = Utc::now().timestamp_nanos() as u64;
+    #[tokio::test]
+    async fn watches_dids_without_requesting_runtime_versions() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let identity = hex::decode(substrate_did)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // requests to live nodes cannot be counted
+        };
+        let mut changes = Box::pin(watch_did(&client, None).await?);
+        let metadata = client.get_metadata().await?;
+        let mut xts = Vec::new();
+        for _ in 0..3 {
+            xts.push(
+                compose_did_creation(
+                    &client,
+                    &metadata,
+                    SIGNER_1_PRIVATE_KEY,
+                    &signer,
+                    &identity,
+                    None,
+                )
+                .await?
+                .1,
+            );
+        }
+        let runtime_version_requests = node.request_count("state_getRuntimeVersion");
+        for xt in xts {
+            submit_extrinsic(&client, &xt)?;
+            assert!(matches!(
+                changes.next().await,
+                Some(Ok(DidChange::Created { .. }))
+            ));
+        }
+        assert_eq!(
+            node.request_count("state_getRuntimeVersion"),
+            runtime_version_requests
+        );
 
-    rng.gen_range(0, 100) + now_timestamp
-}
+        Ok(())
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use regex::Regex;
-    use std::{env, error::Error, sync::Once};
-    use vade_signer::{LocalSigner, Signer};
+    #[tokio::test]
+    async fn decodes_did_changes_with_metadata_of_new_runtimes() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let identity = hex::decode(substrate_did)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // runtime upgrades cannot be triggered on a live node
+        };
+        let mut changes = Box::pin(watch_did(&client, None).await?);
+        let (_, xt) = compose_did_creation(
+            &client,
+            &client.get_metadata().await?,
+            SIGNER_1_PRIVATE_KEY,
+            &signer,
+            &identity,
+            None,
+        )
+        .await?;
+        // new runtime version, so metadata is fetched again for its block
+        node.set_metadata(vec![0, 1, 2], 2);
+        submit_extrinsic(&client, &xt)?;
+        assert!(matches!(
+            changes.next().await,
+            Some(Err(SubstrateError::Codec(_)))
+        ));
 
-    static INIT: Once = Once::new();
+        node.set_metadata(mock_metadata().encode(), 3);
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity,
+            None,
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+        match changes.next().await {
+            Some(Ok(DidChange::Created { did: created, .. })) => assert_eq!(created, did),
+            change => panic!("expected creation of DID, got {:?}", change),
+        }
 
-    const METHOD_REGEX: &str = r#"^(.*):0x(.*)$"#;
+        Ok(())
+    }
 
-    const SIGNER_1_DID: &str = "did:evan:testcore:0x0d87204c3957d73b68ae28d0af961d3c72403906";
-    const SIGNER_1_PRIVATE_KEY: &str =
-        "dfcdcb6d5d09411ae9cbe1b0fd9751ba8803dd4b276d5bf9488ae4ede2669106";
-    const DEFAULT_VADE_EVAN_SUBSTRATE_IP: &str = "substrate-dev.trust-trace.com";
+    #[test]
+    fn reports_undecodable_events_of_did_changes() -> Result<(), Box<dyn Error>> {
+        let client = SubstrateClient::new(SubstrateEndpoints::from_target("127.0.0.1"));
+        let decoder = client.events_decoder(Metadata::parse(mock_metadata())?)?;
+        let did = vec![1u8; 32];
+        // `DidModule::Created`, followed by an event of a module, that is unknown to the metadata
+        let mut events = Compact(2u32).encode();
+        events.extend(Phase::ApplyExtrinsic(0).encode());
+        events.extend(&[1, 0]);
+        events.extend((did.clone(), vec![2u8; 20], 3u64).encode());
+        events.extend(Vec::<H256>::new().encode());
+        events.extend(Phase::ApplyExtrinsic(1).encode());
+        events.extend(&[200, 0, 1, 2, 3]);
+        let notification = json!({
+            "params": {
+                "result": {
+                    "block": "0x01",
+                    "changes": [["0x", format!("0x{}", hex::encode(&events))]],
+                },
+            },
+        });
 
-    fn convert_did_to_substrate_did(did: &str) -> Result<(u8, String), Box<dyn Error>> {
-        let re = Regex::new(METHOD_REGEX)?;
-        let result = re.captures(&did);
-        if let Some(caps) = result {
-            match &caps[1] {
-                "did:evan" => Ok((1, caps[2].to_string())),
-                "did:evan:testcore" => Ok((2, caps[2].to_string())),
-                "did:evan:zkp" => Ok((0, caps[2].to_string())),
-                _ => Err(Box::from(format!("unknown DID format; {}", did))),
+        let changes = decode_did_changes(&decoder, &notification.to_string(), None);
+        match &changes[..] {
+            [Ok(DidChange::Created { did: created, .. }), Err(SubstrateError::Metadata(_))] => {
+                assert_eq!(created, &format!("0x{}", hex::encode(&did)))
             }
-        } else {
-            Err(Box::from(format!("could not parse DID; {}", did)))
+            _ => panic!("expected creation of DID and an error, got {:?}", changes),
         }
-    }
 
-    fn enable_logging() {
-        INIT.call_once(|| {
-            env_logger::try_init().ok();
-        });
+        Ok(())
     }
 
-    fn get_signer() -> Box<dyn Signer> {
-        Box::new(LocalSigner::new())
+    #[tokio::test]
+    async fn can_get_did_at_past_block() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, converted_identity) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let converted_identity_vec = hex::decode(converted_identity)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, _node) = get_client();
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            converted_identity_vec.clone(),
+            Some("first"),
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+        let block_hash = get_block_hash(&client, None)
+            .await?
+            .ok_or("could not get latest block")?;
+        let block_time: u64 = get_storage_value(
+            &client,
+            &client.get_metadata().await?,
+            "Timestamp",
+            "Now",
+            Some(&block_hash),
+        )
+        .await?
+        .ok_or("could not get block time")?;
+        update_payload_in_did(
+            &client,
+            0u32,
+            "second".to_string(),
+            did.clone(),
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            converted_identity_vec.clone(),
+            XtStatus::InBlock,
+        )
+        .await?;
+
+        assert_eq!(get_did(&client, did.clone(), None).await?, "second");
+        assert_eq!(
+            get_did(&client, did.clone(), Some(&block_hash)).await?,
+            "first"
+        );
+        let block_at_time = get_block_hash_at_time(&client, block_time).await?;
+        assert_eq!(
+            get_did(&client, did.clone(), Some(&block_at_time)).await?,
+            "first"
+        );
+        assert!(get_block_number(&client, &block_at_time).await? > 0);
+        let unknown_block = format!("0x{}", hex::encode([0u8; 32]));
+        match get_block_number(&client, &unknown_block)
+            .await
+            .map_err(|err| err.downcast::<SubstrateError>())
+        {
+            Err(Ok(err)) => assert!(matches!(*err, SubstrateError::BlockNotFound(_))),
+            _ => panic!("expected block to be missing"),
+        }
+
+        Ok(())
     }
 
     #[tokio::test]
-    async fn can_whitelist_identity() -> Result<(), Box<dyn Error>> {
+    async fn returns_module_error_for_failed_extrinsic() -> Result<(), Box<dyn Error>> {
         enable_logging();
-        let (method, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
         let signer: Box<dyn Signer> = get_signer();
-        whitelist_identity(
-            env::var("VADE_EVAN_SUBSTRATE_IP")
-                .unwrap_or_else(|_| DEFAULT_VADE_EVAN_SUBSTRATE_IP.to_string()),
+        let (client, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // failures cannot be triggered on a live node
+        };
+        node.fail_next_extrinsic(0);
+        let result = create_did(
+            &client,
             SIGNER_1_PRIVATE_KEY.to_string(),
             &signer,
-            method,
             hex::decode(substrate_did)?,
+            None,
+            XtStatus::InBlock,
         )
-        .await?;
+        .await;
+
+        match result.map_err(|err| err.downcast::<SubstrateError>()) {
+            Err(Ok(err)) => match *err {
+                SubstrateError::DispatchModule {
+                    module,
+                    name,
+                    documentation,
+                    ..
+                } => {
+                    assert_eq!(module, "DidModule");
+                    assert_eq!(name, "IdentityNotWhitelisted");
+                    assert_eq!(
+                        documentation,
+                        "Identity is not whitelisted for sending transactions"
+                    );
+                }
+                _ => panic!("unexpected error: {}", err),
+            },
+            _ => panic!("expected extrinsic to fail"),
+        }
+
         Ok(())
     }
 
     #[tokio::test]
-    async fn can_create_a_did() -> Result<(), Box<dyn Error>> {
+    async fn returns_system_error_for_failed_extrinsic() -> Result<(), Box<dyn Error>> {
         enable_logging();
         let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
         let signer: Box<dyn Signer> = get_signer();
-        let did = create_did(
-            env::var("VADE_EVAN_SUBSTRATE_IP")
-                .unwrap_or_else(|_| DEFAULT_VADE_EVAN_SUBSTRATE_IP.to_string()),
+        let (client, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // failures cannot be triggered on a live node
+        };
+        let cases = vec![
+            (DispatchError::BadOrigin, "BadOrigin", "A bad origin."),
+            (
+                DispatchError::Token(TokenError::NoFunds),
+                "Token::NoFunds",
+                "Funds are unavailable.",
+            ),
+        ];
+        for (dispatch_error, expected_name, expected_documentation) in cases {
+            node.fail_next_extrinsic_with(dispatch_error);
+            let result = create_did(
+                &client,
+                SIGNER_1_PRIVATE_KEY.to_string(),
+                &signer,
+                hex::decode(&substrate_did)?,
+                None,
+                XtStatus::InBlock,
+            )
+            .await;
+
+            match result.map_err(|err| err.downcast::<SubstrateError>()) {
+                Err(Ok(err)) => match *err {
+                    SubstrateError::DispatchSystem {
+                        name,
+                        documentation,
+                    } => {
+                        assert_eq!(name, expected_name);
+                        assert_eq!(documentation, expected_documentation);
+                    }
+                    _ => panic!("unexpected error: {}", err),
+                },
+                _ => panic!("expected extrinsic to fail"),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn returns_error_for_dropped_extrinsic() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, node) = get_client();
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(()), // failures cannot be triggered on a live node
+        };
+        node.drop_next_extrinsic();
+        let result = create_did(
+            &client,
             SIGNER_1_PRIVATE_KEY.to_string(),
             &signer,
-            hex::decode(substrate_did)?,
+            hex::decode(&substrate_did)?,
             None,
+            XtStatus::InBlock,
         )
-        .await?;
+        .await;
 
-        println!("DID: {:?}", did);
+        // dropped extrinsic will never be in a block, so waiting for it must not time out
+        match result.map_err(|err| err.downcast::<SubstrateError>()) {
+            Err(Ok(err)) => match *err {
+                SubstrateError::Dispatch(message) => assert!(message.contains("dropped")),
+                _ => panic!("unexpected error: {}", err),
+            },
+            _ => panic!("expected extrinsic to fail"),
+        }
 
         Ok(())
     }
@@ -1117,71 +4144,53 @@ mod tests {
         let (_, converted_identity) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
         let converted_identity_vec = hex::decode(converted_identity)?;
         let signer: Box<dyn Signer> = get_signer();
+        let (client, _node) = get_client();
         let did = create_did(
-            env::var("VADE_EVAN_SUBSTRATE_IP")
-                .unwrap_or_else(|_| DEFAULT_VADE_EVAN_SUBSTRATE_IP.to_string()),
+            &client,
             SIGNER_1_PRIVATE_KEY.to_string(),
             &signer,
             converted_identity_vec.clone(),
             None,
+            XtStatus::InBlock,
         )
-        .await?;
+        .await?
+        .ok_or("DID has not been created")?;
         add_payload_to_did(
-            env::var("VADE_EVAN_SUBSTRATE_IP")
-                .unwrap_or_else(|_| DEFAULT_VADE_EVAN_SUBSTRATE_IP.to_string()),
+            &client,
             "Hello_World".to_string(),
             did.clone(),
             SIGNER_1_PRIVATE_KEY.to_string(),
             &signer,
             converted_identity_vec.clone(),
+            XtStatus::InBlock,
         )
         .await?;
-        let _detail_count = get_payload_count_for_did(
-            env::var("VADE_EVAN_SUBSTRATE_IP")
-                .unwrap_or_else(|_| DEFAULT_VADE_EVAN_SUBSTRATE_IP.to_string()),
-            did.clone(),
-        )
-        .await?;
-        let did_detail1 = get_did(
-            env::var("VADE_EVAN_SUBSTRATE_IP")
-                .unwrap_or_else(|_| DEFAULT_VADE_EVAN_SUBSTRATE_IP.to_string()),
-            did.clone(),
-        )
-        .await?;
+        let _detail_count = get_payload_count_for_did(&client, did.clone(), None).await?;
+        let did_detail1 = get_did(&client, did.clone(), None).await?;
         update_payload_in_did(
-            env::var("VADE_EVAN_SUBSTRATE_IP")
-                .unwrap_or_else(|_| DEFAULT_VADE_EVAN_SUBSTRATE_IP.to_string()),
+            &client,
             0u32,
             "Hello_World_update".to_string(),
             did.clone(),
             SIGNER_1_PRIVATE_KEY.to_string(),
             &signer,
             converted_identity_vec.clone(),
+            XtStatus::InBlock,
         )
         .await?;
-        let did_detail2 = get_did(
-            env::var("VADE_EVAN_SUBSTRATE_IP")
-                .unwrap_or_else(|_| DEFAULT_VADE_EVAN_SUBSTRATE_IP.to_string()),
-            did.clone(),
-        )
-        .await?;
+        let did_detail2 = get_did(&client, did.clone(), None).await?;
         update_payload_in_did(
-            env::var("VADE_EVAN_SUBSTRATE_IP")
-                .unwrap_or_else(|_| DEFAULT_VADE_EVAN_SUBSTRATE_IP.to_string()),
+            &client,
             0u32,
             "Hello_World".to_string(),
             did.clone(),
             SIGNER_1_PRIVATE_KEY.to_string(),
             &signer,
             converted_identity_vec.clone(),
+            XtStatus::InBlock,
         )
         .await?;
-        let did_detail3 = get_did(
-            env::var("VADE_EVAN_SUBSTRATE_IP")
-                .unwrap_or_else(|_| DEFAULT_VADE_EVAN_SUBSTRATE_IP.to_string()),
-            did.clone(),
-        )
-        .await?;
+        let did_detail3 = get_did(&client, did.clone(), None).await?;
 
         assert_eq!(&did_detail1, &did_detail3);
         assert_ne!(&did_detail1, &did_detail2);
@@ -1189,4 +4198,335 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn can_get_all_payloads_of_did() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, converted_identity) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let converted_identity_vec = hex::decode(converted_identity)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, _node) = get_client();
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            converted_identity_vec.clone(),
+            Some("first"),
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+        add_payload_to_did(
+            &client,
+            "second".to_string(),
+            did.clone(),
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            converted_identity_vec.clone(),
+            XtStatus::InBlock,
+        )
+        .await?;
+
+        assert_eq!(
+            get_payload_of_did(&client, did.clone(), 1, None).await?,
+            "second"
+        );
+        assert_eq!(get_did(&client, did.clone(), None).await?, "second");
+        assert_eq!(
+            get_payloads_of_did(&client, did.clone(), None).await?,
+            vec!["first".to_string(), "second".to_string()],
+        );
+        assert!(get_payload_of_did(&client, did.clone(), 2, None)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_get_all_events_of_block() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (_, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, _node) = get_client();
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            hex::decode(substrate_did)?,
+            None,
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+        let block_hash = get_block_hash(&client, None)
+            .await?
+            .ok_or("missing latest block")?;
+
+        let records = get_events(&client, &block_hash).await?;
+        let created = records
+            .iter()
+            .find(|record| record.event.module == "DidModule" && record.event.variant == "Created")
+            .ok_or("missing 'Created' event")?;
+        assert!(matches!(created.phase, Phase::ApplyExtrinsic(_)));
+        assert!(created.topics.is_empty());
+        assert_eq!(created.event.arguments[0], json!(did));
+        assert!(records.iter().any(|record| matches!(
+            SystemEvent::from_raw(&record.event),
+            Ok(Some(SystemEvent::ExtrinsicSuccess(_)))
+        ) && record.phase == created.phase));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_get_events_of_block_with_modules_outside_of_mock() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let node = MockNode::start_with_metadata(METADATA_V14)?;
+        let client = SubstrateClient::new(node.endpoints.clone());
+        let events = hex::decode(EVENTS_V14.trim().trim_start_matches("0x"))?;
+        let block_hash = node.import_block_with_events(events);
+
+        let records = get_events(&client, &block_hash).await?;
+        let names: Vec<(u32, &str, &str)> = records
+            .iter()
+            .map(|record| match record.phase {
+                Phase::ApplyExtrinsic(index) => Ok((
+                    index,
+                    record.event.module.as_str(),
+                    record.event.variant.as_str(),
+                )),
+                _ => Err(format!("unexpected phase {:?}", record.phase)),
+            })
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            names,
+            vec![
+                (0, "System", "ExtrinsicSuccess"),
+                (1, "System", "NewAccount"),
+                (1, "DidModule", "Created"),
+                (1, "System", "ExtrinsicSuccess"),
+                (2, "Assets", "Transferred"),
+                (2, "System", "ExtrinsicSuccess"),
+                (3, "Utility", "BatchInterrupted"),
+                (3, "System", "ExtrinsicSuccess"),
+                (4, "System", "ExtrinsicFailed"),
+                (5, "System", "Remarked"),
+                (5, "System", "ExtrinsicSuccess"),
+            ]
+        );
+
+        let account = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+        let topic = "0e2c43a1d7f1b3a5a9c0f7d4b3e5f6a7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f3";
+        assert!(matches!(
+            SystemEvent::from_raw(&records[0].event)?,
+            Some(SystemEvent::ExtrinsicSuccess(DispatchInfo {
+                weight: 161_480_000,
+                class: DispatchClass::Mandatory,
+                pays_fee: Pays::Yes,
+            }))
+        ));
+        assert_eq!(records[1].event.arguments, vec![json!(account)]);
+        match DidModuleEvent::from_raw(&records[2].event)? {
+            Some(DidModuleEvent::Created(created)) => assert_eq!(created.nonce, 1),
+            other => panic!("unexpected DID event {:?}", other),
+        }
+        assert_eq!(
+            records[4].event.arguments,
+            vec![
+                json!(7),
+                json!(account),
+                json!("0x8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48"),
+                json!("100000000000000000000"),
+            ]
+        );
+        assert_eq!(
+            records[4].topics,
+            vec![Hash::from_slice(&hex::decode(topic)?)]
+        );
+        assert_eq!(
+            records[6].event.arguments,
+            vec![json!(1), json!({ "module": { "index": 1, "error": 0 } })]
+        );
+        assert!(matches!(
+            SystemEvent::from_raw(&records[8].event)?,
+            Some(SystemEvent::ExtrinsicFailed(
+                DispatchError::Module {
+                    index: 1,
+                    error: 2,
+                    ..
+                },
+                _,
+            ))
+        ));
+        assert_eq!(
+            records[9].event.arguments,
+            vec![json!(account), json!(format!("0x{}", topic))]
+        );
+        assert!(records
+            .iter()
+            .filter(|record| record.event.variant != "Transferred")
+            .all(|record| record.topics.is_empty()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_get_did_module_events_of_block() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (method, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let identity = hex::decode(substrate_did)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, node) = get_client();
+        if node.is_none() {
+            return Ok(()); // batches have to be signed, but accounts on live nodes are not funded
+        }
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity.clone(),
+            None,
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+        let results = send_batch(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            vec![
+                DidCall::WhitelistIdentity {
+                    method,
+                    identity: identity.clone(),
+                },
+                DidCall::CreateDid {
+                    identity: identity.clone(),
+                    payload: None,
+                },
+                DidCall::AddPayload {
+                    did: did.clone(),
+                    identity: identity.clone(),
+                    payload: "first".to_string(),
+                },
+            ],
+            true,
+        )
+        .await?;
+        let block_hash = get_block_hash(&client, None)
+            .await?
+            .ok_or("missing latest block")?;
+
+        let events = get_did_module_events(&client, &block_hash).await?;
+        assert_eq!(events.len(), 3);
+        match &events[0] {
+            DidModuleEvent::IdentityWhitelist(whitelist) => {
+                assert_eq!(whitelist.identity, identity);
+                assert!(whitelist.approved);
+            }
+            event => panic!("expected whitelisting, got {:?}", event),
+        }
+        match &events[1] {
+            DidModuleEvent::Created(created) => {
+                assert_eq!(
+                    results[1].as_ref().ok(),
+                    Some(&Some(format!("0x{}", hex::encode(&created.did))))
+                );
+                assert_eq!(created.owner, identity);
+            }
+            event => panic!("expected creation, got {:?}", event),
+        }
+        match &events[2] {
+            DidModuleEvent::UpdatedDid(updated) => {
+                assert_eq!(format!("0x{}", hex::encode(&updated.did)), did);
+                assert_eq!(updated.index, 0);
+            }
+            event => panic!("expected update, got {:?}", event),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_read_all_entries_of_storage_maps() -> Result<(), Box<dyn Error>> {
+        enable_logging();
+        let (method, substrate_did) = convert_did_to_substrate_did(&SIGNER_1_DID)?;
+        let identity = hex::decode(substrate_did)?;
+        let signer: Box<dyn Signer> = get_signer();
+        let (client, _node) = get_client();
+        whitelist_identity(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            method,
+            identity.clone(),
+            XtStatus::InBlock,
+        )
+        .await?;
+        let did = create_did(
+            &client,
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity.clone(),
+            Some("first"),
+            XtStatus::InBlock,
+        )
+        .await?
+        .ok_or("DID has not been created")?;
+        add_payload_to_did(
+            &client,
+            "second".to_string(),
+            did.clone(),
+            SIGNER_1_PRIVATE_KEY.to_string(),
+            &signer,
+            identity.clone(),
+            XtStatus::InBlock,
+        )
+        .await?;
+        let metadata = client.get_metadata().await?;
+
+        let bytes_did = H256::from(get_did_bytes_array(&did)?);
+        let mut indices: Vec<u32> = get_storage_map_entries::<(H256, u32), Vec<u8>>(
+            &client,
+            &metadata,
+            "DidModule",
+            "DidsDetails",
+            None,
+        )
+        .await?
+        .into_iter()
+        .filter(|((entry_did, _), _)| entry_did == &bytes_did)
+        .map(|((_, index), _)| index)
+        .collect();
+        indices.sort_unstable();
+        assert_eq!(indices, vec![0, 1]);
+        assert!(get_dids(&client, None).await?.contains(&did));
+
+        let whitelisted = get_storage_map_entries::<(Vec<u8>, Vec<u8>), bool>(
+            &client,
+            &metadata,
+            "DidModule",
+            "WhitelistedIdentities",
+            None,
+        )
+        .await?;
+        assert!(!whitelisted.is_empty());
+        assert!(whitelisted.iter().all(|(_, whitelisted)| *whitelisted));
+
+        assert!(get_storage_map_entries::<(H256, u32), Vec<u8>>(
+            &client,
+            &metadata,
+            "DidModule",
+            "DidsDetails",
+            Some(
+                &get_block_hash(&client, Some(0))
+                    .await?
+                    .ok_or("missing genesis")?
+            ),
+        )
+        .await?
+        .is_empty());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,204 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+use crate::utils::errors::SubstrateError;
+use futures::future::{self, Either, Future};
+use futures_timer::Delay;
+use std::{error::Error, time::Duration};
+
+/// default timeout for RPC requests, event waits and content fetches
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Timeouts for requests against substrate nodes and content stores.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timeouts {
+    /// Timeout for a single RPC request
+    pub rpc: Duration,
+    /// Timeout for waiting for events and extrinsic status updates
+    pub events: Duration,
+    /// Timeout for fetching DID documents from content store
+    pub ipfs: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            rpc: DEFAULT_TIMEOUT,
+            events: DEFAULT_TIMEOUT,
+            ipfs: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// Retries of requests, that have failed with a transient error, e.g. a transport error or a
+/// timeout. Delay between retries starts with `initial_backoff` and is doubled after each retry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt, `0` disables retries
+    pub max_retries: u32,
+    /// Delay before first retry
+    pub initial_backoff: Duration,
+    /// Upper limit for delay between retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Runs `action` until it succeeds, fails with a non transient error or `max_retries` has been
+    /// reached.
+    ///
+    /// # Arguments
+    /// * `description` - description of action for logs, e.g. `fetching metadata`
+    /// * `action` - creates a future for each attempt
+    pub async fn retry<T, F, Fut>(
+        &self,
+        description: &str,
+        mut action: F,
+    ) -> Result<T, Box<dyn Error>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Box<dyn Error>>>,
+    {
+        let mut backoff = self.initial_backoff;
+        let mut retries = 0;
+        loop {
+            match action().await {
+                Err(err) if retries < self.max_retries && is_transient(&*err) => {
+                    warn!(
+                        "{} failed, retrying in {}ms; {}",
+                        description,
+                        backoff.as_millis(),
+                        &err
+                    );
+                    Delay::new(backoff).await;
+                    backoff = (backoff * 2).min(self.max_backoff);
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Checks if an error may not occur again when retrying, e.g. for lost connections.
+fn is_transient(err: &(dyn Error + 'static)) -> bool {
+    matches!(
+        err.downcast_ref::<SubstrateError>(),
+        Some(SubstrateError::Transport(_)) | Some(SubstrateError::Timeout(_))
+    )
+}
+
+/// Waits for `future`, but returns a `Timeout` error if it does not complete within `duration`.
+///
+/// # Arguments
+/// * `duration` - maximum time to wait for `future`
+/// * `description` - description of what is waited for, e.g. `waiting for extrinsic status`
+/// * `future` - future to wait for
+pub async fn timeout<F: Future>(
+    duration: Duration,
+    description: &str,
+    future: F,
+) -> Result<F::Output, SubstrateError> {
+    match future::select(Box::pin(future), Delay::new(duration)).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => {
+            error!(
+                "substrate timeout while {}, after; {}ms",
+                description,
+                duration.as_millis()
+            );
+            Err(SubstrateError::Timeout(description.to_string()))
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn get_retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_timeout_error_for_pending_futures() {
+        let result = timeout(
+            Duration::from_millis(10),
+            "waiting for nothing",
+            future::pending::<()>(),
+        )
+        .await;
+        assert!(matches!(result, Err(SubstrateError::Timeout(_))));
+        assert_eq!(
+            timeout(Duration::from_secs(1), "waiting", future::ready(1))
+                .await
+                .ok(),
+            Some(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        let attempts = Cell::new(0);
+        let result: Result<(), Box<dyn Error>> = get_retry_policy()
+            .retry("testing", || async {
+                attempts.set(attempts.get() + 1);
+                Err(Box::from(SubstrateError::Transport("closed".to_string())))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 3);
+
+        attempts.set(0);
+        let result = get_retry_policy()
+            .retry("testing", || async {
+                attempts.set(attempts.get() + 1);
+                match attempts.get() {
+                    1 => Err(Box::from(SubstrateError::Timeout("request".to_string()))),
+                    _ => Ok(attempts.get()),
+                }
+            })
+            .await;
+        assert_eq!(result.ok(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_other_errors() {
+        let attempts = Cell::new(0);
+        let result: Result<(), Box<dyn Error>> = get_retry_policy()
+            .retry("testing", || async {
+                attempts.set(attempts.get() + 1);
+                Err(Box::from(SubstrateError::Rpc("invalid params".to_string())))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 1);
+    }
+}
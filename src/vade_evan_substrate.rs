@@ -19,16 +19,50 @@ extern crate vade;
 
 use crate::utils::substrate::{
     add_payload_to_did,
+    compose_add_payload,
+    compose_did_creation,
+    compose_update_payload,
+    compose_whitelist_identity,
     create_did,
+    create_dids,
+    get_block_hash_at_time,
     get_did,
+    get_did_module_events,
+    get_dids,
+    get_events,
+    get_payload_blocks,
     get_payload_count_for_did,
+    get_payload_of_did,
+    get_payloads_of_did,
+    get_transaction_status,
     is_whitelisted,
+    send_batch,
+    submit_extrinsic,
     update_payload_in_did,
+    watch_did,
     whitelist_identity,
+    DidCall,
+    SubstrateClient,
+};
+pub use crate::utils::{
+    content_store::{ContentStore, IpfsApi, IpfsGateway},
+    did_events::{Created, DidModuleEvent, IdentityWhitelist, UpdatedDid},
+    errors::SubstrateError,
+    extrinsic::{
+        events::{EventRecord, Phase, RawEvent, SystemEvent},
+        keypair::ExtrinsicSigner,
+        rpc::client::XtStatus,
+    },
+    substrate::{DidChange, SubstrateEndpoints, TransactionStatus},
+    timeouts::{RetryPolicy, Timeouts},
 };
 use async_trait::async_trait;
+use futures::stream::{Stream, StreamExt};
+use parity_scale_codec::Decode;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+pub use sp_core::{ecdsa, ed25519, sr25519, Pair};
 use std::error::Error;
 use vade::{VadePlugin, VadePluginResultValue};
 use vade_signer::Signer;
@@ -37,6 +71,10 @@ const EVAN_METHOD: &str = "did:evan";
 const EVAN_METHOD_PREFIX: &str = "did:evan:";
 const DID_SUBSTRATE: &str = "substrate";
 const METHOD_REGEX: &str = r#"^(.*):0x(.*)$"#;
+const DID_RESOLUTION_CONTEXT: &str = "https://w3id.org/did-resolution/v1";
+const DID_DOCUMENT_CONTENT_TYPE: &str = "application/did+ld+json";
+/// `type` of tombstones, see [`DidTombstone`](DidTombstone)
+const DID_TOMBSTONE_TYPE: &str = "vade-evan-substrate/DidTombstone";
 
 macro_rules! parse {
     ($data:expr, $type_name:expr) => {{
@@ -61,6 +99,82 @@ pub struct DidUpdateArguments {
     pub private_key: String,
     pub identity: String,
     pub operation: String,
+    /// How `setDidDocument` stores the new document, defaults to `overwrite`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_mode: Option<DidUpdateMode>,
+    /// Status of extrinsic to wait for, e.g. `finalized`, defaults to `inBlock`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for: Option<XtStatus>,
+    /// Submit extrinsic without waiting for it and return a
+    /// [`SubmittedTransaction`](SubmittedTransaction) instead
+    #[serde(default)]
+    pub async_submit: bool,
+}
+
+/// Ways to store a new DID document with `setDidDocument`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DidUpdateMode {
+    /// Replaces the current document, which is the one with the highest index
+    Overwrite,
+    /// Adds the document as a new version and keeps all previous versions
+    Append,
+}
+
+/// Payload appended to a DID by the `deactivateDid` operation. `DidModule` has no call to deactivate
+/// DIDs, so deactivation is a convention of this plugin: a DID is deactivated if its latest payload
+/// is a tombstone of this DID. Resolvers, that do not follow this convention, return the tombstone
+/// as DID document.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DidTombstone {
+    /// always `DID_TOMBSTONE_TYPE`
+    #[serde(rename = "type")]
+    tombstone_type: String,
+    /// deactivated substrate DID, e.g. `0x1234`
+    did: String,
+}
+
+impl DidTombstone {
+    /// Creates the tombstone for substrate DID `did`.
+    fn new(did: &str) -> DidTombstone {
+        DidTombstone {
+            tombstone_type: DID_TOMBSTONE_TYPE.to_string(),
+            did: format!("0x{}", did.trim_start_matches("0x").to_lowercase()),
+        }
+    }
+
+    /// Checks if `payload` is the tombstone of substrate DID `did`. Payloads, that only look
+    /// similar, e.g. with additional properties, are no tombstones.
+    fn is_tombstone_of(payload: &str, did: &str) -> bool {
+        match serde_json::from_str::<DidTombstone>(payload) {
+            Ok(tombstone) => tombstone == DidTombstone::new(did),
+            Err(_) => false,
+        }
+    }
+}
+
+/// DID operation, that can be sent together with other operations in one extrinsic, see
+/// [`batch`](VadeEvanSubstrate::batch).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "operation")]
+pub enum BatchOperation {
+    /// Whitelists `identity`, like the `whitelistIdentity` operation of `did_update`
+    #[serde(rename_all = "camelCase")]
+    WhitelistIdentity { identity: String },
+    /// Creates a DID for `identity`, with `document` as its first DID document
+    #[serde(rename_all = "camelCase")]
+    CreateDid {
+        identity: String,
+        document: Option<String>,
+    },
+    /// Adds `document` as new version of the DID document of `did`
+    #[serde(rename_all = "camelCase")]
+    AddDidDocument {
+        did: String,
+        identity: String,
+        document: String,
+    },
 }
 
 #[derive(Serialize, Deserialize)]
@@ -68,11 +182,118 @@ pub struct DidUpdateArguments {
 pub struct IdentityArguments {
     pub private_key: String,
     pub identity: String,
+    /// Status of extrinsic to wait for, e.g. `finalized`, defaults to `inBlock`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for: Option<XtStatus>,
+    /// Submit extrinsic without waiting for it and return a
+    /// [`SubmittedTransaction`](SubmittedTransaction) instead
+    #[serde(default)]
+    pub async_submit: bool,
+}
+
+/// How the extrinsic of a DID operation is submitted, taken from `waitFor` and `asyncSubmit`
+/// options.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Submission {
+    /// Waits until the extrinsic has reached given status
+    Wait(XtStatus),
+    /// Submits the extrinsic without waiting for it, see
+    /// [`SubmittedTransaction`](SubmittedTransaction)
+    Async,
+}
+
+impl Submission {
+    /// Gets the submission for `waitFor` and `asyncSubmit` options, `asyncSubmit` takes precedence
+    /// and extrinsics are waited for until `InBlock` by default.
+    fn from_options(wait_for: Option<XtStatus>, async_submit: bool) -> Submission {
+        match async_submit {
+            true => Submission::Async,
+            false => Submission::Wait(wait_for.unwrap_or(XtStatus::InBlock)),
+        }
+    }
+}
+
+/// Handle of an extrinsic submitted with `asyncSubmit`, its status can be checked with
+/// [`get_transaction_status`](VadeEvanSubstrate::get_transaction_status).
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmittedTransaction {
+    pub transaction_hash: String,
 }
 
 pub struct ResolverConfig {
     pub signer: Box<dyn Signer>,
+    /// Substrate host, shorthand for endpoints with default layout, see
+    /// [`SubstrateEndpoints::from_target`](SubstrateEndpoints::from_target)
     pub target: String,
+    /// Endpoints to use instead of the ones derived from `target`
+    pub endpoints: Option<SubstrateEndpoints>,
+    /// Store to fetch DID documents from, defaults to IPFS gateway of endpoints
+    pub content_store: Option<Box<dyn ContentStore>>,
+    /// Substrate account to sign extrinsics with, e.g. an `sr25519::Pair`, extrinsics are sent
+    /// unsigned if omitted
+    pub extrinsic_signer: Option<Box<dyn ExtrinsicSigner>>,
+    /// Timeouts for RPC requests, event waits and content fetches, defaults to 60s each
+    pub timeouts: Option<Timeouts>,
+    /// Retries of storage and metadata requests, that have failed with transient errors
+    pub retry_policy: Option<RetryPolicy>,
+    /// Return a [`DidResolutionResult`](DidResolutionResult) from `did_resolve` instead of the
+    /// plain DID document
+    pub resolution_result: bool,
+    /// Add the blocks, in which a DID has been created and updated, to the metadata of DID
+    /// Resolution Results, see [`get_payload_blocks`](crate::utils::substrate::get_payload_blocks)
+    pub block_metadata: bool,
+}
+
+/// Result of resolving a DID as described in the W3C DID Core specification.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidResolutionResult {
+    #[serde(rename = "@context")]
+    pub context: String,
+    pub did_document: Option<Value>,
+    pub did_document_metadata: DidDocumentMetadata,
+    pub did_resolution_metadata: DidResolutionMetadata,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocumentMetadata {
+    /// Number of block, in which the DID has been created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_block: Option<u64>,
+    /// Number of block, in which the resolved document has been set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_block: Option<u64>,
+    /// Index of resolved document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deactivated: Option<bool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidResolutionMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Error code, e.g. `notFound` or `invalidDid`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DidResolutionResult {
+    fn from_error(error: &str) -> DidResolutionResult {
+        DidResolutionResult {
+            context: DID_RESOLUTION_CONTEXT.to_string(),
+            did_document: None,
+            did_document_metadata: DidDocumentMetadata::default(),
+            did_resolution_metadata: DidResolutionMetadata {
+                content_type: None,
+                error: Some(error.to_string()),
+            },
+        }
+    }
 }
 
 /// Message passed to vade containing the desired did implementation.
@@ -85,15 +306,52 @@ pub struct TypeOptions {
 /// Resolver for DIDs on the Trust&Trace substrate chain
 pub struct VadeEvanSubstrate {
     config: ResolverConfig,
+    client: SubstrateClient,
 }
 
 impl VadeEvanSubstrate {
     /// Creates new instance of `VadeEvanSubstrate`.
-    pub fn new(config: ResolverConfig) -> VadeEvanSubstrate {
+    pub fn new(mut config: ResolverConfig) -> VadeEvanSubstrate {
         match env_logger::try_init() {
             Ok(_) | Err(_) => (),
         };
-        VadeEvanSubstrate { config }
+        let endpoints = config
+            .endpoints
+            .clone()
+            .unwrap_or_else(|| SubstrateEndpoints::from_target(&config.target));
+        let mut client = SubstrateClient::new(endpoints);
+        if let Some(content_store) = config.content_store.take() {
+            client.content_store = content_store;
+        }
+        client.extrinsic_signer = config.extrinsic_signer.take();
+        client.timeouts = config.timeouts.unwrap_or_default();
+        client.retry_policy = config.retry_policy.unwrap_or_default();
+        VadeEvanSubstrate { config, client }
+    }
+
+    /// Registers a custom type for decoding event arguments, e.g. a struct of a custom runtime
+    /// module. Values are decoded with the `Decode` implementation of `U` and converted to JSON
+    /// with its `Serialize` implementation.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - type name as used in runtime metadata, without path, e.g. `MyStruct`
+    pub fn register_event_type<U: Decode + Serialize>(&mut self, name: &str) {
+        self.client.event_types.register::<U>(name);
+    }
+
+    /// Registers a type name for decoding event arguments, that is decoded like another type.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - type name as used in runtime metadata, without path, e.g. `Amount`
+    /// * `definition` - type to decode it as, e.g. `u128` or `(u32, Option<[u8; 32]>)`
+    pub fn register_event_type_alias(
+        &mut self,
+        name: &str,
+        definition: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(self.client.event_types.register_alias(name, definition)?)
     }
 
     async fn set_did_document(
@@ -102,36 +360,128 @@ impl VadeEvanSubstrate {
         private_key: &str,
         identity: &str,
         payload: &str,
+        update_mode: DidUpdateMode,
+        submission: Submission,
     ) -> Result<Option<String>, Box<dyn Error>> {
         debug!(
-            "setting DID document for did: {}, identity; {}",
-            &did, &identity
+            "setting DID document for did: {}, identity; {}, mode: {:?}",
+            &did, &identity, &update_mode
         );
         let payload_count: u32 =
-            get_payload_count_for_did(self.config.target.clone(), did.to_string()).await?;
-        if payload_count > 0 {
+            get_payload_count_for_did(&self.client, did.to_string(), None).await?;
+        if self.is_deactivated(did, payload_count, None).await? {
+            return Err(Box::new(SubstrateError::DidDeactivated(did.to_string())));
+        }
+        // the document with the highest index is the current one
+        let overwrite = payload_count > 0 && update_mode == DidUpdateMode::Overwrite;
+        let exit_on = match submission {
+            Submission::Wait(exit_on) => exit_on,
+            Submission::Async => {
+                let metadata = self.client.get_metadata().await?;
+                let (_, xt) = match overwrite {
+                    true => {
+                        compose_update_payload(
+                            &self.client,
+                            &metadata,
+                            payload_count - 1,
+                            payload,
+                            did,
+                            private_key,
+                            &self.config.signer,
+                            &hex::decode(identity)?,
+                        )
+                        .await?
+                    }
+                    false => {
+                        compose_add_payload(
+                            &self.client,
+                            &metadata,
+                            payload,
+                            did.trim_start_matches("0x"),
+                            private_key,
+                            &self.config.signer,
+                            &hex::decode(identity)?,
+                        )
+                        .await?
+                    }
+                };
+                return Ok(Some(self.submit(&xt)?));
+            }
+        };
+        if overwrite {
             update_payload_in_did(
-                self.config.target.clone(),
-                0 as u32,
+                &self.client,
+                payload_count - 1,
                 payload.to_string(),
                 did.to_string(),
                 private_key.to_string(),
                 &self.config.signer,
                 hex::decode(identity)?,
+                exit_on,
             )
             .await?;
         } else {
             add_payload_to_did(
-                self.config.target.clone(),
+                &self.client,
                 payload.to_string(),
                 did.to_string(),
                 private_key.to_string(),
                 &self.config.signer,
                 hex::decode(identity)?,
+                exit_on,
             )
             .await?;
         }
-        Ok(Some("".to_string()))
+        Ok(None)
+    }
+
+    /// Checks if a DID has been deactivated, i.e. if its latest payload is a
+    /// [`DidTombstone`](DidTombstone).
+    ///
+    /// # Arguments
+    ///
+    /// * `did` - substrate DID to check
+    /// * `payload_count` - number of payloads of `did` at `at`
+    /// * `at` - hash of block to check DID at, checks at latest block if omitted
+    async fn is_deactivated(
+        &self,
+        did: &str,
+        payload_count: u32,
+        at: Option<&str>,
+    ) -> Result<bool, Box<dyn Error>> {
+        if payload_count == 0 {
+            return Ok(false);
+        }
+        let latest =
+            get_payload_of_did(&self.client, did.to_string(), payload_count - 1, at).await?;
+        Ok(DidTombstone::is_tombstone_of(&latest, did))
+    }
+
+    /// Submits an extrinsic without waiting for it.
+    ///
+    /// # Returns
+    ///
+    /// Serialized [`SubmittedTransaction`](SubmittedTransaction)
+    fn submit(&self, xthex_prefixed: &str) -> Result<String, Box<dyn Error>> {
+        let transaction_hash = submit_extrinsic(&self.client, xthex_prefixed)?;
+        Ok(serde_json::to_string(&SubmittedTransaction {
+            transaction_hash,
+        })?)
+    }
+
+    /// Gets the status of an extrinsic submitted with `asyncSubmit`. Extrinsics, that have been
+    /// included in a block, are reported as `failed` if their dispatch has failed. Extrinsics are
+    /// not tracked anymore once their final status has been returned or 10 minutes after their
+    /// submission.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - `transactionHash` of [`SubmittedTransaction`](SubmittedTransaction)
+    pub async fn get_transaction_status(
+        &self,
+        hash: &str,
+    ) -> Result<TransactionStatus, Box<dyn Error>> {
+        get_transaction_status(&self.client, hash).await
     }
 
     pub async fn is_whitelisted(
@@ -142,7 +492,7 @@ impl VadeEvanSubstrate {
         let (_, substrate_identity) = convert_did_to_substrate_identity(&did)?;
         let substrate_identity_vec = hex::decode(&substrate_identity)?;
         let result = is_whitelisted(
-            self.config.target.clone(),
+            &self.client,
             private_key.to_owned(),
             &self.config.signer,
             substrate_identity_vec,
@@ -150,6 +500,361 @@ impl VadeEvanSubstrate {
         .await?;
         Ok(result)
     }
+
+    /// Resolves a DID to a W3C DID Resolution Result. Supports the same parameters as
+    /// [`did_resolve`](VadeEvanSubstrate::did_resolve). Blocks in metadata are only added if
+    /// `block_metadata` is set in [`ResolverConfig`](ResolverConfig) and are omitted if unknown.
+    ///
+    /// # Arguments
+    ///
+    /// * `did` - DID to resolve, e.g. `did:evan:0x1234?versionId=1`
+    pub async fn resolve_did(&self, did: &str) -> Result<DidResolutionResult, Box<dyn Error>> {
+        let did_url = match parse_did_url(did) {
+            Ok(did_url) => did_url,
+            Err(_) => return Ok(DidResolutionResult::from_error("invalidDid")),
+        };
+        let substrate_identity = match convert_did_to_substrate_identity(did_url.did) {
+            Ok((_, substrate_identity)) => substrate_identity,
+            Err(_) => return Ok(DidResolutionResult::from_error("invalidDid")),
+        };
+        let at = self.get_block_for_did_url(&did_url).await?;
+        let at = at.as_deref();
+        let latest =
+            match get_payload_count_for_did(&self.client, substrate_identity.clone(), at).await? {
+                0 => return Ok(DidResolutionResult::from_error("notFound")),
+                payload_count => payload_count - 1,
+            };
+        let index = did_url.version_id.unwrap_or(latest);
+        let document =
+            match get_payload_of_did(&self.client, substrate_identity.clone(), index, at).await {
+                Ok(document) => document,
+                Err(err) => match err.downcast_ref::<SubstrateError>() {
+                    Some(SubstrateError::DidNotFound(_)) => {
+                        return Ok(DidResolutionResult::from_error("notFound"))
+                    }
+                    _ => return Err(err),
+                },
+            };
+        let deactivated = match index == latest {
+            true => DidTombstone::is_tombstone_of(&document, &substrate_identity),
+            false => {
+                self.is_deactivated(&substrate_identity, latest + 1, at)
+                    .await?
+            }
+        };
+        // deactivated DIDs have no current document, but their versions can still be resolved
+        let did_document: Option<Value> =
+            if DidTombstone::is_tombstone_of(&document, &substrate_identity)
+                || (deactivated && did_url.version_id.is_none())
+            {
+                None
+            } else {
+                match serde_json::from_str(&document) {
+                    Ok(did_document) => Some(did_document),
+                    Err(_) => return Ok(DidResolutionResult::from_error("invalidDidDocument")),
+                }
+            };
+        let (created_block, updated_block) = if self.config.block_metadata {
+            match get_payload_blocks(&self.client, substrate_identity, index, at).await {
+                Ok(blocks) => blocks,
+                Err(err) => {
+                    warn!("could not get blocks of DID {}; {}", did, &err);
+                    (None, None)
+                }
+            }
+        } else {
+            (None, None)
+        };
+
+        Ok(DidResolutionResult {
+            context: DID_RESOLUTION_CONTEXT.to_string(),
+            did_document,
+            did_document_metadata: DidDocumentMetadata {
+                created_block,
+                updated_block,
+                version_id: Some(index.to_string()),
+                deactivated: Some(deactivated),
+            },
+            did_resolution_metadata: DidResolutionMetadata {
+                content_type: Some(DID_DOCUMENT_CONTENT_TYPE.to_string()),
+                error: None,
+            },
+        })
+    }
+
+    /// Gets hash of block to resolve a DID URL at.
+    async fn get_block_for_did_url(
+        &self,
+        did_url: &DidUrl<'_>,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(match (did_url.block_hash, did_url.version_time) {
+            (Some(block_hash), _) => Some(block_hash.to_string()),
+            (None, Some(version_time)) => {
+                Some(get_block_hash_at_time(&self.client, version_time).await?)
+            }
+            (None, None) => None,
+        })
+    }
+
+    /// Sends multiple DID operations in one extrinsic, e.g. to whitelist an identity and to create
+    /// DIDs for it in one step.
+    ///
+    /// With `atomic` set, either all operations or none of them are applied and a failing
+    /// operation fails the whole batch. Otherwise operations are applied until the first one fails.
+    ///
+    /// Batches are sent as signed extrinsics, so `extrinsic_signer` has to be set in
+    /// [`ResolverConfig`](ResolverConfig), otherwise `SubstrateError::Signer` is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `private_key` - key reference to sign operations with
+    /// * `operations` - operations to apply, in given order
+    /// * `atomic` - apply either all operations or none of them
+    ///
+    /// # Returns
+    ///
+    /// Result per operation, created DID for `CreateDid` and `None` for other operations, errors
+    /// for the failed operation and all operations after it
+    pub async fn batch(
+        &self,
+        private_key: &str,
+        operations: &[BatchOperation],
+        atomic: bool,
+    ) -> Result<Vec<Result<Option<String>, SubstrateError>>, Box<dyn Error>> {
+        let mut calls = Vec::new();
+        let mut did_prefixes = Vec::new();
+        for operation in operations {
+            let (call, did_prefix) = match operation {
+                BatchOperation::WhitelistIdentity { identity } => {
+                    let (method, substrate_identity) = convert_did_to_substrate_identity(identity)?;
+                    let call = DidCall::WhitelistIdentity {
+                        method,
+                        identity: hex::decode(&substrate_identity)?,
+                    };
+                    (call, None)
+                }
+                BatchOperation::CreateDid { identity, document } => {
+                    if let Some(document) = document {
+                        check_did_document(document)?;
+                    }
+                    let (_, substrate_identity) = convert_did_to_substrate_identity(identity)?;
+                    let call = DidCall::CreateDid {
+                        identity: hex::decode(&substrate_identity)?,
+                        payload: document.clone(),
+                    };
+                    // created DIDs use the method of their identity, e.g. `did:evan:testcore`
+                    let did_prefix = identity
+                        .rsplit_once(':')
+                        .map(|(prefix, _)| prefix.to_string());
+                    (call, did_prefix)
+                }
+                BatchOperation::AddDidDocument {
+                    did,
+                    identity,
+                    document,
+                } => {
+                    check_did_document(document)?;
+                    let (_, substrate_did) = convert_did_to_substrate_identity(did)?;
+                    let (_, substrate_identity) = convert_did_to_substrate_identity(identity)?;
+                    let call = DidCall::AddPayload {
+                        did: substrate_did,
+                        identity: hex::decode(&substrate_identity)?,
+                        payload: document.clone(),
+                    };
+                    (call, None)
+                }
+            };
+            calls.push(call);
+            did_prefixes.push(did_prefix);
+        }
+        let results = send_batch(
+            &self.client,
+            private_key.to_string(),
+            &self.config.signer,
+            calls,
+            atomic,
+        )
+        .await?;
+        Ok(results
+            .into_iter()
+            .zip(did_prefixes)
+            .map(|(result, did_prefix)| {
+                result.map(|did| match (did, did_prefix) {
+                    (Some(did), Some(did_prefix)) => Some(format!("{}:{}", did_prefix, did)),
+                    (did, _) => did,
+                })
+            })
+            .collect())
+    }
+
+    /// Creates multiple DIDs for `identity`. DIDs are created concurrently with up to `concurrency`
+    /// pending creations at a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `private_key` - key reference to sign creations with
+    /// * `identity` - identity requesting the DIDs, e.g. `did:evan:testcore:0x...`
+    /// * `count` - number of DIDs to create
+    /// * `payloads` - optional DID documents, one per DID to create
+    /// * `concurrency` - maximum number of DID creations, that are submitted at the same time
+    ///
+    /// # Returns
+    ///
+    /// Created DID or error per DID, ordered like `payloads`
+    pub async fn create_dids(
+        &self,
+        private_key: &str,
+        identity: &str,
+        count: usize,
+        payloads: Option<&[String]>,
+        concurrency: usize,
+    ) -> Result<Vec<Result<String, SubstrateError>>, Box<dyn Error>> {
+        let payloads: Vec<Option<String>> = match payloads {
+            Some(payloads) if payloads.len() != count => {
+                return Err(Box::from(format!(
+                    "expected {} payloads, got {}",
+                    count,
+                    payloads.len()
+                )))
+            }
+            Some(payloads) => {
+                for payload in payloads {
+                    check_did_document(payload)?;
+                }
+                payloads.iter().cloned().map(Some).collect()
+            }
+            None => vec![None; count],
+        };
+        let (_, substrate_identity) = convert_did_to_substrate_identity(identity)?;
+        // created DIDs use the method of their identity, e.g. `did:evan:testcore`
+        let did_prefix = identity
+            .rsplit_once(':')
+            .map(|(prefix, _)| prefix)
+            .ok_or_else(|| format!("could not parse identity; {}", identity))?;
+        let results = create_dids(
+            &self.client,
+            private_key.to_string(),
+            &self.config.signer,
+            hex::decode(&substrate_identity)?,
+            payloads,
+            concurrency,
+        )
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|result| result.map(|did| format!("{}:{}", did_prefix, did)))
+            .collect())
+    }
+
+    /// Lists all DIDs, that have at least one DID document, by iterating over the DID storage of
+    /// the latest block.
+    ///
+    /// # Arguments
+    ///
+    /// * `did_method` - DID method to prefix DIDs with, e.g. `did:evan:testcore`
+    pub async fn get_dids(&self, did_method: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(get_dids(&self.client, None)
+            .await?
+            .into_iter()
+            .map(|did| format!("{}:{}", did_method, did))
+            .collect())
+    }
+
+    /// Watches `did` for changes, that are included in new blocks, e.g. to update cached DID
+    /// documents without polling [`did_resolve`](VadeEvanSubstrate::did_resolve). The stream ends
+    /// if the connection to the node is closed. Errors are returned for blocks, whose changes could
+    /// not be decoded completely, cached documents should be considered outdated then.
+    ///
+    /// # Arguments
+    ///
+    /// * `did` - DID to watch, e.g. `did:evan:0x1234`
+    pub async fn watch_did(
+        &self,
+        did: &str,
+    ) -> Result<impl Stream<Item = Result<DidChange, SubstrateError>>, Box<dyn Error>> {
+        let (_, substrate_identity) = convert_did_to_substrate_identity(did)?;
+        let did_method = did
+            .trim_end_matches(&substrate_identity)
+            .trim_end_matches(":0x");
+        self.watch(did_method, Some(substrate_identity)).await
+    }
+
+    /// Watches all DIDs for changes, that are included in new blocks. DIDs in changes are prefixed
+    /// with `did_method`.
+    ///
+    /// # Arguments
+    ///
+    /// * `did_method` - DID method to prefix DIDs with, e.g. `did:evan:testcore`
+    pub async fn watch_dids(
+        &self,
+        did_method: &str,
+    ) -> Result<impl Stream<Item = Result<DidChange, SubstrateError>>, Box<dyn Error>> {
+        self.watch(did_method, None).await
+    }
+
+    async fn watch(
+        &self,
+        did_method: &str,
+        substrate_identity: Option<String>,
+    ) -> Result<impl Stream<Item = Result<DidChange, SubstrateError>>, Box<dyn Error>> {
+        let did_method = did_method.to_string();
+        Ok(watch_did(&self.client, substrate_identity)
+            .await?
+            .map(move |change| {
+                change.map(|change| match change {
+                    DidChange::Created {
+                        did,
+                        owner,
+                        block_hash,
+                    } => DidChange::Created {
+                        did: format!("{}:{}", &did_method, &did),
+                        owner,
+                        block_hash,
+                    },
+                    DidChange::Updated {
+                        did,
+                        index,
+                        block_hash,
+                    } => DidChange::Updated {
+                        did: format!("{}:{}", &did_method, &did),
+                        index,
+                        block_hash,
+                    },
+                })
+            }))
+    }
+
+    /// Gets all `DidModule` events of a block, ordered like they have been emitted.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_hash` - hash of block to get events of
+    pub async fn get_did_events(
+        &self,
+        block_hash: &str,
+    ) -> Result<Vec<DidModuleEvent>, Box<dyn Error>> {
+        get_did_module_events(&self.client, block_hash).await
+    }
+
+    /// Gets all events of a block with the phase they have been emitted in and their topics,
+    /// ordered like they have been emitted.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_hash` - hash of block to get events of
+    pub async fn get_events(&self, block_hash: &str) -> Result<Vec<EventRecord>, Box<dyn Error>> {
+        get_events(&self.client, block_hash).await
+    }
+
+    /// Gets all DID documents, that have been stored for `did`, ordered by their `versionId`.
+    ///
+    /// # Arguments
+    ///
+    /// * `did` - DID to fetch documents for
+    pub async fn get_did_documents(&self, did: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let (_, substrate_identity) = convert_did_to_substrate_identity(did)?;
+        get_payloads_of_did(&self.client, substrate_identity, None).await
+    }
 }
 
 #[async_trait(?Send)]
@@ -162,6 +867,11 @@ impl VadePlugin for VadeEvanSubstrate {
     /// * `options` - serialized [`IdentityArguments`](https://docs.rs/vade_evan_substrate/*/vade_evan_substrate/vade_evan_substrate/struct.IdentityArguments.html)
     /// * `payload` - no payload required, so can be left empty
     ///
+    /// With `options.asyncSubmit` set, the extrinsic is submitted without waiting for it and a
+    /// serialized [`SubmittedTransaction`](SubmittedTransaction) is returned instead of the DID.
+    /// With `options.waitFor` set to `ready` or `broadcast`, no DID is returned, as it is only known
+    /// once the extrinsic has been included in a block.
+    ///
     async fn did_create(
         &mut self,
         did_method: &str,
@@ -182,22 +892,39 @@ impl VadePlugin for VadeEvanSubstrate {
                     &options.identity, &err
                 )
             })?;
+        check_did_document(payload)?;
+        let payload = match payload {
+            "" => None,
+            _ => Some(payload),
+        };
+        let exit_on = match Submission::from_options(options.wait_for, options.async_submit) {
+            Submission::Wait(exit_on) => exit_on,
+            Submission::Async => {
+                let (_, xt) = compose_did_creation(
+                    &self.client,
+                    &self.client.get_metadata().await?,
+                    &options.private_key,
+                    &self.config.signer,
+                    &hex::decode(&substrate_identity)?,
+                    payload,
+                )
+                .await?;
+                return Ok(VadePluginResultValue::Success(Some(self.submit(&xt)?)));
+            }
+        };
         let inner_result = create_did(
-            self.config.target.clone(),
+            &self.client,
             options.private_key.clone(),
             &self.config.signer,
             hex::decode(&substrate_identity)?,
-            match payload {
-                "" => None,
-                _ => Some(payload),
-            },
+            payload,
+            exit_on,
         )
         .await?;
 
-        Ok(VadePluginResultValue::Success(Some(format!(
-            "\"{}:{}\"",
-            &did_method, &inner_result
-        ))))
+        Ok(VadePluginResultValue::Success(inner_result.map(
+            |inner_result| format!("\"{}:{}\"", &did_method, &inner_result),
+        )))
     }
 
     /// Updates data related to a DID. Two updates are supported depending on the value of
@@ -205,7 +932,19 @@ impl VadePlugin for VadeEvanSubstrate {
     ///
     /// - whitelistIdentity: whitelists identity `did` on substrate, this is required to be able to
     ///   perform transactions this this identity
-    /// - setDidDocument: sets the DID document for `did`
+    /// - setDidDocument: sets the DID document for `did`, by default it overwrites the current
+    ///   document, which is the one with the highest index (payload count - 1),
+    ///   `options.updateMode` `append` keeps previous documents as older versions and adds the new
+    ///   one at the next index
+    /// - deactivateDid: deactivates `did`, its documents cannot be updated or resolved afterwards,
+    ///   but previous versions can still be resolved by their `versionId`. `DidModule` has no
+    ///   deactivation call, so a tombstone document is appended as latest version, see the README
+    ///
+    /// `options.waitFor` sets the status of the extrinsic to wait for, e.g. `finalized` for updates,
+    /// that must not be reverted anymore, defaults to `inBlock`. For `ready` and `broadcast`, the
+    /// extrinsic has not been dispatched yet, so its result is not checked. With `options.asyncSubmit` set, the
+    /// extrinsic is submitted without waiting for it and a serialized
+    /// [`SubmittedTransaction`](SubmittedTransaction) is returned.
     ///
     /// # Arguments
     ///
@@ -228,12 +967,13 @@ impl VadePlugin for VadeEvanSubstrate {
             .map_err(|e| format!("{} when parsing {}", &e, &options))?;
         let (method, substrate_identity) = convert_did_to_substrate_identity(&did)?;
         let substrate_identity_vec = hex::decode(&substrate_identity)?;
+        let submission = Submission::from_options(input.wait_for, input.async_submit);
 
         match input.operation.as_str() {
             "ensureWhitelisted" => {
                 // Check if identity is whitelisted
                 let is_whitelisted = is_whitelisted(
-                    self.config.target.clone(),
+                    &self.client,
                     input.private_key.clone(),
                     &self.config.signer,
                     substrate_identity_vec,
@@ -250,30 +990,65 @@ impl VadePlugin for VadeEvanSubstrate {
                     Ok(VadePluginResultValue::Success(None))
                 }
             }
-            "whitelistIdentity" => {
-                whitelist_identity(
-                    self.config.target.clone(),
-                    input.private_key.clone(),
-                    &self.config.signer,
-                    method,
-                    substrate_identity_vec,
-                )
-                .await?;
-                Ok(VadePluginResultValue::Success(None))
-            }
+            "whitelistIdentity" => match submission {
+                Submission::Wait(exit_on) => {
+                    whitelist_identity(
+                        &self.client,
+                        input.private_key.clone(),
+                        &self.config.signer,
+                        method,
+                        substrate_identity_vec,
+                        exit_on,
+                    )
+                    .await?;
+                    Ok(VadePluginResultValue::Success(None))
+                }
+                Submission::Async => {
+                    let (_, xt) = compose_whitelist_identity(
+                        &self.client,
+                        &self.client.get_metadata().await?,
+                        &input.private_key,
+                        &self.config.signer,
+                        method,
+                        &substrate_identity_vec,
+                    )
+                    .await?;
+                    Ok(VadePluginResultValue::Success(Some(self.submit(&xt)?)))
+                }
+            },
             "setDidDocument" => {
                 if !did.starts_with(EVAN_METHOD) {
                     return Ok(VadePluginResultValue::Ignored);
                 }
+                check_did_document(payload)?;
                 let (_, executing_did) = convert_did_to_substrate_identity(&input.identity)?;
                 self.set_did_document(
                     &substrate_identity,
                     &input.private_key,
                     &executing_did,
                     payload,
+                    input.update_mode.unwrap_or(DidUpdateMode::Overwrite),
+                    submission,
                 )
-                .await?;
-                Ok(VadePluginResultValue::Success(None))
+                .await
+                .map(VadePluginResultValue::Success)
+            }
+            "deactivateDid" => {
+                if !did.starts_with(EVAN_METHOD) {
+                    return Ok(VadePluginResultValue::Ignored);
+                }
+                let (_, executing_did) = convert_did_to_substrate_identity(&input.identity)?;
+                let tombstone = serde_json::to_string(&DidTombstone::new(&substrate_identity))?;
+                self.set_did_document(
+                    &substrate_identity,
+                    &input.private_key,
+                    &executing_did,
+                    &tombstone,
+                    DidUpdateMode::Append,
+                    submission,
+                )
+                .await
+                .map(VadePluginResultValue::Success)
             }
             _ => Err(Box::from(format!(
                 "invalid did update operation \"{}\"",
@@ -284,6 +1059,14 @@ impl VadePlugin for VadeEvanSubstrate {
 
     /// Fetch data about a DID, which returns this DID's DID document.
     ///
+    /// A specific version of the document can be fetched by appending its index as `versionId`,
+    /// e.g. `did:evan:0x1234?versionId=1`. The document, that was valid at a past point in time,
+    /// can be fetched with a `versionTime` (e.g. `?versionTime=2021-05-10T17:00:00Z`) or with the
+    /// hash of a block (e.g. `?blockHash=0x1234`).
+    ///
+    /// Returns a serialized [`DidResolutionResult`](DidResolutionResult) instead of the DID document
+    /// if `resolution_result` is set in [`ResolverConfig`](ResolverConfig).
+    ///
     /// # Arguments
     ///
     /// * `did` - did to fetch data for
@@ -294,16 +1077,104 @@ impl VadePlugin for VadeEvanSubstrate {
         if !did_id.starts_with(EVAN_METHOD) {
             return Ok(VadePluginResultValue::Ignored);
         }
-        let identity_did = convert_did_to_substrate_identity(&did_id);
+        if self.config.resolution_result {
+            let result = self.resolve_did(did_id).await?;
+            return Ok(VadePluginResultValue::Success(Some(serde_json::to_string(
+                &result,
+            )?)));
+        }
+        let did_url = parse_did_url(did_id)?;
+        let identity_did = convert_did_to_substrate_identity(did_url.did);
         if identity_did.is_err() {
             return Ok(VadePluginResultValue::Ignored);
         }
         let (_, substrate_identity) = identity_did?;
-        let did_result = get_did(self.config.target.clone(), substrate_identity).await?;
+        let at = self.get_block_for_did_url(&did_url).await?;
+        let did_result = match did_url.version_id {
+            Some(version_id) => {
+                get_payload_of_did(
+                    &self.client,
+                    substrate_identity.clone(),
+                    version_id,
+                    at.as_deref(),
+                )
+                .await?
+            }
+            None => get_did(&self.client, substrate_identity.clone(), at.as_deref()).await?,
+        };
+        if DidTombstone::is_tombstone_of(&did_result, &substrate_identity) {
+            return Err(Box::new(SubstrateError::DidDeactivated(
+                did_url.did.to_string(),
+            )));
+        }
         Ok(VadePluginResultValue::Success(Some(did_result)))
     }
 }
 
+/// DID with parameters from its query.
+struct DidUrl<'a> {
+    did: &'a str,
+    /// index of DID document
+    version_id: Option<u32>,
+    /// time in milliseconds since unix epoch
+    version_time: Option<u64>,
+    block_hash: Option<&'a str>,
+}
+
+/// Splits a DID URL into DID and its parameters.
+///
+/// # Arguments
+///
+/// `did_url` - a DID, optionally with query, e.g. `did:evan:0x1234?versionId=1`
+fn parse_did_url(did_url: &str) -> Result<DidUrl<'_>, Box<dyn Error>> {
+    let (did, query) = match did_url.split_once('?') {
+        Some((did, query)) => (did, query),
+        None => (did_url, ""),
+    };
+    let mut parsed = DidUrl {
+        did,
+        version_id: None,
+        version_time: None,
+        block_hash: None,
+    };
+    for parameter in query.split('&') {
+        match parameter.split_once('=') {
+            Some(("versionId", value)) => {
+                parsed.version_id = Some(
+                    value
+                        .parse::<u32>()
+                        .map_err(|err| format!("invalid versionId in {}; {}", did_url, &err))?,
+                )
+            }
+            Some(("versionTime", value)) => {
+                parsed.version_time = Some(
+                    parse_version_time(value)
+                        .map_err(|err| format!("invalid versionTime in {}; {}", did_url, &err))?,
+                )
+            }
+            Some(("blockHash", value)) => parsed.block_hash = Some(value),
+            _ => (),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Parses an RFC 3339 timestamp, e.g. `2021-05-10T17:00:00Z`, to milliseconds since unix epoch.
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_version_time(value: &str) -> Result<u64, Box<dyn Error>> {
+    Ok(chrono::DateTime::parse_from_rfc3339(value)?.timestamp_millis() as u64)
+}
+
+/// Parses an RFC 3339 timestamp, e.g. `2021-05-10T17:00:00Z`, to milliseconds since unix epoch.
+#[cfg(target_arch = "wasm32")]
+fn parse_version_time(value: &str) -> Result<u64, Box<dyn Error>> {
+    let time = js_sys::Date::parse(value);
+    if time.is_nan() {
+        return Err(Box::from("not a valid date"));
+    }
+    Ok(time as u64)
+}
+
 /// Converts a DID to a substrate compatible method prefixed DID hex string.
 ///
 /// # Arguments
@@ -329,3 +1200,741 @@ fn convert_did_to_substrate_identity(did: &str) -> Result<(u8, String), Box<dyn
         Err(Box::from(format!("could not parse DID; {}", did)))
     }
 }
+
+/// Checks that a DID document is no [`DidTombstone`](DidTombstone), so DIDs can only be
+/// deactivated with the `deactivateDid` operation.
+fn check_did_document(document: &str) -> Result<(), Box<dyn Error>> {
+    if let Ok(Value::Object(document)) = serde_json::from_str(document) {
+        if document.get("type").and_then(Value::as_str) == Some(DID_TOMBSTONE_TYPE) {
+            return Err(Box::from(
+                "DID document is reserved for deactivation, use operation 'deactivateDid' instead",
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        mock_node::{MockNode, METADATA_V12, METADATA_V13, METADATA_V14},
+        substrate::{get_block_hash, get_storage_value},
+    };
+    use chrono::{SecondsFormat, TimeZone, Utc};
+    use vade_signer::LocalSigner;
+
+    const SIGNER_1_DID: &str = "did:evan:testcore:0x0d87204c3957d73b68ae28d0af961d3c72403906";
+    const SIGNER_1_PRIVATE_KEY: &str =
+        "dfcdcb6d5d09411ae9cbe1b0fd9751ba8803dd4b276d5bf9488ae4ede2669106";
+
+    /// Gets a resolver, that sends extrinsics signed with the key of the test identity.
+    fn get_resolver(node: &MockNode) -> VadeEvanSubstrate {
+        let pair = ecdsa::Pair::from_seed_slice(
+            &hex::decode(SIGNER_1_PRIVATE_KEY).expect("could not decode private key"),
+        )
+        .expect("could not create extrinsic signer");
+        VadeEvanSubstrate::new(ResolverConfig {
+            signer: Box::new(LocalSigner::new()),
+            target: "127.0.0.1".to_string(),
+            endpoints: Some(node.endpoints.clone()),
+            content_store: None,
+            extrinsic_signer: Some(Box::new(pair)),
+            timeouts: None,
+            retry_policy: None,
+            resolution_result: true,
+            block_metadata: true,
+        })
+    }
+
+    async fn create_did(resolver: &mut VadeEvanSubstrate, document: &str) -> String {
+        let options = serde_json::json!({
+            "type": DID_SUBSTRATE,
+            "privateKey": SIGNER_1_PRIVATE_KEY,
+            "identity": SIGNER_1_DID,
+        });
+        match resolver
+            .did_create("did:evan:testcore", &options.to_string(), document)
+            .await
+        {
+            Ok(VadePluginResultValue::Success(Some(did))) => {
+                serde_json::from_str(&did).expect("could not parse created DID")
+            }
+            _ => panic!("could not create DID"),
+        }
+    }
+
+    #[tokio::test]
+    async fn can_resolve_did_with_metadata() -> Result<(), Box<dyn Error>> {
+        let node = MockNode::start()?;
+        let mut resolver = get_resolver(&node);
+        let did = create_did(&mut resolver, r#"{"id":"first"}"#).await;
+
+        let result = resolver.resolve_did(&did).await?;
+        assert_eq!(
+            result.did_document,
+            Some(serde_json::json!({"id": "first"}))
+        );
+        assert_eq!(
+            result.did_document_metadata.version_id.as_deref(),
+            Some("0")
+        );
+        assert!(result.did_document_metadata.created_block.is_some());
+        assert_eq!(
+            result.did_document_metadata.created_block,
+            result.did_document_metadata.updated_block,
+        );
+        assert_eq!(
+            result.did_resolution_metadata.content_type.as_deref(),
+            Some(DID_DOCUMENT_CONTENT_TYPE),
+        );
+
+        match resolver.did_resolve(&did).await? {
+            VadePluginResultValue::Success(Some(serialized)) => {
+                let parsed: DidResolutionResult = serde_json::from_str(&serialized)?;
+                assert_eq!(parsed.did_document, result.did_document);
+            }
+            _ => panic!("expected DID resolution result"),
+        }
+
+        let options = serde_json::json!({
+            "type": DID_SUBSTRATE,
+            "privateKey": SIGNER_1_PRIVATE_KEY,
+            "identity": SIGNER_1_DID,
+            "operation": "setDidDocument",
+        });
+        resolver
+            .did_update(&did, &options.to_string(), r#"{"id":"second"}"#)
+            .await?;
+        let updated = resolver.resolve_did(&did).await?;
+        assert_eq!(
+            updated.did_document,
+            Some(serde_json::json!({"id": "second"}))
+        );
+        assert_eq!(
+            updated.did_document_metadata.created_block,
+            result.did_document_metadata.created_block,
+        );
+        assert!(
+            updated.did_document_metadata.updated_block
+                > result.did_document_metadata.updated_block
+        );
+
+        // blocks are searched on chain, so they do not depend on the instance, that sent changes
+        let other = get_resolver(&node).resolve_did(&did).await?;
+        assert_eq!(
+            other.did_document_metadata.created_block,
+            updated.did_document_metadata.created_block,
+        );
+        assert_eq!(
+            other.did_document_metadata.updated_block,
+            updated.did_document_metadata.updated_block,
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_create_update_and_batch_with_metadata_fixtures() -> Result<(), Box<dyn Error>> {
+        for metadata in &[METADATA_V12, METADATA_V13, METADATA_V14] {
+            let node = MockNode::start_with_metadata(metadata)?;
+            let mut resolver = get_resolver(&node);
+            let did = create_did(&mut resolver, r#"{"id":"first"}"#).await;
+            let options = serde_json::json!({
+                "type": DID_SUBSTRATE,
+                "privateKey": SIGNER_1_PRIVATE_KEY,
+                "identity": SIGNER_1_DID,
+                "operation": "setDidDocument",
+            });
+            resolver
+                .did_update(&did, &options.to_string(), r#"{"id":"second"}"#)
+                .await?;
+            let result = resolver.resolve_did(&did).await?;
+            assert_eq!(
+                result.did_document,
+                Some(serde_json::json!({"id": "second"}))
+            );
+            assert!(result.did_document_metadata.created_block.is_some());
+            assert!(
+                result.did_document_metadata.updated_block
+                    > result.did_document_metadata.created_block
+            );
+
+            // `batch_all` is the third call of `Utility` in all fixtures
+            let results = resolver
+                .batch(
+                    SIGNER_1_PRIVATE_KEY,
+                    &[BatchOperation::AddDidDocument {
+                        did: did.clone(),
+                        identity: SIGNER_1_DID.to_string(),
+                        document: r#"{"id":"third"}"#.to_string(),
+                    }],
+                    true,
+                )
+                .await?;
+            assert!(matches!(results[..], [Ok(None)]));
+            let batched = resolver.resolve_did(&did).await?;
+            assert_eq!(
+                batched.did_document,
+                Some(serde_json::json!({"id": "third"}))
+            );
+            assert!(
+                batched.did_document_metadata.updated_block
+                    > result.did_document_metadata.updated_block
+            );
+
+            let options = serde_json::json!({
+                "type": DID_SUBSTRATE,
+                "privateKey": SIGNER_1_PRIVATE_KEY,
+                "identity": SIGNER_1_DID,
+                "operation": "deactivateDid",
+            });
+            resolver.did_update(&did, &options.to_string(), "").await?;
+            let deactivated = resolver.resolve_did(&did).await?;
+            assert_eq!(deactivated.did_document_metadata.deactivated, Some(true));
+            assert!(deactivated.did_document.is_none());
+            assert_eq!(resolver.get_did_documents(&did).await?.len(), 3);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_watch_did_changes() -> Result<(), Box<dyn Error>> {
+        let node = MockNode::start()?;
+        let mut resolver = get_resolver(&node);
+        let mut all_changes = Box::pin(resolver.watch_dids("did:evan:testcore").await?);
+        let did = create_did(&mut resolver, r#"{"id":"first"}"#).await;
+        let mut did_changes = Box::pin(resolver.watch_did(&did).await?);
+        let other_did = create_did(&mut resolver, r#"{"id":"other"}"#).await;
+        let options = serde_json::json!({
+            "type": DID_SUBSTRATE,
+            "privateKey": SIGNER_1_PRIVATE_KEY,
+            "identity": SIGNER_1_DID,
+            "operation": "setDidDocument",
+        });
+        resolver
+            .did_update(&did, &options.to_string(), r#"{"id":"second"}"#)
+            .await?;
+
+        match all_changes.next().await {
+            Some(Ok(DidChange::Created { did: created, .. })) => assert_eq!(created, did),
+            change => panic!("expected creation of DID, got {:?}", change),
+        }
+        match all_changes.next().await {
+            Some(Ok(DidChange::Created { did: created, .. })) => assert_eq!(created, other_did),
+            change => panic!("expected creation of other DID, got {:?}", change),
+        }
+        match did_changes.next().await {
+            Some(Ok(DidChange::Updated {
+                did: updated,
+                index,
+                ..
+            })) => {
+                assert_eq!(updated, did);
+                assert_eq!(index, 0);
+            }
+            change => panic!("expected update of DID, got {:?}", change),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_deactivate_did() -> Result<(), Box<dyn Error>> {
+        let node = MockNode::start()?;
+        let mut resolver = get_resolver(&node);
+        let did = create_did(&mut resolver, r#"{"id":"first"}"#).await;
+        let (_, substrate_did) = convert_did_to_substrate_identity(&did)?;
+        let mut options = serde_json::json!({
+            "type": DID_SUBSTRATE,
+            "privateKey": SIGNER_1_PRIVATE_KEY,
+            "identity": SIGNER_1_DID,
+            "operation": "setDidDocument",
+        });
+        let tombstone = serde_json::to_string(&DidTombstone::new(&substrate_did))?;
+        let reserved = resolver
+            .did_update(&did, &options.to_string(), &tombstone)
+            .await;
+        assert!(reserved.is_err());
+        assert_eq!(resolver.get_did_documents(&did).await?.len(), 1);
+
+        options["operation"] = serde_json::json!("deactivateDid");
+        resolver.did_update(&did, &options.to_string(), "").await?;
+        let documents = resolver.get_did_documents(&did).await?;
+        assert_eq!(documents.len(), 2);
+        assert!(DidTombstone::is_tombstone_of(&documents[1], &substrate_did));
+
+        let result = resolver.resolve_did(&did).await?;
+        assert_eq!(result.did_document_metadata.deactivated, Some(true));
+        assert!(result.did_document.is_none());
+        let first = resolver
+            .resolve_did(&format!("{}?versionId=0", &did))
+            .await?;
+        assert_eq!(first.did_document, Some(serde_json::json!({"id": "first"})));
+        assert_eq!(first.did_document_metadata.deactivated, Some(true));
+
+        options["operation"] = serde_json::json!("setDidDocument");
+        let update = resolver
+            .did_update(&did, &options.to_string(), r#"{"id":"second"}"#)
+            .await;
+        match update.map_err(|err| err.downcast::<SubstrateError>()) {
+            Err(Ok(err)) => assert!(matches!(*err, SubstrateError::DidDeactivated(_))),
+            _ => panic!("expected update of deactivated DID to fail"),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_append_and_overwrite_did_documents() -> Result<(), Box<dyn Error>> {
+        let node = MockNode::start()?;
+        let mut resolver = get_resolver(&node);
+        let did = create_did(&mut resolver, r#"{"id":"first"}"#).await;
+        let mut options = serde_json::json!({
+            "type": DID_SUBSTRATE,
+            "privateKey": SIGNER_1_PRIVATE_KEY,
+            "identity": SIGNER_1_DID,
+            "operation": "setDidDocument",
+            "updateMode": "append",
+        });
+        for document in &[r#"{"id":"second"}"#, r#"{"id":"third"}"#] {
+            resolver
+                .did_update(&did, &options.to_string(), document)
+                .await?;
+        }
+        let appended = resolver.resolve_did(&did).await?;
+        assert_eq!(
+            appended.did_document,
+            Some(serde_json::json!({"id": "third"}))
+        );
+        assert_eq!(
+            appended.did_document_metadata.version_id.as_deref(),
+            Some("2")
+        );
+
+        // overwriting replaces the latest document, not the first one
+        options["updateMode"] = serde_json::json!("overwrite");
+        resolver
+            .did_update(&did, &options.to_string(), r#"{"id":"fourth"}"#)
+            .await?;
+        let documents = resolver
+            .get_did_documents(&did)
+            .await?
+            .iter()
+            .map(|document| serde_json::from_str(document))
+            .collect::<Result<Vec<Value>, _>>()?;
+        assert_eq!(
+            documents,
+            vec![
+                serde_json::json!({"id": "first"}),
+                serde_json::json!({"id": "second"}),
+                serde_json::json!({"id": "fourth"}),
+            ]
+        );
+        let overwritten = resolver.resolve_did(&did).await?;
+        assert_eq!(
+            overwritten.did_document,
+            Some(serde_json::json!({"id": "fourth"}))
+        );
+        assert_eq!(
+            overwritten.did_document_metadata.version_id.as_deref(),
+            Some("2")
+        );
+        let first = resolver
+            .resolve_did(&format!("{}?versionId=0", &did))
+            .await?;
+        assert_eq!(first.did_document, Some(serde_json::json!({"id": "first"})));
+
+        Ok(())
+    }
+
+    #[test]
+    fn takes_submission_from_options() {
+        assert_eq!(
+            Submission::from_options(None, false),
+            Submission::Wait(XtStatus::InBlock)
+        );
+        assert_eq!(
+            Submission::from_options(Some(XtStatus::Finalized), false),
+            Submission::Wait(XtStatus::Finalized)
+        );
+        assert_eq!(
+            Submission::from_options(Some(XtStatus::Finalized), true),
+            Submission::Async
+        );
+    }
+
+    #[test]
+    fn validates_tombstones_structurally() {
+        let did = "0x0D87204C3957D73B68AE28D0AF961D3C72403906";
+        let tombstone = serde_json::to_string(&DidTombstone::new(did)).unwrap();
+        assert!(DidTombstone::is_tombstone_of(&tombstone, did));
+        assert!(DidTombstone::is_tombstone_of(
+            &tombstone,
+            &did.trim_start_matches("0x").to_lowercase()
+        ));
+        assert!(check_did_document(&tombstone).is_err());
+
+        let lookalikes = vec![
+            // former deactivation payload is a regular document now
+            r#"{"deactivated":true}"#.to_string(),
+            serde_json::json!({ "type": DID_TOMBSTONE_TYPE }).to_string(),
+            serde_json::json!({ "type": "DidTombstone", "did": did }).to_string(),
+            serde_json::json!({ "type": DID_TOMBSTONE_TYPE, "did": "0x1234" }).to_string(),
+            serde_json::json!({
+                "type": DID_TOMBSTONE_TYPE,
+                "did": did,
+                "service": [],
+            })
+            .to_string(),
+        ];
+        for lookalike in &lookalikes {
+            assert!(!DidTombstone::is_tombstone_of(lookalike, did));
+        }
+        assert!(check_did_document(&lookalikes[0]).is_ok());
+        assert!(check_did_document(&lookalikes[2]).is_ok());
+        // documents with the tombstone type are reserved, even if they are no valid tombstones
+        assert!(check_did_document(&lookalikes[1]).is_err());
+        assert!(check_did_document(&lookalikes[4]).is_err());
+    }
+
+    #[tokio::test]
+    async fn can_batch_did_operations() -> Result<(), Box<dyn Error>> {
+        let node = MockNode::start()?;
+        let mut resolver = get_resolver(&node);
+        let did = create_did(&mut resolver, r#"{"id":"first"}"#).await;
+        let operations = vec![
+            BatchOperation::WhitelistIdentity {
+                identity: SIGNER_1_DID.to_string(),
+            },
+            BatchOperation::CreateDid {
+                identity: SIGNER_1_DID.to_string(),
+                document: Some(r#"{"id":"batched"}"#.to_string()),
+            },
+            BatchOperation::AddDidDocument {
+                did: did.clone(),
+                identity: SIGNER_1_DID.to_string(),
+                document: r#"{"id":"second"}"#.to_string(),
+            },
+        ];
+
+        let results = resolver
+            .batch(SIGNER_1_PRIVATE_KEY, &operations, false)
+            .await?;
+        assert_eq!(results.len(), 3);
+        let created = match &results[1] {
+            Ok(Some(created)) => created.clone(),
+            _ => panic!("expected DID to be created"),
+        };
+        assert!(created.starts_with("did:evan:testcore:0x"));
+        assert_eq!(
+            resolver.resolve_did(&created).await?.did_document,
+            Some(serde_json::json!({"id": "batched"}))
+        );
+        assert_eq!(
+            resolver.resolve_did(&did).await?.did_document,
+            Some(serde_json::json!({"id": "second"}))
+        );
+
+        node.fail_next_batch_call(1, 2);
+        let results = resolver
+            .batch(SIGNER_1_PRIVATE_KEY, &operations, false)
+            .await?;
+        assert!(matches!(results[0], Ok(None)));
+        match &results[1] {
+            Err(SubstrateError::DispatchModule { name, .. }) => assert_eq!(name, "NotDidOwner"),
+            _ => panic!("expected second operation to fail"),
+        }
+        assert!(results[2].is_err());
+
+        node.fail_next_batch_call(2, 2);
+        assert!(resolver
+            .batch(SIGNER_1_PRIVATE_KEY, &operations, true)
+            .await
+            .is_err());
+        assert_eq!(resolver.get_did_documents(&did).await?.len(), 2);
+
+        let mut unsigned = get_resolver(&node);
+        unsigned.client.extrinsic_signer = None;
+        let unsigned = unsigned
+            .batch(SIGNER_1_PRIVATE_KEY, &operations, true)
+            .await;
+        match unsigned.map_err(|err| err.downcast::<SubstrateError>()) {
+            Err(Ok(err)) => assert!(matches!(*err, SubstrateError::Signer(_))),
+            _ => panic!("expected batch without extrinsic signer to fail"),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_create_multiple_dids() -> Result<(), Box<dyn Error>> {
+        let node = MockNode::start()?;
+        let mut resolver = get_resolver(&node);
+        let documents: Vec<String> = (0..5).map(|i| format!(r#"{{"id":"{}"}}"#, i)).collect();
+
+        let results = resolver
+            .create_dids(SIGNER_1_PRIVATE_KEY, SIGNER_1_DID, 5, Some(&documents), 2)
+            .await?;
+        assert_eq!(results.len(), 5);
+        for (result, document) in results.iter().zip(&documents) {
+            let did = result.as_ref().expect("expected DID to be created");
+            assert!(did.starts_with("did:evan:testcore:0x"));
+            assert_eq!(
+                resolver.resolve_did(did).await?.did_document,
+                Some(serde_json::from_str::<Value>(document)?)
+            );
+        }
+        let mut created: Vec<String> = results.into_iter().filter_map(Result::ok).collect();
+        let mut listed = resolver.get_dids("did:evan:testcore").await?;
+        created.sort();
+        listed.sort();
+        assert_eq!(listed, created);
+
+        node.fail_next_extrinsic(2);
+        let results = resolver
+            .create_dids(SIGNER_1_PRIVATE_KEY, SIGNER_1_DID, 3, None, 3)
+            .await?;
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 2);
+        assert!(results.iter().any(|result| matches!(
+            result,
+            Err(SubstrateError::DispatchModule { name, .. }) if name == "NotDidOwner"
+        )));
+
+        assert!(resolver
+            .create_dids(SIGNER_1_PRIVATE_KEY, SIGNER_1_DID, 2, Some(&documents), 2)
+            .await
+            .is_err());
+
+        // events of blocks, whose notifications have been dropped, are fetched after a timeout
+        resolver.client.timeouts.events = std::time::Duration::from_secs(1);
+        node.drop_next_storage_notifications(1);
+        let results = resolver
+            .create_dids(SIGNER_1_PRIVATE_KEY, SIGNER_1_DID, 2, None, 1)
+            .await?;
+        assert!(results.iter().all(Result::is_ok));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_wait_for_finalized_extrinsics() -> Result<(), Box<dyn Error>> {
+        let node = MockNode::start()?;
+        let mut resolver = get_resolver(&node);
+        let mut options = serde_json::json!({
+            "type": DID_SUBSTRATE,
+            "privateKey": SIGNER_1_PRIVATE_KEY,
+            "identity": SIGNER_1_DID,
+            "waitFor": "finalized",
+        });
+        let did = match resolver
+            .did_create(
+                "did:evan:testcore",
+                &options.to_string(),
+                r#"{"id":"first"}"#,
+            )
+            .await?
+        {
+            VadePluginResultValue::Success(Some(did)) => serde_json::from_str::<String>(&did)?,
+            _ => panic!("could not create DID"),
+        };
+
+        options["operation"] = serde_json::json!("setDidDocument");
+        node.fail_next_extrinsic(2);
+        let result = resolver
+            .did_update(&did, &options.to_string(), r#"{"id":"second"}"#)
+            .await;
+        match result {
+            Err(err) => assert!(matches!(
+                err.downcast_ref::<SubstrateError>(),
+                Some(SubstrateError::DispatchModule { name, .. }) if name == "NotDidOwner"
+            )),
+            _ => panic!("expected update to fail"),
+        }
+
+        options["waitFor"] = serde_json::json!("future");
+        assert!(resolver
+            .did_update(&did, &options.to_string(), r#"{"id":"second"}"#)
+            .await
+            .is_err());
+        assert_eq!(
+            resolver.resolve_did(&did).await?.did_document,
+            Some(serde_json::json!({"id": "first"}))
+        );
+
+        Ok(())
+    }
+
+    async fn wait_for_final_status(
+        resolver: &VadeEvanSubstrate,
+        transaction: &str,
+    ) -> Result<TransactionStatus, Box<dyn Error>> {
+        let transaction: SubmittedTransaction = serde_json::from_str(transaction)?;
+        for _ in 0..100 {
+            match resolver
+                .get_transaction_status(&transaction.transaction_hash)
+                .await?
+            {
+                status @ TransactionStatus::Finalized { .. }
+                | status @ TransactionStatus::Failed { .. } => return Ok(status),
+                _ => futures_timer::Delay::new(std::time::Duration::from_millis(50)).await,
+            }
+        }
+        Err(Box::from("transaction has not been finalized"))
+    }
+
+    #[tokio::test]
+    async fn can_submit_extrinsics_without_waiting() -> Result<(), Box<dyn Error>> {
+        let node = MockNode::start()?;
+        let mut resolver = get_resolver(&node);
+        let did = create_did(&mut resolver, r#"{"id":"first"}"#).await;
+        let options = serde_json::json!({
+            "type": DID_SUBSTRATE,
+            "privateKey": SIGNER_1_PRIVATE_KEY,
+            "identity": SIGNER_1_DID,
+            "operation": "setDidDocument",
+            "asyncSubmit": true,
+        });
+
+        let transaction = match resolver
+            .did_update(&did, &options.to_string(), r#"{"id":"second"}"#)
+            .await?
+        {
+            VadePluginResultValue::Success(Some(transaction)) => transaction,
+            _ => panic!("expected transaction to be returned"),
+        };
+        assert!(matches!(
+            wait_for_final_status(&resolver, &transaction).await?,
+            TransactionStatus::Finalized { .. }
+        ));
+        // transactions are not tracked anymore, once their final status has been reported
+        let submitted: SubmittedTransaction = serde_json::from_str(&transaction)?;
+        assert!(resolver
+            .get_transaction_status(&submitted.transaction_hash)
+            .await
+            .is_err());
+        assert_eq!(
+            resolver.resolve_did(&did).await?.did_document,
+            Some(serde_json::json!({"id": "second"}))
+        );
+
+        node.fail_next_extrinsic(2);
+        let transaction = match resolver
+            .did_update(&did, &options.to_string(), r#"{"id":"third"}"#)
+            .await?
+        {
+            VadePluginResultValue::Success(Some(transaction)) => transaction,
+            _ => panic!("expected transaction to be returned"),
+        };
+        match wait_for_final_status(&resolver, &transaction).await? {
+            TransactionStatus::Failed {
+                block_hash: Some(_),
+                error,
+            } => assert!(error.contains("NotDidOwner")),
+            status => panic!("expected transaction to fail, got {:?}", status),
+        }
+
+        Ok(())
+    }
+
+    /// Resolves a DID URL with a resolver, that returns plain DID documents.
+    async fn resolve_document(
+        resolver: &mut VadeEvanSubstrate,
+        did_url: &str,
+    ) -> Result<Value, Box<dyn Error>> {
+        match resolver.did_resolve(did_url).await? {
+            VadePluginResultValue::Success(Some(document)) => Ok(serde_json::from_str(&document)?),
+            _ => Err(Box::from(format!("could not resolve {}", did_url))),
+        }
+    }
+
+    #[tokio::test]
+    async fn resolves_plain_did_documents_at_past_blocks() -> Result<(), Box<dyn Error>> {
+        let node = MockNode::start()?;
+        let mut resolver = get_resolver(&node);
+        resolver.config.resolution_result = false;
+        let did = create_did(&mut resolver, r#"{"id":"first"}"#).await;
+        let block_hash = get_block_hash(&resolver.client, None)
+            .await?
+            .ok_or("could not get latest block")?;
+        let block_time: u64 = get_storage_value(
+            &resolver.client,
+            &resolver.client.get_metadata().await?,
+            "Timestamp",
+            "Now",
+            Some(&block_hash),
+        )
+        .await?
+        .ok_or("could not get block time")?;
+        let version_time = Utc
+            .timestamp_millis_opt(block_time as i64)
+            .single()
+            .ok_or("invalid block time")?
+            .to_rfc3339_opts(SecondsFormat::Millis, true);
+
+        let mut options = serde_json::json!({
+            "type": DID_SUBSTRATE,
+            "privateKey": SIGNER_1_PRIVATE_KEY,
+            "identity": SIGNER_1_DID,
+            "operation": "setDidDocument",
+        });
+        resolver
+            .did_update(&did, &options.to_string(), r#"{"id":"second"}"#)
+            .await?;
+        let first = serde_json::json!({"id": "first"});
+        assert_eq!(
+            resolve_document(&mut resolver, &did).await?,
+            serde_json::json!({"id": "second"})
+        );
+        let at_block = format!("{}?blockHash={}", &did, &block_hash);
+        assert_eq!(resolve_document(&mut resolver, &at_block).await?, first);
+        let at_time = format!("{}?versionTime={}", &did, &version_time);
+        assert_eq!(resolve_document(&mut resolver, &at_time).await?, first);
+
+        options["operation"] = serde_json::json!("deactivateDid");
+        resolver.did_update(&did, &options.to_string(), "").await?;
+        for did_url in &[did.clone(), format!("{}?versionId=1", &did)] {
+            match resolver
+                .did_resolve(did_url)
+                .await
+                .map_err(|err| err.downcast::<SubstrateError>())
+            {
+                Err(Ok(err)) => assert!(matches!(*err, SubstrateError::DidDeactivated(_))),
+                _ => panic!("expected resolution of {} to fail", did_url),
+            }
+        }
+        // DID has not been deactivated at past blocks
+        assert_eq!(resolve_document(&mut resolver, &at_block).await?, first);
+        assert_eq!(resolve_document(&mut resolver, &at_time).await?, first);
+        assert_eq!(
+            resolve_document(&mut resolver, &format!("{}?versionId=0", &did)).await?,
+            first
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn returns_resolution_errors() -> Result<(), Box<dyn Error>> {
+        let node = MockNode::start()?;
+        let resolver = get_resolver(&node);
+
+        let not_found = resolver
+            .resolve_did("did:evan:testcore:0x0000000000000000000000000000000000000000000000000000000000001234")
+            .await?;
+        assert_eq!(
+            not_found.did_resolution_metadata.error.as_deref(),
+            Some("notFound")
+        );
+        assert!(not_found.did_document.is_none());
+
+        let invalid = resolver.resolve_did("did:evan:unknown:0x1234").await?;
+        assert_eq!(
+            invalid.did_resolution_metadata.error.as_deref(),
+            Some("invalidDid")
+        );
+
+        Ok(())
+    }
+}